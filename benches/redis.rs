@@ -0,0 +1,49 @@
+/*
+Benchmark `cache_get` and `cache_set` against a local redis. Gets on a cache without `refresh` are
+sent as a single `GET`, while refreshing caches pipeline the `GET` with an `EXPIRE`.
+Start a redis docker image if you don't already have it running locally:
+    docker run --rm --name cached-redis-bench -p 6379:6379 -d redis
+Set the required env variable and run the benchmarks with the required features:
+    CACHED_REDIS_CONNECTION_STRING=redis://127.0.0.1:6379 cargo bench --bench redis --features "async redis_store redis_tokio"
+Cleanup the redis docker container:
+    docker rm -f cached-redis-bench
+ */
+
+use cached::{IOCached, RedisCache};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn build(refresh: bool) -> RedisCache<u32, u32> {
+    RedisCache::new("cache-redis-bench", 60)
+        .set_refresh(refresh)
+        .build()
+        .expect("error building bench redis cache")
+}
+
+fn get(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get");
+    for (name, refresh) in [("plain", false), ("refresh", true)] {
+        let cache = build(refresh);
+        cache.cache_set(1, 1).unwrap();
+        group.bench_function(name, |b| b.iter(|| cache.cache_get(black_box(&1)).unwrap()));
+    }
+    let cache = build(false);
+    cache.cache_remove(&2).unwrap();
+    group.bench_function("miss", |b| {
+        b.iter(|| cache.cache_get(black_box(&2)).unwrap())
+    });
+    group.finish();
+}
+
+fn set(c: &mut Criterion) {
+    let cache = build(false);
+    let mut i = 0u32;
+    c.bench_function("set", |b| {
+        b.iter(|| {
+            i = i.wrapping_add(1);
+            cache.cache_set(black_box(i % 1000), i).unwrap()
+        })
+    });
+}
+
+criterion_group!(benches, get, set);
+criterion_main!(benches);
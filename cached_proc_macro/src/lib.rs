@@ -52,7 +52,7 @@ struct MacroArgs {
 ///   specified, `create` must also be specified.
 /// - `create`: (optional, string expr) specify an expression used to create a new cache store, e.g. `create = r##"{ CacheType::new() }"##`.
 /// - `key`: (optional, string type) specify what type to use for the cache key, e.g. `type = "TimedCached<u32, u32>"`.
-///    When `key` is specified, `convert` must also be specified.
+///   When `key` is specified, `convert` must also be specified.
 /// - `convert`: (optional, string expr) specify an expression used to convert function arguments to a cache
 ///   key, e.g. `convert = r##"{ format!("{}:{}", arg1, arg2) }"##`. When `convert` is specified,
 ///   `key` or `type` must also be set.
@@ -926,12 +926,38 @@ struct IOMacroArgs {
     #[darling(default)]
     cache_prefix_block: Option<String>,
     #[darling(default)]
+    prefix: Option<String>,
+    #[darling(default)]
     name: Option<String>,
     #[darling(default)]
     time: Option<u64>,
     #[darling(default)]
+    ttl: Option<u64>,
+    #[darling(default)]
     time_refresh: Option<bool>,
     #[darling(default)]
+    connection_string: Option<String>,
+    #[darling(default)]
+    fallback_prefix: Option<String>,
+    #[darling(default)]
+    env_namespace_from: Option<String>,
+    #[darling(default)]
+    ignore_set_errors: Option<bool>,
+    #[darling(default)]
+    lifespan_jitter: Option<u64>,
+    #[darling(default)]
+    hash_keys: Option<bool>,
+    #[darling(default)]
+    max_keys: Option<u64>,
+    #[darling(default)]
+    max_read_bytes: Option<usize>,
+    #[darling(default)]
+    checksum: Option<bool>,
+    #[darling(default)]
+    soft_remove: Option<u64>,
+    #[darling(default)]
+    configure: Option<String>,
+    #[darling(default)]
     key: Option<String>,
     #[darling(default)]
     convert: Option<String>,
@@ -949,16 +975,28 @@ struct IOMacroArgs {
 /// - `name`: (optional, string) specify the name for the generated cache, defaults to the function name uppercase.
 /// - `redis`: (optional, bool) default to a `RedisCache` or `AsyncRedisCache`
 /// - `time`: (optional, u64) specify a cache TTL in seconds, implies the cache type is a `TimedCached` or `TimedSizedCache`.
+/// - `ttl`: (optional, u64) an alias of `time`.
 /// - `time_refresh`: (optional, bool) specify whether to refresh the TTL on cache hits.
 /// - `type`: (optional, string type) explicitly specify the cache store type to use.
 /// - `cache_prefix_block`: (optional, string expr) specify an expression used to create the string used as a
 ///   prefix for all cache keys of this function, e.g. `cache_prefix_block = r##"{ "my_prefix" }"##`.
+///   The prefix must not contain `:`.
 ///   When not specified, the cache prefix will be constructed from the name of the function. This
 ///   could result in unexpected conflicts between io_cached-functions of the same name so it's
 ///   recommended that you specify a prefix you're sure will be unique.
+/// - `prefix`: (optional, string) specify the cache prefix as a literal, e.g. `prefix = "my_prefix"`,
+///   instead of a `cache_prefix_block`.
+/// - `connection_string`, `fallback_prefix`, `env_namespace_from`, `ignore_set_errors`,
+///   `lifespan_jitter`, `hash_keys`, `max_keys`, `max_read_bytes`, `checksum`, `soft_remove`:
+///   (optional) passed to the redis cache builder's `set_*` method of the same name, e.g.
+///   `connection_string = "redis://127.0.0.1:6379"` or `max_keys = 1000`. Only allowed with `redis`.
+/// - `configure`: (optional, string closure) specify a closure taking and returning the redis
+///   cache builder, applied after the other builder attributes, to set any other builder option,
+///   e.g. `configure = r##"|b| b.set_key_normalizer(cached::Normalization::Lowercase)"##`.
+///   Only allowed with `redis`.
 /// - `create`: (optional, string expr) specify an expression used to create a new cache store, e.g. `create = r##"{ CacheType::new() }"##`.
 /// - `key`: (optional, string type) specify what type to use for the cache key, e.g. `type = "TimedCached<u32, u32>"`.
-///    When `key` is specified, `convert` must also be specified.
+///   When `key` is specified, `convert` must also be specified.
 /// - `convert`: (optional, string expr) specify an expression used to convert function arguments to a cache
 ///   key, e.g. `convert = r##"{ format!("{}:{}", arg1, arg2) }"##`. When `convert` is specified,
 ///   `key` or `type` must also be set.
@@ -1154,12 +1192,66 @@ pub fn io_cached(args: TokenStream, input: TokenStream) -> TokenStream {
         (None, Some(_), None) => panic!("convert requires key or type to be set"),
     };
 
+    let time = match (args.time, args.ttl) {
+        (Some(_), Some(_)) => panic!("cannot specify both `time` and its alias `ttl`"),
+        (time, ttl) => time.or(ttl),
+    };
+    let cache_prefix_block = match (&args.cache_prefix_block, &args.prefix) {
+        (Some(_), Some(_)) => panic!("cannot specify both `cache_prefix_block` and `prefix`"),
+        (Some(block), None) => Some(block.clone()),
+        (None, Some(prefix)) => Some(format!("{{ {:?} }}", prefix)),
+        (None, None) => None,
+    };
+
+    // redis builder options, in the order they're applied
+    let mut builder_setters = Vec::new();
+    if let Some(connection_string) = &args.connection_string {
+        builder_setters.push(quote! { .set_connection_string(#connection_string) });
+    }
+    if let Some(fallback_prefix) = &args.fallback_prefix {
+        builder_setters.push(quote! { .set_fallback_prefix(#fallback_prefix) });
+    }
+    if let Some(env_namespace_from) = &args.env_namespace_from {
+        builder_setters.push(quote! { .set_env_namespace_from(#env_namespace_from) });
+    }
+    if let Some(ignore_set_errors) = args.ignore_set_errors {
+        builder_setters.push(quote! { .set_ignore_set_errors(#ignore_set_errors) });
+    }
+    if let Some(lifespan_jitter) = args.lifespan_jitter {
+        builder_setters.push(quote! { .set_lifespan_jitter(#lifespan_jitter) });
+    }
+    if let Some(hash_keys) = args.hash_keys {
+        builder_setters.push(quote! { .set_hash_keys(#hash_keys) });
+    }
+    if let Some(max_keys) = args.max_keys {
+        builder_setters.push(quote! { .set_max_keys(#max_keys) });
+    }
+    if let Some(max_read_bytes) = args.max_read_bytes {
+        builder_setters.push(quote! { .set_max_read_bytes(#max_read_bytes) });
+    }
+    if let Some(checksum) = args.checksum {
+        builder_setters.push(quote! { .set_checksum(#checksum) });
+    }
+    if let Some(soft_remove) = args.soft_remove {
+        builder_setters.push(quote! { .set_soft_remove(#soft_remove) });
+    }
+    let configure = args.configure.as_ref().map(|configure| {
+        parse_str::<ExprClosure>(configure).expect("unable to parse configure closure")
+    });
+    let builder_options_set = !builder_setters.is_empty() || configure.is_some();
+    if builder_options_set && !args.redis {
+        panic!("redis cache builder attributes require `redis = true`");
+    }
+    if let Some(time_refresh) = args.time_refresh {
+        builder_setters.insert(0, quote! { .set_refresh(#time_refresh) });
+    }
+
     // make the cache type and create statement
     let (cache_ty, cache_create) = match (
         &args.redis,
-        &args.time,
+        &time,
         &args.time_refresh,
-        &args.cache_prefix_block,
+        &cache_prefix_block,
         &args.cache_type,
         &args.cache_create,
     ) {
@@ -1180,8 +1272,12 @@ pub fn io_cached(args: TokenStream, input: TokenStream) -> TokenStream {
             };
             let cache_create = match cache_create {
                 Some(cache_create) => {
-                    if time.is_some() || time_refresh.is_some() || cache_prefix.is_some() {
-                        panic!("cannot specify `time`, `time_refresh`, or `cache_prefix` when passing `create block");
+                    if time.is_some()
+                        || time_refresh.is_some()
+                        || cache_prefix.is_some()
+                        || builder_options_set
+                    {
+                        panic!("cannot specify `time`, `time_refresh`, `cache_prefix`, or builder attributes when passing `create block");
                     } else {
                         let cache_create = parse_str::<Block>(cache_create.as_ref())
                             .expect("unable to parse cache create block");
@@ -1201,28 +1297,29 @@ pub fn io_cached(args: TokenStream, input: TokenStream) -> TokenStream {
                         let cache_prefix = if let Some(cp) = cache_prefix {
                             cp.to_string()
                         } else {
-                            format!(" {{ \"cached::proc_macro::io_cached::{}\" }}", cache_ident)
+                            format!(" {{ \"cached_proc_macro_io_cached_{}\" }}", cache_ident)
                         };
                         let cache_prefix = parse_str::<Block>(cache_prefix.as_ref())
                             .expect("unable to parse cache_prefix_block");
-                        match time_refresh {
-                            Some(time_refresh) => {
-                                if asyncness.is_some() {
-                                    quote! { cached::AsyncRedisCache::new(#cache_prefix, #time).set_refresh(#time_refresh).build().await.expect("error constructing AsyncRedisCache in #[io_cached] macro") }
-                                } else {
-                                    quote! {
-                                        cached::RedisCache::new(#cache_prefix, #time).set_refresh(#time_refresh).build().expect("error constructing RedisCache in #[io_cached] macro")
-                                    }
-                                }
-                            }
-                            None => {
-                                if asyncness.is_some() {
-                                    quote! { cached::AsyncRedisCache::new(#cache_prefix, #time).build().await.expect("error constructing AsyncRedisCache in #[io_cached] macro") }
-                                } else {
-                                    quote! {
-                                        cached::RedisCache::new(#cache_prefix, #time).build().expect("error constructing RedisCache in #[io_cached] macro")
-                                    }
+                        let builder = if asyncness.is_some() {
+                            quote! { cached::AsyncRedisCache::new(#cache_prefix, #time)#(#builder_setters)* }
+                        } else {
+                            quote! { cached::RedisCache::new(#cache_prefix, #time)#(#builder_setters)* }
+                        };
+                        let builder = match &configure {
+                            Some(configure) => quote! {{
+                                fn configure<B, F: FnOnce(B) -> B>(builder: B, f: F) -> B {
+                                    f(builder)
                                 }
+                                configure(#builder, #configure)
+                            }},
+                            None => builder,
+                        };
+                        if asyncness.is_some() {
+                            quote! { #builder.build().await.expect("error constructing AsyncRedisCache in #[io_cached] macro") }
+                        } else {
+                            quote! {
+                                #builder.build().expect("error constructing RedisCache in #[io_cached] macro")
                             }
                         }
                     }
@@ -0,0 +1,36 @@
+/*
+Compare the latency of `cache_get` with and without r2d2's connection check on checkout.
+Start a redis docker image if you don't already have it running locally:
+    docker run --rm --name cached-redis-example -p 6379:6379 -d redis
+Set the required env variable and run this example with the required features:
+    CACHED_REDIS_CONNECTION_STRING=redis://127.0.0.1:6379 cargo run --release --example redis_checkout --features "async redis_store redis_tokio"
+Cleanup the redis docker container:
+    docker rm -f cached-redis-example
+ */
+
+use cached::{IOCached, RedisCache};
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 10_000;
+
+fn time_gets(test_on_check_out: bool) -> Duration {
+    let cache: RedisCache<u32, u32> = RedisCache::new("cache-redis-example-checkout", 60)
+        .set_test_on_check_out(test_on_check_out)
+        .build()
+        .expect("error building example redis cache");
+    cache.cache_set(1, 1).unwrap();
+
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        cache.cache_get(&1).unwrap();
+    }
+    started.elapsed() / ITERATIONS
+}
+
+fn main() {
+    // warm up both the server and the local connection
+    time_gets(true);
+
+    println!("with test_on_check_out: {:?} per get", time_gets(true));
+    println!("without test_on_check_out: {:?} per get", time_gets(false));
+}
@@ -25,6 +25,11 @@ See [`cached::stores` docs](stores) cache stores available.
 - `redis_store`: Include Redis cache store
 - `redis_async_std`: Include async Redis support using `async-std` and `async-std` tls support
 - `redis_tokio`: Include async Redis support using `tokio` and `tokio` tls support
+- `chaos`: Include fault injection hooks on the Redis stores for testing failure handling
+- `redis_zstd`: Include zstd compression of values in the Redis stores
+- `redis_gzip`: Include gzip compression of values in the Redis stores
+- `redis_bb8`: Include a `bb8` connection pool option for the async Redis store
+- `redis_encryption`: Include AES-GCM encryption of values in the Redis stores
 
 
 This crate provides procedural and declarative macros, in `cached::proc_macro` and `cached::macros`.
@@ -174,11 +179,22 @@ pub mod macros;
 pub mod proc_macro;
 pub mod stores;
 
+#[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+pub use stores::CompressionKind;
+#[cfg(feature = "chaos")]
+pub use stores::FaultInjector;
 #[cfg(any(feature = "redis_async_std", feature = "redis_tokio"))]
-pub use stores::AsyncRedisCache;
+pub use stores::{AsyncCachePipeline, AsyncRedisCache, KeyEvent};
 #[cfg(feature = "redis_store")]
-pub use stores::{RedisCache, RedisCacheError};
-pub use stores::{SizedCache, TimedCache, TimedSizedCache, UnboundCache};
+pub use stores::{
+    BuildDiagnostics, CacheEvent, CacheOp, CachePipeline, CacheStats, CompositeKey, ConfigWarning,
+    ConnectionEvent, ConnectionObserver, DiagnosticCheck, EntryMeta, ExpireOption, GetOutcome,
+    InsertError, JsonOptions, KeyHasher, NonFiniteFloats, Normalization, PipelineReply,
+    RedisBytesCache, RedisCache, RedisCacheBuildError, RedisCacheError, RedisCacheLoadError,
+    RedisListCache, RedisStreamCache, ResiliencePolicy, ShardedRedisCache, StatsSnapshot,
+    TaggedCodec, TtlFn, ValueCodec,
+};
+pub use stores::{NoopCache, SizedCache, TimedCache, TimedSizedCache, UnboundCache};
 
 #[cfg(feature = "proc_macro")]
 pub use async_mutex;
@@ -262,6 +278,10 @@ pub trait CachedAsync<K, V> {
 }
 
 /// Cache operations on an io-connected store
+///
+/// The trait is object safe, so stores sharing an error type, like a `RedisCache` and a
+/// `NoopCache<K, V, RedisCacheError>`, can be selected at runtime behind a
+/// `Box<dyn IOCached<K, V, Error = E>>`.
 pub trait IOCached<K, V> {
     type Error;
 
@@ -288,6 +308,10 @@ pub trait IOCached<K, V> {
     }
 }
 
+/// Cache operations on an io-connected store, asynchronously
+///
+/// Like `IOCached`, the trait is object safe, for use behind a
+/// `Box<dyn IOCachedAsync<K, V, Error = E> + Send + Sync>`.
 #[cfg(feature = "async")]
 #[async_trait]
 pub trait IOCachedAsync<K, V> {
@@ -8,6 +8,7 @@ use std::hash::Hash;
 #[cfg(feature = "async")]
 use {super::CachedAsync, async_trait::async_trait, futures::Future};
 
+mod noop;
 #[cfg(feature = "redis_store")]
 mod redis;
 mod sized;
@@ -16,7 +17,22 @@ mod timed_sized;
 mod unbound;
 
 #[cfg(feature = "redis_store")]
-pub use crate::stores::redis::{RedisCache, RedisCacheError};
+#[doc(hidden)]
+pub use crate::stores::redis::redis_auto_prefix;
+#[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+pub use crate::stores::redis::CompressionKind;
+#[cfg(feature = "chaos")]
+pub use crate::stores::redis::FaultInjector;
+#[cfg(feature = "redis_store")]
+pub use crate::stores::redis::{
+    BuildDiagnostics, CacheEvent, CacheOp, CachePipeline, CacheStats, CompositeKey, ConfigWarning,
+    ConnectionEvent, ConnectionObserver, DiagnosticCheck, EntryMeta, ExpireOption, GetOutcome,
+    InsertError, JsonOptions, KeyHasher, NonFiniteFloats, Normalization, PipelineReply,
+    RedisBytesCache, RedisCache, RedisCacheBuildError, RedisCacheError, RedisCacheLoadError,
+    RedisListCache, RedisStreamCache, ResiliencePolicy, ShardedRedisCache, StatsSnapshot,
+    TaggedCodec, TtlFn, ValueCodec,
+};
+pub use noop::NoopCache;
 pub use sized::SizedCache;
 pub use timed::TimedCache;
 pub use timed_sized::TimedSizedCache;
@@ -27,7 +43,7 @@ pub use unbound::UnboundCache;
     feature = "redis_store",
     any(feature = "redis_async_std", feature = "redis_tokio")
 ))]
-pub use crate::stores::redis::AsyncRedisCache;
+pub use crate::stores::redis::{AsyncCachePipeline, AsyncRedisCache, KeyEvent};
 
 impl<K: Hash + Eq, V> Cached<K, V> for HashMap<K, V> {
     fn cache_get(&mut self, k: &K) -> Option<&V> {
@@ -0,0 +1,129 @@
+use crate::IOCached;
+use std::marker::PhantomData;
+
+#[cfg(feature = "async")]
+use {crate::IOCachedAsync, async_trait::async_trait};
+
+/// Cache store that stores nothing
+///
+/// Every `cache_get` misses and `cache_set` discards its value, so a `NoopCache` can stand in
+/// for an io-connected store to switch caching off at runtime, for example behind a
+/// `Box<dyn IOCached<K, V, Error = E>>`. The error type `E` is never returned and only needs
+/// to match the store being replaced.
+#[derive(Debug)]
+pub struct NoopCache<K, V, E = std::convert::Infallible> {
+    refresh: bool,
+    _phantom_k: PhantomData<K>,
+    _phantom_v: PhantomData<V>,
+    _phantom_e: PhantomData<E>,
+}
+
+impl<K, V, E> NoopCache<K, V, E> {
+    /// Creates a `NoopCache`
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> NoopCache<K, V, E> {
+        NoopCache {
+            refresh: false,
+            _phantom_k: PhantomData,
+            _phantom_v: PhantomData,
+            _phantom_e: PhantomData,
+        }
+    }
+}
+
+impl<K, V, E> Clone for NoopCache<K, V, E> {
+    fn clone(&self) -> Self {
+        NoopCache {
+            refresh: self.refresh,
+            _phantom_k: PhantomData,
+            _phantom_v: PhantomData,
+            _phantom_e: PhantomData,
+        }
+    }
+}
+
+impl<K, V, E> IOCached<K, V> for NoopCache<K, V, E> {
+    type Error = E;
+
+    fn cache_get(&self, _key: &K) -> Result<Option<V>, E> {
+        Ok(None)
+    }
+
+    fn cache_set(&self, _key: K, _val: V) -> Result<Option<V>, E> {
+        Ok(None)
+    }
+
+    fn cache_remove(&self, _key: &K) -> Result<Option<V>, E> {
+        Ok(None)
+    }
+
+    fn cache_set_refresh(&mut self, refresh: bool) -> bool {
+        std::mem::replace(&mut self.refresh, refresh)
+    }
+}
+
+#[cfg(feature = "async")]
+#[async_trait]
+impl<K, V, E> IOCachedAsync<K, V> for NoopCache<K, V, E>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    E: Send + Sync,
+{
+    type Error = E;
+
+    async fn cache_get(&self, _key: &K) -> Result<Option<V>, E> {
+        Ok(None)
+    }
+
+    async fn cache_set(&self, _key: K, _val: V) -> Result<Option<V>, E> {
+        Ok(None)
+    }
+
+    async fn cache_remove(&self, _key: &K) -> Result<Option<V>, E> {
+        Ok(None)
+    }
+
+    fn cache_set_refresh(&mut self, refresh: bool) -> bool {
+        std::mem::replace(&mut self.refresh, refresh)
+    }
+}
+
+#[cfg(test)]
+/// Cache store tests
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_nothing() {
+        let mut c: NoopCache<u32, u32> = NoopCache::new();
+        assert_eq!(Ok(None), IOCached::cache_get(&c, &1));
+        assert_eq!(Ok(None), IOCached::cache_set(&c, 1, 100));
+        assert_eq!(Ok(None), IOCached::cache_get(&c, &1));
+        assert_eq!(Ok(None), IOCached::cache_remove(&c, &1));
+        assert!(!IOCached::cache_set_refresh(&mut c, true));
+        assert!(IOCached::cache_set_refresh(&mut c, false));
+        assert_eq!(None, IOCached::cache_lifespan(&c));
+    }
+
+    #[test]
+    fn boxed() {
+        let caches: Vec<Box<dyn IOCached<u32, u32, Error = String>>> =
+            vec![Box::new(NoopCache::new()), Box::new(NoopCache::new())];
+        for c in caches {
+            assert_eq!(Ok(None), c.cache_set(1, 100));
+            assert_eq!(Ok(None), c.cache_get(&1));
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn boxed_async() {
+        let caches: Vec<Box<dyn IOCachedAsync<u32, u32, Error = String> + Send + Sync>> =
+            vec![Box::new(NoopCache::new()), Box::new(NoopCache::new())];
+        for c in caches {
+            assert_eq!(Ok(None), c.cache_set(1, 100).await);
+            assert_eq!(Ok(None), c.cache_get(&1).await);
+        }
+    }
+}
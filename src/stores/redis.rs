@@ -1,38 +1,776 @@
 use crate::IOCached;
+use bytes::Bytes;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use std::fmt::Display;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::SyncSender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 pub struct RedisCacheBuilder<K, V> {
     seconds: u64,
+    max_list_len: usize,
+    max_stream_len: usize,
     refresh: bool,
+    negative_seconds: Option<u64>,
     prefix: String,
+    fallback_prefix: Option<String>,
+    name: Option<String>,
     connection_string: Option<String>,
+    env_namespace_from: Option<String>,
+    hash_tag: Option<String>,
+    ignore_set_errors: bool,
+    test_on_check_out: bool,
+    pool_max_size: Option<u32>,
+    pool_connection_timeout: Option<Duration>,
+    command_timeout: Option<Duration>,
+    fail_fast_on_exhaustion: bool,
+    pooled: bool,
+    event_sink: Option<SyncSender<CacheEvent>>,
+    connection_observer: Option<ConnectionObserver>,
+    write_wait: Option<(u32, Duration)>,
+    health_cache_ttl: Duration,
+    batch_chunk_size: Option<usize>,
+    soft_remove: Option<u64>,
+    lifespan_jitter: u64,
+    key_hasher: Option<KeyHasher>,
+    ttl_fn: Option<TtlFn<V>>,
+    key_normalization: Normalization,
+    max_keys: Option<u64>,
+    track_recency: bool,
+    value_chunk_size: Option<usize>,
+    refresh_expire_option: Option<ExpireOption>,
+    codec: Option<Arc<dyn ValueCodec<V>>>,
+    fallback_codec: Option<Option<Arc<dyn ValueCodec<V>>>>,
+    json_options: JsonOptions,
+    max_read_bytes: Option<usize>,
+    checksum: bool,
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    compression: Option<CompressionKind>,
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    compression_min_ratio: Option<f32>,
+    #[cfg(feature = "redis_zstd")]
+    compression_dictionary: Option<Vec<u8>>,
+    #[cfg(feature = "redis_encryption")]
+    encryption_key: Option<[u8; 32]>,
+    #[cfg(feature = "chaos")]
+    fault_injector: Option<FaultInjector>,
     _phantom_k: PhantomData<K>,
     _phantom_v: PhantomData<V>,
 }
 
+impl<K, V> Clone for RedisCacheBuilder<K, V> {
+    fn clone(&self) -> Self {
+        RedisCacheBuilder {
+            seconds: self.seconds,
+            max_list_len: self.max_list_len,
+            max_stream_len: self.max_stream_len,
+            refresh: self.refresh,
+            negative_seconds: self.negative_seconds,
+            prefix: self.prefix.clone(),
+            fallback_prefix: self.fallback_prefix.clone(),
+            name: self.name.clone(),
+            connection_string: self.connection_string.clone(),
+            env_namespace_from: self.env_namespace_from.clone(),
+            hash_tag: self.hash_tag.clone(),
+            ignore_set_errors: self.ignore_set_errors,
+            test_on_check_out: self.test_on_check_out,
+            pool_max_size: self.pool_max_size,
+            pool_connection_timeout: self.pool_connection_timeout,
+            command_timeout: self.command_timeout,
+            fail_fast_on_exhaustion: self.fail_fast_on_exhaustion,
+            pooled: self.pooled,
+            event_sink: self.event_sink.clone(),
+            connection_observer: self.connection_observer.clone(),
+            write_wait: self.write_wait,
+            health_cache_ttl: self.health_cache_ttl,
+            batch_chunk_size: self.batch_chunk_size,
+            soft_remove: self.soft_remove,
+            lifespan_jitter: self.lifespan_jitter,
+            key_hasher: self.key_hasher.clone(),
+            ttl_fn: self.ttl_fn.clone(),
+            key_normalization: self.key_normalization,
+            max_keys: self.max_keys,
+            track_recency: self.track_recency,
+            value_chunk_size: self.value_chunk_size,
+            refresh_expire_option: self.refresh_expire_option,
+            codec: self.codec.clone(),
+            fallback_codec: self.fallback_codec.clone(),
+            json_options: self.json_options,
+            max_read_bytes: self.max_read_bytes,
+            checksum: self.checksum,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: self.compression,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: self.compression_min_ratio,
+            #[cfg(feature = "redis_zstd")]
+            compression_dictionary: self.compression_dictionary.clone(),
+            #[cfg(feature = "redis_encryption")]
+            encryption_key: self.encryption_key,
+            #[cfg(feature = "chaos")]
+            fault_injector: self.fault_injector.clone(),
+            _phantom_k: PhantomData,
+            _phantom_v: PhantomData,
+        }
+    }
+}
+
 const ENV_KEY: &str = "CACHED_REDIS_CONNECTION_STRING";
+const ENV_PREFIX_KEY: &str = "CACHED_REDIS_PREFIX";
+const ENV_LIFESPAN_KEY: &str = "CACHED_REDIS_LIFESPAN";
+const ENV_REFRESH_KEY: &str = "CACHED_REDIS_REFRESH";
 const PREFIX_NAMESPACE: &str = "cached-redis-store";
+const KEY_SEPARATOR: char = ':';
+const DEFAULT_MAX_LIST_LEN: usize = 1000;
+const DEFAULT_MAX_STREAM_LEN: usize = 1000;
+/// The field holding the value in the entries of a `RedisStreamCache`
+const STREAM_VALUE_FIELD: &str = "value";
+const DEFAULT_HEALTH_CACHE_TTL: Duration = Duration::from_secs(1);
+/// Entries sent per pipeline by `warm` when no batch chunk size is set
+const DEFAULT_WARM_CHUNK_SIZE: usize = 1000;
+/// Keys requested per `SCAN`, and renamed per pipeline, by `RedisCache::migrate_prefix`
+const MIGRATE_BATCH_SIZE: usize = 1000;
+/// Index members requested per `SSCAN`, and checked per pipeline, by `RedisCache::cache_reconcile`
+const RECONCILE_BATCH_SIZE: usize = 1000;
+/// How many times `RedisCache::cache_update` runs before giving up on a contended key
+const UPDATE_MAX_ATTEMPTS: u32 = 16;
+/// Stored in place of a value by `cache_set_miss`. Values are always wrapped
+/// in a `CachedRedisValue`, so this can't collide with a real value.
+const NEGATIVE_ENTRY: &str = r#"{"negative":true}"#;
+/// Soft-removed values are stored as `{"removed":<the previous CachedRedisValue>}`
+const SOFT_REMOVED_START: &str = r#"{"removed":"#;
+/// Atomically soft-remove `KEYS[1]` for `ARGV[1]` seconds and return its previous value.
+/// Entries that aren't values, like negative entries, are deleted instead.
+const SOFT_REMOVE_SCRIPT: &str = r#"
+local v = redis.call('GET', KEYS[1])
+if not v or string.sub(v, 1, 11) == '{"removed":' then
+    return v
+end
+-- values are JSON, compressed with zstd (with or without a dictionary) or gzip, encrypted,
+-- or checksummed
+if string.sub(v, 1, 9) == '{"value":'
+    or string.sub(v, 1, 4) == '\40\181\47\253'
+    or string.sub(v, 1, 2) == '\31\139'
+    or string.sub(v, 1, 4) == '\0\67\67\1'
+    or string.sub(v, 1, 4) == '\0\67\68\1'
+    or string.sub(v, 1, 4) == '\0\67\69\1' then
+    redis.call('SET', KEYS[1], '{"removed":' .. v .. '}', 'EX', ARGV[1])
+else
+    redis.call('DEL', KEYS[1])
+end
+return v
+"#;
+/// Atomically get `KEYS[1]` and, if it exists, reset its TTL to `ARGV[1]` seconds. The parts of
+/// a chunk manifest, starting with `ARGV[2]`, are refreshed too, named by appending `ARGV[3]`
+/// and the part index to the key.
+const GET_AND_REFRESH_SCRIPT: &str = r#"
+local v = redis.call('GET', KEYS[1])
+if v then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+    if string.sub(v, 1, #ARGV[2]) == ARGV[2] then
+        local ok, manifest = pcall(cjson.decode, v)
+        if ok and type(manifest.chunks) == 'number' then
+            for index = 0, manifest.chunks - 1 do
+                redis.call('EXPIRE', KEYS[1] .. ARGV[3] .. index, ARGV[1])
+            end
+        end
+    end
+end
+return v
+"#;
+/// Atomically reset the TTL of `KEYS[1]` to `ARGV[1]` seconds, and of the parts of its chunk
+/// manifest like `GET_AND_REFRESH_SCRIPT`, returning whether it exists
+const TOUCH_SCRIPT: &str = r#"
+if redis.call('EXPIRE', KEYS[1], ARGV[1]) == 0 then
+    return 0
+end
+local v = redis.pcall('GET', KEYS[1])
+if type(v) == 'string' and string.sub(v, 1, #ARGV[2]) == ARGV[2] then
+    local ok, manifest = pcall(cjson.decode, v)
+    if ok and type(manifest.chunks) == 'number' then
+        for index = 0, manifest.chunks - 1 do
+            redis.call('EXPIRE', KEYS[1] .. ARGV[3] .. index, ARGV[1])
+        end
+    end
+end
+return 1
+"#;
+/// Reply with the TTL of `KEYS[1]` like `TTL`, treating negative entries, equal to `ARGV[1]`,
+/// and soft-removed entries, starting with `ARGV[2]`, as missing keys
+const LIVE_TTL_SCRIPT: &str = r#"
+local v = redis.pcall('GET', KEYS[1])
+if not v then
+    return -2
+end
+if type(v) == 'string' and (v == ARGV[1] or string.sub(v, 1, #ARGV[2]) == ARGV[2]) then
+    return -2
+end
+return redis.call('TTL', KEYS[1])
+"#;
+/// Atomically get `KEYS[1]` or, if it's missing, move `KEYS[2]` to `KEYS[1]` and return its value
+const GET_WITH_FALLBACK_SCRIPT: &str = r#"
+local v = redis.call('GET', KEYS[1])
+if v then
+    return v
+end
+v = redis.call('GET', KEYS[2])
+if v then
+    redis.call('RENAME', KEYS[2], KEYS[1])
+end
+return v
+"#;
+/// Atomically add `ARGV[1]` to the counter at `KEYS[1]`, clamped to `[ARGV[2], ARGV[3]]`.
+/// New counters start from zero and expire after `ARGV[4]` seconds, existing ones keep their TTL,
+/// with `KEEPTTL` if `ARGV[5]` is `1` and by reading their TTL otherwise.
+const INCR_BOUNDED_SCRIPT: &str = r#"
+local v = redis.call('GET', KEYS[1])
+if v and not tonumber(v) then
+    return redis.error_reply('ERR value is not an integer')
+end
+local n = (tonumber(v) or 0) + tonumber(ARGV[1])
+n = math.max(tonumber(ARGV[2]), math.min(tonumber(ARGV[3]), n))
+if v and ARGV[5] == '1' then
+    redis.call('SET', KEYS[1], n, 'KEEPTTL')
+elseif v then
+    local ttl = redis.call('PTTL', KEYS[1])
+    if ttl > 0 then
+        redis.call('SET', KEYS[1], n, 'PX', ttl)
+    else
+        redis.call('SET', KEYS[1], n)
+    end
+else
+    redis.call('SET', KEYS[1], n, 'EX', ARGV[4])
+end
+return n
+"#;
+/// Atomically set `KEYS[1]` to `ARGV[1]` and reply with its previous value. Existing keys keep
+/// their TTL, with `KEEPTTL` if `ARGV[3]` is `1` and by reading their TTL otherwise, new keys
+/// expire after `ARGV[2]` seconds.
+const SET_KEEP_TTL_SCRIPT: &str = r#"
+local v = redis.call('GET', KEYS[1])
+if v and ARGV[3] == '1' then
+    redis.call('SET', KEYS[1], ARGV[1], 'KEEPTTL')
+elseif v then
+    local ttl = redis.call('PTTL', KEYS[1])
+    if ttl > 0 then
+        redis.call('SET', KEYS[1], ARGV[1], 'PX', ttl)
+    else
+        redis.call('SET', KEYS[1], ARGV[1])
+    end
+else
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+end
+return v
+"#;
+/// Atomically set `KEYS[1]` to `ARGV[2]` if it still holds `ARGV[1]`, keeping its TTL with
+/// `KEEPTTL` if `ARGV[3]` is `1` and by reading it otherwise, replying with whether it was set
+const REWRITE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) ~= ARGV[1] then
+    return 0
+end
+if ARGV[3] == '1' then
+    redis.call('SET', KEYS[1], ARGV[2], 'KEEPTTL')
+else
+    local ttl = redis.call('PTTL', KEYS[1])
+    if ttl > 0 then
+        redis.call('SET', KEYS[1], ARGV[2], 'PX', ttl)
+    else
+        redis.call('SET', KEYS[1], ARGV[2])
+    end
+end
+return 1
+"#;
+/// Atomically set `KEYS[1]` to `ARGV[1]` for `ARGV[2]` seconds if it's missing, or refresh
+/// its TTL to `ARGV[2]` seconds otherwise if `ARGV[7]` is `1`, returning `1` if the value was
+/// set. Negative entries, equal to `ARGV[3]`, and soft-removed entries, starting with `ARGV[4]`,
+/// count as missing. Refreshing a chunk manifest, starting with `ARGV[5]`, also refreshes its
+/// parts, named by appending `ARGV[6]` and the part index to the key.
+const SET_IF_ABSENT_SCRIPT: &str = r#"
+local v = redis.call('GET', KEYS[1])
+if not v or v == ARGV[3] or string.sub(v, 1, #ARGV[4]) == ARGV[4] then
+    redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[2])
+    return 1
+end
+if ARGV[7] ~= '1' then
+    return 0
+end
+redis.call('EXPIRE', KEYS[1], ARGV[2])
+if string.sub(v, 1, #ARGV[5]) == ARGV[5] then
+    local ok, manifest = pcall(cjson.decode, v)
+    if ok and type(manifest.chunks) == 'number' then
+        for index = 0, manifest.chunks - 1 do
+            redis.call('EXPIRE', KEYS[1] .. ARGV[6] .. index, ARGV[2])
+        end
+    end
+end
+return 0
+"#;
+/// Add `ARGV[1]` to the key index set `KEYS[1]` and evict random indexed keys until the index
+/// holds at most `ARGV[2]` keys, returning the number of keys deleted. Index members that
+/// already expired are dropped without counting, and eviction stops if it picks `ARGV[1]`.
+/// Evicted values holding a chunk manifest, starting with `ARGV[3]`, also lose their parts,
+/// named by appending `ARGV[4]` and the part index to the key, and evicted keys are removed
+/// from the recency set `KEYS[2]`, if given.
+const EVICT_SCRIPT: &str = r#"
+redis.call('SADD', KEYS[1], ARGV[1])
+local max = tonumber(ARGV[2])
+local evicted = 0
+while redis.call('SCARD', KEYS[1]) > max do
+    local key = redis.call('SPOP', KEYS[1])
+    if key == ARGV[1] then
+        redis.call('SADD', KEYS[1], key)
+        break
+    end
+    local raw = redis.pcall('GET', key)
+    if type(raw) == 'string' and string.sub(raw, 1, #ARGV[3]) == ARGV[3] then
+        local ok, manifest = pcall(cjson.decode, raw)
+        if ok and type(manifest.chunks) == 'number' then
+            for index = 0, manifest.chunks - 1 do
+                redis.call('DEL', key .. ARGV[4] .. index)
+            end
+        end
+    end
+    if KEYS[2] then
+        redis.call('ZREM', KEYS[2], key)
+    end
+    evicted = evicted + redis.call('DEL', key)
+end
+return evicted
+"#;
+/// Atomically delete the keys in the tag set `KEYS[1]` and the set itself, returning the
+/// number of keys deleted. Members that already expired are dropped with the set.
+const INVALIDATE_TAG_SCRIPT: &str = r#"
+local deleted = 0
+for _, key in ipairs(redis.call('SMEMBERS', KEYS[1])) do
+    deleted = deleted + redis.call('DEL', key)
+end
+redis.call('DEL', KEYS[1])
+return deleted
+"#;
+/// Atomically rename `KEYS[1]` to `KEYS[2]` unless `KEYS[1]` is missing or `KEYS[2]` exists
+const RENAME_SCRIPT: &str = r#"
+if redis.call('EXISTS', KEYS[1]) == 0 then
+    return 0
+end
+return redis.call('RENAMENX', KEYS[1], KEYS[2])
+"#;
 
 use thiserror::Error;
 
+mod json;
+pub use json::{JsonOptions, NonFiniteFloats};
+
 #[derive(Error, Debug)]
 pub enum RedisCacheBuildError {
     #[error("redis connection error")]
     Connection(#[from] redis::RedisError),
     #[error("redis pool error")]
     Pool(#[from] r2d2::Error),
+    #[cfg(feature = "redis_bb8")]
+    #[error("redis bb8 pool error")]
+    Bb8Pool(#[from] bb8::RunError<redis::RedisError>),
     #[error("Connection string not specified or invalid in env var {env_key:?}: {error:?}")]
     MissingConnectionString {
         env_key: String,
         error: std::env::VarError,
     },
+    #[error("Prefix {prefix:?} must not contain the key separator {separator:?}")]
+    InvalidPrefix { prefix: String, separator: char },
+    #[error("Hash tag {tag:?} must not be empty or contain braces")]
+    InvalidHashTag { tag: String },
+    #[error("Environment namespace not specified in env var {env_key:?}: {error:?}")]
+    MissingEnvNamespace {
+        env_key: String,
+        error: std::env::VarError,
+    },
+    #[error("Setting not specified in env var {env_key:?}: {error:?}")]
+    MissingEnvVar {
+        env_key: String,
+        error: std::env::VarError,
+    },
+    #[error("Invalid value {value:?} in env var {env_key:?}")]
+    InvalidEnvVar { env_key: String, value: String },
+    #[error("A sharded cache needs at least one connection string")]
+    NoShards,
+    #[error("Shard {index} ({connection_string}) failed to build")]
+    Shard {
+        index: usize,
+        /// The shard's connection string, without credentials
+        connection_string: String,
+        #[source]
+        error: Box<RedisCacheBuildError>,
+    },
+}
+
+/// `connection_string` with any username and password replaced by `***`, for error messages
+fn redact_credentials(connection_string: &str) -> String {
+    match connection_string.split_once("://") {
+        Some((scheme, rest)) => {
+            let authority_end = rest.find('/').unwrap_or(rest.len());
+            match rest[..authority_end].rfind('@') {
+                Some(at) => format!("{}://***{}", scheme, &rest[at..]),
+                None => connection_string.to_string(),
+            }
+        }
+        None => connection_string.to_string(),
+    }
+}
+
+/// The outcome of one step checked by `RedisCacheBuilder::diagnose`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticCheck {
+    /// The step succeeded, with details on what was found
+    Passed(String),
+    /// The step failed, with the reason
+    Failed(String),
+    /// The step wasn't attempted because an earlier step failed
+    Skipped,
+}
+
+impl DiagnosticCheck {
+    pub fn is_passed(&self) -> bool {
+        matches!(self, DiagnosticCheck::Passed(_))
+    }
+}
+
+impl Display for DiagnosticCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiagnosticCheck::Passed(details) => write!(f, "ok ({})", details),
+            DiagnosticCheck::Failed(reason) => write!(f, "FAILED ({})", reason),
+            DiagnosticCheck::Skipped => write!(f, "skipped"),
+        }
+    }
+}
+
+/// A report of each step `build` goes through to connect to redis, see `RedisCacheBuilder::diagnose`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildDiagnostics {
+    /// Whether a connection string was set on the builder or read from the env
+    pub connection_string: DiagnosticCheck,
+    /// Whether the connection string parses as a redis URL
+    pub parse: DiagnosticCheck,
+    /// Whether the server's address accepts a connection
+    pub reachable: DiagnosticCheck,
+    /// Whether a redis connection, including authentication, can be opened and answers a `PING`
+    pub ping: DiagnosticCheck,
+}
+
+impl BuildDiagnostics {
+    fn checks(&self) -> [(&'static str, &DiagnosticCheck); 4] {
+        [
+            ("connection string", &self.connection_string),
+            ("parse", &self.parse),
+            ("reachable", &self.reachable),
+            ("ping", &self.ping),
+        ]
+    }
+
+    /// Whether every step passed
+    pub fn is_ok(&self) -> bool {
+        self.checks().iter().all(|(_, check)| check.is_passed())
+    }
+
+    /// The name and reason of the first step that failed
+    pub fn first_failure(&self) -> Option<(&'static str, &str)> {
+        self.checks().iter().find_map(|&(name, check)| match check {
+            DiagnosticCheck::Failed(reason) => Some((name, reason.as_str())),
+            _ => None,
+        })
+    }
+}
+
+impl Display for BuildDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, check) in self.checks() {
+            writeln!(f, "{}: {}", name, check)?;
+        }
+        Ok(())
+    }
+}
+
+/// A likely misconfiguration noticed by `build`, which builds the cache anyway.
+/// See `RedisCache::config_warnings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigWarning {
+    /// The lifespan is 0 seconds, which redis rejects as an expiry, so sets fail
+    ZeroLifespan,
+    /// The lifespan jitter is larger than the lifespan, so some values live more than twice as
+    /// long as others
+    JitterExceedsLifespan { jitter: u64, lifespan: u64 },
+    /// Hits refresh the TTL without jitter, so the expiries of values that are read line up again
+    RefreshDropsJitter,
+    /// An expire option is set for refreshes, but hits don't refresh the TTL
+    ExpireOptionWithoutRefresh,
+    /// A compression min ratio or dictionary is set, but values aren't compressed with it
+    UnusedCompressionSettings,
+}
+
+impl Display for ConfigWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigWarning::ZeroLifespan => {
+                write!(f, "a lifespan of 0 seconds makes every set fail")
+            }
+            ConfigWarning::JitterExceedsLifespan { jitter, lifespan } => write!(
+                f,
+                "lifespan jitter of {}s exceeds the lifespan of {}s",
+                jitter, lifespan
+            ),
+            ConfigWarning::RefreshDropsJitter => {
+                write!(f, "refreshes on hits reset the TTL without lifespan jitter")
+            }
+            ConfigWarning::ExpireOptionWithoutRefresh => {
+                write!(f, "the refresh expire option has no effect without refresh")
+            }
+            ConfigWarning::UnusedCompressionSettings => write!(
+                f,
+                "compression settings have no effect without a matching compression"
+            ),
+        }
+    }
+}
+
+/// The warnings about the settings of a cache being built, see `ConfigWarning`
+fn config_warnings(
+    seconds: u64,
+    lifespan_jitter: u64,
+    refresh: bool,
+    refresh_expire_option: bool,
+    unused_compression_settings: bool,
+) -> Vec<ConfigWarning> {
+    let mut warnings = Vec::new();
+    if seconds == 0 {
+        warnings.push(ConfigWarning::ZeroLifespan);
+    }
+    if lifespan_jitter > seconds {
+        warnings.push(ConfigWarning::JitterExceedsLifespan {
+            jitter: lifespan_jitter,
+            lifespan: seconds,
+        });
+    }
+    if refresh && lifespan_jitter > 0 {
+        warnings.push(ConfigWarning::RefreshDropsJitter);
+    }
+    if refresh_expire_option && !refresh {
+        warnings.push(ConfigWarning::ExpireOptionWithoutRefresh);
+    }
+    if unused_compression_settings {
+        warnings.push(ConfigWarning::UnusedCompressionSettings);
+    }
+    warnings
+}
+
+/// How long each network step of `RedisCacheBuilder::diagnose` may take
+const DIAGNOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Check that a connection can be opened to `addr`, without speaking the redis protocol
+fn check_reachable(addr: &redis::ConnectionAddr) -> DiagnosticCheck {
+    use std::net::ToSocketAddrs;
+    let (host, port) = match addr {
+        redis::ConnectionAddr::Tcp(host, port) => (host, *port),
+        redis::ConnectionAddr::TcpTls { host, port, .. } => (host, *port),
+        #[cfg(unix)]
+        redis::ConnectionAddr::Unix(path) => {
+            return match std::os::unix::net::UnixStream::connect(path) {
+                Ok(_) => DiagnosticCheck::Passed(format!("connected to {}", path.display())),
+                Err(e) => DiagnosticCheck::Failed(format!("{}: {}", path.display(), e)),
+            };
+        }
+        #[cfg(not(unix))]
+        redis::ConnectionAddr::Unix(_) => return DiagnosticCheck::Skipped,
+    };
+    let socket_addrs = match (host.as_str(), port).to_socket_addrs() {
+        Ok(socket_addrs) => socket_addrs.collect::<Vec<_>>(),
+        Err(e) => return DiagnosticCheck::Failed(format!("could not resolve {}: {}", host, e)),
+    };
+    let mut last_error = None;
+    for socket_addr in socket_addrs {
+        match std::net::TcpStream::connect_timeout(&socket_addr, DIAGNOSE_TIMEOUT) {
+            Ok(_) => return DiagnosticCheck::Passed(format!("connected to {}", socket_addr)),
+            Err(e) => last_error = Some(format!("{}: {}", socket_addr, e)),
+        }
+    }
+    DiagnosticCheck::Failed(
+        last_error.unwrap_or_else(|| format!("{} resolved to no addresses", host)),
+    )
+}
+
+/// Open a connection with `client` and `PING` the server
+fn check_ping(client: &redis::Client) -> DiagnosticCheck {
+    let res = client
+        .get_connection_with_timeout(DIAGNOSE_TIMEOUT)
+        .and_then(|mut conn| redis::cmd("PING").query::<String>(&mut conn));
+    match res {
+        Ok(reply) => DiagnosticCheck::Passed(format!("server replied {}", reply)),
+        Err(e) => DiagnosticCheck::Failed(e.to_string()),
+    }
+}
+
+/// The settings read by `from_env`
+struct EnvSettings {
+    prefix: String,
+    seconds: u64,
+    refresh: bool,
+}
+
+fn env_var(env_key: &str) -> Result<Option<String>, RedisCacheBuildError> {
+    match std::env::var(env_key) {
+        Ok(value) => Ok(Some(value)),
+        Err(std::env::VarError::NotPresent) => Ok(None),
+        Err(error) => Err(RedisCacheBuildError::MissingEnvVar {
+            env_key: env_key.to_string(),
+            error,
+        }),
+    }
+}
+
+fn parse_env_var<T: std::str::FromStr>(
+    env_key: &str,
+    value: String,
+) -> Result<T, RedisCacheBuildError> {
+    value
+        .parse()
+        .map_err(|_| RedisCacheBuildError::InvalidEnvVar {
+            env_key: env_key.to_string(),
+            value,
+        })
+}
+
+fn load_env_settings() -> Result<EnvSettings, RedisCacheBuildError> {
+    let required = |env_key: &str| {
+        env_var(env_key)?.ok_or_else(|| RedisCacheBuildError::MissingEnvVar {
+            env_key: env_key.to_string(),
+            error: std::env::VarError::NotPresent,
+        })
+    };
+    let prefix = required(ENV_PREFIX_KEY)?;
+    let seconds = parse_env_var(ENV_LIFESPAN_KEY, required(ENV_LIFESPAN_KEY)?)?;
+    let refresh = match env_var(ENV_REFRESH_KEY)? {
+        None => false,
+        Some(value) => parse_env_var(ENV_REFRESH_KEY, value)?,
+    };
+    Ok(EnvSettings {
+        prefix,
+        seconds,
+        refresh,
+    })
+}
+
+/// Expands to a prefix for a redis cache store derived from the calling package and module,
+/// so that independent libraries sharing a redis instance don't collide without having to
+/// coordinate prefixes.
+///
+/// `redis_auto_prefix!()` expands to a call with `env!("CARGO_PKG_NAME")` and `module_path!()`,
+/// evaluated where the macro is used. The prefix is the package name followed by the module
+/// path below the crate root, with `::` replaced by `.` since prefixes must not contain `:`.
+/// For a cache built in the `users::cache` module of the `my-lib` package, the prefix is
+/// `my-lib.users.cache` and keys are laid out as `cached-redis-store:my-lib.users.cache:<key>`.
+/// Moving the cache to another module changes its prefix, which invalidates existing entries.
+///
+/// ```rust,no_run
+/// # use cached::RedisCache;
+/// let cache: RedisCache<u32, String> = RedisCache::new(cached::redis_auto_prefix!(), 60)
+///     .build()
+///     .unwrap();
+/// ```
+#[macro_export]
+macro_rules! redis_auto_prefix {
+    () => {
+        $crate::stores::redis_auto_prefix(env!("CARGO_PKG_NAME"), module_path!())
+    };
+}
+
+/// The prefix of `redis_auto_prefix!` for `module_path` in `package`
+#[doc(hidden)]
+pub fn redis_auto_prefix(package: &str, module_path: &str) -> String {
+    let mut prefix = package.to_string();
+    for module in module_path.split("::").skip(1) {
+        prefix.push('.');
+        prefix.push_str(module);
+    }
+    prefix
+}
+
+/// Keys are laid out as `<namespace>:<prefix>:<key>`, or `<namespace>:<env>:<prefix>:<key>`
+/// when namespaced by environment
+fn generate_prefix(env_namespace: Option<&str>, prefix: &str) -> String {
+    match env_namespace {
+        None => format!(
+            "{}{sep}{}{sep}",
+            PREFIX_NAMESPACE,
+            prefix,
+            sep = KEY_SEPARATOR
+        ),
+        Some(env) => format!(
+            "{}{sep}{}{sep}{}{sep}",
+            PREFIX_NAMESPACE,
+            env,
+            prefix,
+            sep = KEY_SEPARATOR
+        ),
+    }
+}
+
+/// Put `hash_tag` in braces in front of `prefix`, see `RedisCacheBuilder::set_hash_tag`
+fn hash_tagged(hash_tag: Option<&str>, prefix: String) -> String {
+    match hash_tag {
+        None => prefix,
+        Some(tag) => format!("{{{}}}{}", tag, prefix),
+    }
+}
+
+/// Load the environment namespace from the env var `env_key`, if one was configured
+fn load_env_namespace(env_key: Option<&str>) -> Result<Option<String>, RedisCacheBuildError> {
+    let env_key = match env_key {
+        None => return Ok(None),
+        Some(env_key) => env_key,
+    };
+    let env = std::env::var(env_key).map_err(|e| RedisCacheBuildError::MissingEnvNamespace {
+        env_key: env_key.to_string(),
+        error: e,
+    })?;
+    validate_prefix(&env)?;
+    Ok(Some(env))
+}
+
+/// Prefixes can't contain the separator, otherwise the prefix and key of a
+/// generated key can't be told apart
+fn validate_prefix(prefix: &str) -> Result<(), RedisCacheBuildError> {
+    if prefix.contains(KEY_SEPARATOR) {
+        Err(RedisCacheBuildError::InvalidPrefix {
+            prefix: prefix.to_string(),
+            separator: KEY_SEPARATOR,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Like `validate_prefix`, for prefixes given to the operations of a built cache
+fn check_prefix(prefix: &str) -> Result<(), RedisCacheError> {
+    if prefix.contains(KEY_SEPARATOR) {
+        Err(RedisCacheError::InvalidPrefix {
+            prefix: prefix.to_string(),
+            separator: KEY_SEPARATOR,
+        })
+    } else {
+        Ok(())
+    }
 }
 
-fn generate_prefix(prefix: &str) -> String {
-    format!("{}:{}", PREFIX_NAMESPACE, prefix)
+/// Redis Cluster ignores empty hash tags and ends them at the first `}`
+fn validate_hash_tag(tag: &str) -> Result<(), RedisCacheBuildError> {
+    if tag.is_empty() || tag.contains(['{', '}'].as_ref()) {
+        Err(RedisCacheBuildError::InvalidHashTag {
+            tag: tag.to_string(),
+        })
+    } else {
+        Ok(())
+    }
 }
 
 impl<K, V> RedisCacheBuilder<K, V>
@@ -44,14 +782,66 @@ where
     pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> RedisCacheBuilder<K, V> {
         Self {
             seconds,
+            max_list_len: DEFAULT_MAX_LIST_LEN,
+            max_stream_len: DEFAULT_MAX_STREAM_LEN,
             refresh: false,
-            prefix: generate_prefix(prefix.as_ref()),
+            negative_seconds: None,
+            prefix: prefix.as_ref().to_string(),
+            fallback_prefix: None,
+            name: None,
             connection_string: None,
+            env_namespace_from: None,
+            hash_tag: None,
+            ignore_set_errors: false,
+            test_on_check_out: true,
+            pool_max_size: None,
+            pool_connection_timeout: None,
+            command_timeout: None,
+            fail_fast_on_exhaustion: false,
+            pooled: true,
+            event_sink: None,
+            connection_observer: None,
+            write_wait: None,
+            health_cache_ttl: DEFAULT_HEALTH_CACHE_TTL,
+            batch_chunk_size: None,
+            soft_remove: None,
+            lifespan_jitter: 0,
+            key_hasher: None,
+            ttl_fn: None,
+            key_normalization: Normalization::None,
+            max_keys: None,
+            track_recency: false,
+            value_chunk_size: None,
+            refresh_expire_option: None,
+            codec: None,
+            fallback_codec: None,
+            json_options: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            compression_dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            encryption_key: None,
+            #[cfg(feature = "chaos")]
+            fault_injector: None,
             _phantom_k: Default::default(),
             _phantom_v: Default::default(),
         }
     }
 
+    /// Initialize a builder from the environment. The prefix is read from `CACHED_REDIS_PREFIX`,
+    /// the lifespan in seconds from `CACHED_REDIS_LIFESPAN`, and whether hits refresh the TTL from
+    /// `CACHED_REDIS_REFRESH` (`true` or `false`, defaults to `false`). As with `new`, the connection
+    /// string is read from `CACHED_REDIS_CONNECTION_STRING` by `build` unless set explicitly.
+    pub fn from_env() -> Result<Self, RedisCacheBuildError> {
+        let settings = load_env_settings()?;
+        Ok(Self::new(settings.prefix, settings.seconds).set_refresh(settings.refresh))
+    }
+
     /// Specify the cache TTL/lifespan in seconds
     pub fn set_lifespan(mut self, seconds: u64) -> Self {
         self.seconds = seconds;
@@ -64,548 +854,10206 @@ where
         self
     }
 
-    /// Set the prefix for the keys
-    pub fn set_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
-        self.prefix = generate_prefix(prefix.as_ref());
+    /// Make the `EXPIRE` refreshing the TTL on cache hits conditional, e.g. `ExpireOption::Gt`
+    /// to never shorten the TTL of a key set with a longer lifespan. Requires redis 7,
+    /// `build` checks the server version and falls back to a plain `EXPIRE` on older servers.
+    pub fn set_refresh_expire_option(mut self, option: ExpireOption) -> Self {
+        self.refresh_expire_option = Some(option);
         self
     }
 
-    /// Set the connection string for redis
-    pub fn set_connection_string(mut self, cs: &str) -> Self {
-        self.connection_string = Some(cs.to_string());
+    /// Specify the TTL in seconds of negative entries stored by `cache_set_miss`,
+    /// usually shorter than the lifespan. Defaults to the lifespan.
+    pub fn set_negative_lifespan(mut self, seconds: u64) -> Self {
+        self.negative_seconds = Some(seconds);
         self
     }
 
-    /// Return the current connection string or load from the env var: CACHED_REDIS_CONNECTION_STRING
-    pub fn connection_string(&self) -> Result<String, RedisCacheBuildError> {
-        match self.connection_string {
-            Some(ref s) => Ok(s.to_string()),
-            None => {
-                std::env::var(ENV_KEY).map_err(|e| RedisCacheBuildError::MissingConnectionString {
-                    env_key: ENV_KEY.to_string(),
-                    error: e,
-                })
-            }
-        }
+    /// Specify the maximum number of values kept per list by a `RedisListCache`,
+    /// the oldest values are trimmed on push. Defaults to 1000, and is at least 1.
+    pub fn set_max_list_len(mut self, max_list_len: usize) -> Self {
+        self.max_list_len = max_list_len.max(1);
+        self
     }
 
-    fn create_pool(&self) -> Result<r2d2::Pool<redis::Client>, RedisCacheBuildError> {
-        let s = self.connection_string()?;
-        let client: redis::Client = redis::Client::open(s)?;
-        let pool: r2d2::Pool<redis::Client> = r2d2::Pool::builder().build(client)?;
-        Ok(pool)
+    /// Specify the maximum number of events kept per stream by a `RedisStreamCache`,
+    /// the oldest events are trimmed on append. Defaults to 1000, and is at least 1.
+    pub fn set_max_stream_len(mut self, max_stream_len: usize) -> Self {
+        self.max_stream_len = max_stream_len.max(1);
+        self
     }
 
-    pub fn build(self) -> Result<RedisCache<K, V>, RedisCacheBuildError> {
-        Ok(RedisCache {
-            seconds: self.seconds,
-            refresh: self.refresh,
-            connection_string: self.connection_string()?,
-            pool: self.create_pool()?,
-            prefix: self.prefix,
-            _phantom_k: self._phantom_k,
-            _phantom_v: self._phantom_v,
-        })
+    /// Set the prefix for the keys. The prefix must not contain `:`, this is checked by `build`
+    pub fn set_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
+        self.prefix = prefix.as_ref().to_string();
+        self
     }
-}
 
-/// Cache store backed by redis
-///
-/// Values have a ttl applied and enforced by redis.
-pub struct RedisCache<K, V> {
-    pub(super) seconds: u64,
-    pub(super) refresh: bool,
-    pub(super) prefix: String,
-    connection_string: String,
-    pool: r2d2::Pool<redis::Client>,
-    _phantom_k: PhantomData<K>,
-    _phantom_v: PhantomData<V>,
-}
+    /// On a `cache_get` miss, look the key up under `prefix` too and, if found, move the
+    /// value with its remaining TTL to the cache's own prefix. This keeps caches warm while
+    /// changing prefixes. Like the cache's own prefix, `prefix` must not contain `:`.
+    pub fn set_fallback_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
+        self.fallback_prefix = Some(prefix.as_ref().to_string());
+        self
+    }
 
-impl<K, V> RedisCache<K, V>
-where
-    K: Display,
-    V: Serialize + DeserializeOwned,
-{
-    #[allow(clippy::new_ret_no_self)]
-    /// Initialize a `RedisCacheBuilder`
-    pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> RedisCacheBuilder<K, V> {
-        RedisCacheBuilder::new(prefix, seconds)
+    /// Name the cache in its errors, to tell caches apart in apps with several of them. Errors of
+    /// gets, sets, and removes are wrapped in `RedisCacheError::Named`, see `RedisCacheError::unnamed`.
+    pub fn set_name<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.name = Some(name.as_ref().to_string());
+        self
     }
 
-    fn generate_key(&self, key: &K) -> String {
-        format!("{}{}", self.prefix, key)
+    /// Set the connection string for redis
+    pub fn set_connection_string(mut self, cs: &str) -> Self {
+        self.connection_string = Some(cs.to_string());
+        self
     }
 
-    /// Return the redis connection string used
-    pub fn connection_string(&self) -> String {
-        self.connection_string.clone()
+    /// Wrap `tag` in braces in front of every key of the cache, so Redis Cluster hashes only the
+    /// tag and all the keys land in the same slot. This lets multi-key commands like `MGET`,
+    /// the scripts behind `set_max_keys` and `cache_set_tagged`, and fallback reads work on
+    /// a cluster, and keeps batch operations on one node. All of the cache's traffic and
+    /// memory then sits on a single node, which can turn it into a hot spot, so this suits
+    /// small groups of related keys rather than large caches. `build` fails if `tag` is empty
+    /// or contains a brace.
+    pub fn set_hash_tag<S: AsRef<str>>(mut self, tag: S) -> Self {
+        self.hash_tag = Some(tag.as_ref().to_string());
+        self
     }
-}
 
-#[derive(Error, Debug)]
-pub enum RedisCacheError {
-    #[error("redis error")]
-    RedisCacheError(#[from] redis::RedisError),
-    #[error("redis pool error")]
-    PoolError(#[from] r2d2::Error),
-    #[error("Error deserializing cached value {cached_value:?}: {error:?}")]
-    CacheDeserializationError {
-        cached_value: String,
-        error: serde_json::Error,
-    },
-    #[error("Error serializing cached value: {error:?}")]
-    CacheSerializationError { error: serde_json::Error },
-}
+    /// Namespace keys by the value of the env var `var` (e.g. `APP_ENV`), read by `build`,
+    /// so caches in different environments sharing a redis never see each other's keys.
+    /// `build` fails if the var is unset or its value contains `:`.
+    pub fn set_env_namespace_from(mut self, var: &str) -> Self {
+        self.env_namespace_from = Some(var.to_string());
+        self
+    }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-struct CachedRedisValue<V> {
-    value: V,
-}
+    /// Specify whether errors during `cache_set` are swallowed, treating the cache
+    /// as best-effort on writes. Ignored errors still count towards `CacheStats::errors`
+    /// and `cache_set` returns `Ok(None)` instead.
+    pub fn set_ignore_set_errors(mut self, ignore: bool) -> Self {
+        self.ignore_set_errors = ignore;
+        self
+    }
 
-impl<'de, K, V> IOCached<K, V> for RedisCache<K, V>
-where
-    K: Display,
-    V: Serialize + DeserializeOwned,
-{
-    type Error = RedisCacheError;
+    /// Specify whether pooled connections are checked with a `PING` when taken from the pool,
+    /// defaults to `true`. Disabling the check saves a round-trip per operation, at the risk of
+    /// an operation failing on a connection that died while idle.
+    pub fn set_test_on_check_out(mut self, test: bool) -> Self {
+        self.test_on_check_out = test;
+        self
+    }
 
-    fn cache_get(&self, key: &K) -> Result<Option<V>, RedisCacheError> {
-        let mut conn = self.pool.get()?;
-        let mut pipe = redis::pipe();
-        let key = self.generate_key(key);
+    /// Specify the maximum number of connections in the pool, defaults to r2d2's 10
+    pub fn set_pool_max_size(mut self, max_size: u32) -> Self {
+        self.pool_max_size = Some(max_size.max(1));
+        self
+    }
 
-        pipe.get(key.clone());
-        if self.refresh {
-            pipe.expire(key, self.seconds as usize).ignore();
-        }
-        // ugh: https://github.com/mitsuhiko/redis-rs/pull/388#issuecomment-910919137
-        let res: (Option<String>,) = pipe.query(&mut *conn)?;
-        match res.0 {
-            None => Ok(None),
-            Some(s) => {
-                let v: CachedRedisValue<V> = serde_json::from_str(&s).map_err(|e| {
-                    RedisCacheError::CacheDeserializationError {
-                        cached_value: s,
-                        error: e,
-                    }
-                })?;
-                Ok(Some(v.value))
-            }
-        }
+    /// Specify how long building the cache and acquiring a pooled connection wait for a
+    /// connection before failing, defaults to r2d2's 30 seconds
+    pub fn set_pool_connection_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_connection_timeout = Some(timeout);
+        self
     }
 
-    fn cache_set(&self, key: K, val: V) -> Result<Option<V>, RedisCacheError> {
-        let mut conn = self.pool.get()?;
-        let mut pipe = redis::pipe();
-        let key = self.generate_key(&key);
+    /// Fail commands that get no reply within `timeout` with `RedisCacheError::Timeout`,
+    /// instead of blocking on a hung server forever. The timeout is set as the read and write
+    /// timeout of the connections' sockets, so it applies to each command or pipeline rather
+    /// than to a whole operation. A connection that timed out may still receive the late reply,
+    /// so it's discarded instead of being returned to the pool.
+    pub fn set_command_timeout(mut self, timeout: Duration) -> Self {
+        self.command_timeout = Some(timeout);
+        self
+    }
 
-        let val = CachedRedisValue { value: val };
-        pipe.get(key.clone());
-        pipe.set_ex::<String, String>(
-            key,
-            serde_json::to_string(&val)
-                .map_err(|e| RedisCacheError::CacheSerializationError { error: e })?,
-            self.seconds as usize,
-        )
-        .ignore();
+    /// Specify whether operations fail immediately with `RedisCacheError::PoolExhausted` when
+    /// every pooled connection is in use, instead of waiting for one up to the pool's
+    /// connection timeout. Connections being opened in the background don't count as available.
+    /// Defaults to `false`.
+    pub fn set_fail_fast_on_exhaustion(mut self, fail_fast: bool) -> Self {
+        self.fail_fast_on_exhaustion = fail_fast;
+        self
+    }
 
-        let res: (Option<String>,) = pipe.query(&mut *conn)?;
-        match res.0 {
-            None => Ok(None),
-            Some(s) => {
-                let v: CachedRedisValue<V> = serde_json::from_str(&s).map_err(|e| {
-                    RedisCacheError::CacheDeserializationError {
-                        cached_value: s,
-                        error: e,
-                    }
-                })?;
-                Ok(Some(v.value))
-            }
-        }
+    /// Configure the error handling settings of `policy` at once, see `ResiliencePolicy`.
+    /// `serve_stale_on_loader_error` doesn't apply to `RedisCache`, which has no fallible loader.
+    pub fn set_resilience(mut self, policy: ResiliencePolicy) -> Self {
+        self.ignore_set_errors = policy.ignore_set_errors;
+        self.fail_fast_on_exhaustion = policy.fail_fast_on_exhaustion;
+        self
     }
 
-    fn cache_remove(&self, key: &K) -> Result<Option<V>, RedisCacheError> {
-        let mut conn = self.pool.get()?;
-        let mut pipe = redis::pipe();
-        let key = self.generate_key(key);
+    /// Open a fresh connection for every operation instead of keeping a connection pool.
+    /// Each operation pays for a connection handshake, but no connections are left idle,
+    /// which suits short-lived processes. The pool settings are ignored in this mode.
+    pub fn without_pool(mut self) -> Self {
+        self.pooled = false;
+        self
+    }
 
-        pipe.get(key.clone());
-        pipe.del::<String>(key).ignore();
-        let res: (Option<String>,) = pipe.query(&mut *conn)?;
-        match res.0 {
-            None => Ok(None),
-            Some(s) => {
-                let v: CachedRedisValue<V> = serde_json::from_str(&s).map_err(|e| {
-                    RedisCacheError::CacheDeserializationError {
-                        cached_value: s,
-                        error: e,
-                    }
-                })?;
-                Ok(Some(v.value))
-            }
-        }
+    /// Send a `CacheEvent` to `sink` for every get, set, and remove. Sending never
+    /// blocks, events are dropped when the channel is full.
+    pub fn set_event_sink(mut self, sink: SyncSender<CacheEvent>) -> Self {
+        self.event_sink = Some(sink);
+        self
     }
 
-    fn cache_lifespan(&self) -> Option<u64> {
-        Some(self.seconds)
+    /// Call `observer` whenever a connection to redis is opened or closed, for visibility
+    /// into connection churn. Pooled connections report `Connected` when opened and
+    /// `Disconnected` when discarded, and operations failing on a dropped connection report
+    /// `Disconnected` too.
+    pub fn set_connection_observer(mut self, observer: ConnectionObserver) -> Self {
+        self.connection_observer = Some(observer);
+        self
     }
 
-    fn cache_set_lifespan(&mut self, seconds: u64) -> Option<u64> {
-        let old = self.seconds;
-        self.seconds = seconds;
-        Some(old)
+    /// Make `cache_set` wait until the write is acknowledged by `num_replicas` replicas,
+    /// using redis `WAIT`. If fewer replicas acknowledge within `timeout`, `cache_set`
+    /// returns `RedisCacheError::WriteWaitTimeout`, though the value has already been
+    /// written to the primary. A zero `timeout` waits forever.
+    pub fn set_write_wait(mut self, num_replicas: u32, timeout: Duration) -> Self {
+        self.write_wait = Some((num_replicas, timeout));
+        self
     }
 
-    fn cache_set_refresh(&mut self, refresh: bool) -> bool {
-        let old = self.refresh;
-        self.refresh = refresh;
-        old
+    /// Specify how long `RedisCache::is_healthy` reuses the result of its last `PING`,
+    /// defaults to one second
+    pub fn set_health_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.health_cache_ttl = ttl;
+        self
     }
-}
 
-#[cfg(all(
-    feature = "async",
-    any(feature = "redis_async_std", feature = "redis_tokio")
-))]
-mod async_redis {
-    use super::*;
-    use {crate::IOCachedAsync, async_trait::async_trait};
+    /// Split `RedisCache::warm` into pipelines of at most `chunk_size` entries, sent one after
+    /// another, to bound the memory held by each request. Defaults to 1000 entries.
+    pub fn set_batch_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.batch_chunk_size = Some(chunk_size.max(1));
+        self
+    }
 
-    pub struct AsyncRedisCacheBuilder<K, V> {
-        seconds: u64,
-        refresh: bool,
-        prefix: String,
-        connection_string: Option<String>,
-        _phantom_k: PhantomData<K>,
-        _phantom_v: PhantomData<V>,
+    /// Make `cache_remove` soft-remove values: instead of being deleted, a removed value is kept
+    /// for `grace_seconds` but treated as missing by `cache_get`, while `cache_get_detailed` reports
+    /// it as `GetOutcome::Removed` so in-flight readers can still use it. Setting the key again
+    /// with `cache_set` revives it with a full lifespan. `cache_exists` and `cache_ttl` treat
+    /// soft-removed values as missing too.
+    pub fn set_soft_remove(mut self, grace_seconds: u64) -> Self {
+        self.soft_remove = Some(grace_seconds);
+        self
     }
 
-    impl<K, V> AsyncRedisCacheBuilder<K, V>
+    /// Extend the TTL of values written by `cache_set` and its variants, the batch sets and
+    /// pipelined sets by a random `0..=max_seconds` seconds, so values cached together don't all
+    /// expire at once. The lifespan recorded with a value, read by `cache_original_lifespan`, is
+    /// the lifespan before jitter. Refreshes on `cache_get` reset the TTL to the cache's lifespan
+    /// without jitter.
+    pub fn set_lifespan_jitter(mut self, max_seconds: u64) -> Self {
+        self.lifespan_jitter = max_seconds;
+        self
+    }
+
+    /// Specify whether keys are hashed with SHA-256 before being prefixed, keeping keys short
+    /// and uniform however long their `Display` output is. Hashed keys can't be read back from
+    /// redis, so `watch_keyspace` can't report them.
+    pub fn set_hash_keys(mut self, hash: bool) -> Self {
+        self.key_hasher = if hash {
+            Some(Arc::new(sha256_hex))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Hash keys with `hasher` instead of SHA-256, see `set_hash_keys`. A faster
+    /// non-cryptographic hash suits keys that aren't chosen by adversaries.
+    pub fn set_key_hasher(mut self, hasher: KeyHasher) -> Self {
+        self.key_hasher = Some(hasher);
+        self
+    }
+
+    /// Compute the lifespan of each value the cache writes with `ttl_fn` in place of the cache's
+    /// lifespan, e.g. from a publish time stored in the value. Writes given a lifespan, like
+    /// `cache_set_with_lifespan`, keep it. Lifespans under a second are raised to one second, as
+    /// redis rejects them. Lifespan jitter still applies, while refreshes on hits reset the TTL
+    /// to the cache's lifespan.
+    pub fn set_ttl_fn(mut self, ttl_fn: TtlFn<V>) -> Self {
+        self.ttl_fn = Some(ttl_fn);
+        self
+    }
+
+    /// Normalize keys with `normalization` before hashing and prefixing them, so that keys
+    /// like `Foo` and `foo` share an entry in case-insensitive domains. Changing the
+    /// normalization of an existing cache invalidates the entries stored under keys it changes.
+    pub fn set_key_normalizer(mut self, normalization: Normalization) -> Self {
+        self.key_normalization = normalization;
+        self
+    }
+
+    /// Cap the number of keys of the cache at `max_keys`, evicting random keys once sets exceed
+    /// it. Keys set through `cache_set`, `cache_set_with_lifespan`, `cache_set_in`, and
+    /// `cache_set_raw_key` are recorded in a key index set stored under the cache prefix, and
+    /// evicted by a script sent in the same round-trip as the set, along with their chunks of
+    /// `set_chunked_storage` and their entries of `set_track_recency`. Eviction is best-effort:
+    /// keys set by other means aren't indexed, expired and removed keys linger in the index until
+    /// picked, and an eviction may pick the key just set, which stops it for that set, so the cap
+    /// can be exceeded under concurrent sets.
+    /// The index also gives an exact key count with `cache_len`, see `cache_reconcile`.
+    pub fn set_max_keys(mut self, max_keys: u64) -> Self {
+        self.max_keys = Some(max_keys);
+        self
+    }
+
+    /// Specify whether sets record their key with the current time in a sorted set stored
+    /// under the cache prefix, so the most recently set keys can be listed with `cache_recent`.
+    /// Like `set_max_keys`, this applies to keys set through `cache_set`,
+    /// `cache_set_with_lifespan`, `cache_set_in`, and `cache_set_raw_key`.
+    pub fn set_track_recency(mut self, track: bool) -> Self {
+        self.track_recency = track;
+        self
+    }
+
+    /// Store serialized values larger than `chunk_size` bytes in parts of at most `chunk_size`
+    /// bytes, for servers or proxies limiting the size of values. Parts are stored under
+    /// `{prefix}{key}:part:{i}` with the same lifespan as the value, whose key holds a manifest
+    /// counting them. `cache_get` reassembles the parts and misses if any of them is gone,
+    /// and `cache_remove` deletes them. Replacing a chunked value reads it before the set,
+    /// which takes an extra round-trip.
+    ///
+    /// Chunking applies to `cache_set`, `cache_set_with_lifespan`, `cache_set_in`,
+    /// `cache_set_raw_key`, `cache_update`, and pipelined sets. `cache_meta`,
+    /// `cache_original_lifespan`, `cache_get_and_refresh`, `cache_touch`, and
+    /// `cache_set_or_refresh` also read or refresh the parts. Other batch and conditional
+    /// operations like `cache_get_multi` or `cache_insert` read and write values whole, and fail
+    /// to decode manifests.
+    pub fn set_chunked_storage(mut self, chunk_size: usize) -> Self {
+        self.value_chunk_size = Some(chunk_size.max(1));
+        self
+    }
+
+    /// Encode and decode values with `codec` instead of storing them as JSON
+    pub fn set_codec(mut self, codec: Arc<dyn ValueCodec<V>>) -> Self {
+        self.codec = Some(codec);
+        self
+    }
+
+    /// Store the variants of `V` as distinct types, see `TaggedCodec`
+    pub fn set_tagged_codec(self, codec: TaggedCodec<V>) -> Self
     where
-        K: Display,
-        V: Serialize + DeserializeOwned,
+        V: 'static,
     {
-        /// Initialize a `RedisCacheBuilder`
-        pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> AsyncRedisCacheBuilder<K, V> {
-            Self {
-                seconds,
-                refresh: false,
-                prefix: generate_prefix(prefix.as_ref()),
-                connection_string: None,
-                _phantom_k: Default::default(),
-                _phantom_v: Default::default(),
+        self.set_codec(Arc::new(codec))
+    }
+
+    /// Read values the configured codec fails to decode with `codec` instead, for instance while
+    /// moving keys over to a new codec. `cache_get` rewrites values read this way in the
+    /// configured format, keeping their remaining TTL and recorded lifespan, so keys migrate as
+    /// they're read. Keys changed since the read aren't rewritten, and failing rewrites are
+    /// ignored. Values aren't rewritten when chunked storage is on.
+    pub fn set_fallback_codec(mut self, codec: Arc<dyn ValueCodec<V>>) -> Self {
+        self.fallback_codec = Some(Some(codec));
+        self
+    }
+
+    /// Read values the configured codec fails to decode as JSON instead, like
+    /// `set_fallback_codec` does with a codec. For moving away from JSON with `set_codec`.
+    pub fn set_json_fallback(mut self) -> Self {
+        self.fallback_codec = Some(None);
+        self
+    }
+
+    /// Store values as JSON with `options`, see `JsonOptions`. Ignored when a codec is set.
+    pub fn set_json_options(mut self, options: JsonOptions) -> Self {
+        self.json_options = options;
+        self
+    }
+
+    /// Fail reads of values stored in more than `max` bytes with `RedisCacheError::ValueTooLarge`
+    /// before decompressing or deserializing them, to guard against oversized values written
+    /// by another producer. The limit applies to the stored bytes, after any compression.
+    pub fn set_max_read_bytes(mut self, max: usize) -> Self {
+        self.max_read_bytes = Some(max);
+        self
+    }
+
+    /// Store a CRC32 of each value with it, verified on read to detect values corrupted in
+    /// redis, which fail with `RedisCacheError::ChecksumMismatch`. Values stored without a
+    /// checksum are still read.
+    pub fn set_checksum(mut self, checksum: bool) -> Self {
+        self.checksum = checksum;
+        self
+    }
+
+    /// Compress values with `kind` before storing them. Values are decompressed on read
+    /// whichever enabled format they were written with, see `CompressionKind`.
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    pub fn set_compression(mut self, kind: CompressionKind) -> Self {
+        self.compression = Some(kind);
+        self
+    }
+
+    /// Only keep compressed values that are at least `min_ratio` times smaller than the
+    /// uncompressed ones, storing the others uncompressed, so incompressible values aren't
+    /// stored larger than they are. Reads detect either form from the value's header. A ratio
+    /// of `1.0` stores uncompressed values whenever compression doesn't make them smaller.
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    pub fn set_compression_min_ratio(mut self, min_ratio: f32) -> Self {
+        self.compression_min_ratio = Some(min_ratio);
+        self
+    }
+
+    /// Compress values with the zstd `dictionary`, trained on values like the cached ones,
+    /// when zstd compression is set. Values record the id of the dictionary they were compressed
+    /// with, so values compressed without a dictionary are still read, while values compressed
+    /// with another dictionary fail with `RedisCacheError::CompressionError`.
+    #[cfg(feature = "redis_zstd")]
+    pub fn set_compression_dictionary(mut self, dictionary: Vec<u8>) -> Self {
+        self.compression_dictionary = Some(dictionary);
+        self
+    }
+
+    /// Encrypt values with AES-256-GCM using `key` before storing them, after compression if
+    /// any. Each value is stored with its own random nonce. Values that aren't encrypted are
+    /// still read, so existing caches can switch to encryption, while values encrypted with
+    /// another key fail with `RedisCacheError::EncryptionError`.
+    #[cfg(feature = "redis_encryption")]
+    pub fn set_encryption(mut self, key: [u8; 32]) -> Self {
+        self.encryption_key = Some(key);
+        self
+    }
+
+    /// Consult `injector` at the start of every get, set, and remove. Returning
+    /// `Some(error)` fails the operation with that error without contacting redis.
+    #[cfg(feature = "chaos")]
+    pub fn set_fault_injector(mut self, injector: FaultInjector) -> Self {
+        self.fault_injector = Some(injector);
+        self
+    }
+
+    /// Return the current connection string or load from the env var: CACHED_REDIS_CONNECTION_STRING
+    pub fn connection_string(&self) -> Result<String, RedisCacheBuildError> {
+        match self.connection_string {
+            Some(ref s) => Ok(s.to_string()),
+            None => {
+                std::env::var(ENV_KEY).map_err(|e| RedisCacheBuildError::MissingConnectionString {
+                    env_key: ENV_KEY.to_string(),
+                    error: e,
+                })
             }
         }
+    }
 
-        /// Specify the cache TTL/lifespan in seconds
-        pub fn set_lifespan(mut self, seconds: u64) -> Self {
-            self.seconds = seconds;
-            self
+    /// Check each step `build` goes through to connect to redis, continuing where `build`
+    /// would stop at the first error: where the connection string comes from, whether it
+    /// parses, whether the server is reachable, and whether it answers a `PING`, which
+    /// includes authenticating. Steps after a failed step are skipped. This blocks on the
+    /// network for up to a few seconds per step.
+    pub fn diagnose(&self) -> BuildDiagnostics {
+        let mut diagnostics = BuildDiagnostics {
+            connection_string: DiagnosticCheck::Skipped,
+            parse: DiagnosticCheck::Skipped,
+            reachable: DiagnosticCheck::Skipped,
+            ping: DiagnosticCheck::Skipped,
+        };
+        let s = match self.connection_string {
+            Some(ref s) => {
+                diagnostics.connection_string =
+                    DiagnosticCheck::Passed("set on the builder".to_string());
+                s.clone()
+            }
+            None => match self.connection_string() {
+                Ok(s) => {
+                    diagnostics.connection_string =
+                        DiagnosticCheck::Passed(format!("read from env var {}", ENV_KEY));
+                    s
+                }
+                Err(e) => {
+                    diagnostics.connection_string = DiagnosticCheck::Failed(e.to_string());
+                    return diagnostics;
+                }
+            },
+        };
+        let client = match redis::Client::open(s) {
+            Ok(client) => client,
+            Err(e) => {
+                diagnostics.parse = DiagnosticCheck::Failed(e.to_string());
+                return diagnostics;
+            }
+        };
+        let addr = &client.get_connection_info().addr;
+        diagnostics.parse = DiagnosticCheck::Passed(format!("server at {}", addr));
+        diagnostics.reachable = check_reachable(addr);
+        if !diagnostics.reachable.is_passed() {
+            return diagnostics;
         }
+        diagnostics.ping = check_ping(&client);
+        diagnostics
+    }
 
-        /// Specify whether cache hits refresh the TTL
-        pub fn set_refresh(mut self, refresh: bool) -> Self {
-            self.refresh = refresh;
-            self
+    /// The warnings about this builder's settings, kept by the cache it builds
+    fn config_warnings(&self) -> Vec<ConfigWarning> {
+        #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+        let unused = self.compression.is_none() && self.compression_min_ratio.is_some();
+        #[cfg(not(any(feature = "redis_zstd", feature = "redis_gzip")))]
+        let unused = false;
+        #[cfg(feature = "redis_zstd")]
+        let unused = unused
+            || (self.compression != Some(CompressionKind::Zstd)
+                && self.compression_dictionary.is_some());
+        config_warnings(
+            self.seconds,
+            self.lifespan_jitter,
+            self.refresh,
+            self.refresh_expire_option.is_some(),
+            unused,
+        )
+    }
+
+    fn create_connections(&self) -> Result<Connections, RedisCacheBuildError> {
+        let s = self.connection_string()?;
+        let client = ClientManager {
+            client: redis::Client::open(s)?,
+            command_timeout: self.command_timeout,
+        };
+        if !self.pooled {
+            return Ok(Connections::Direct {
+                client,
+                observer: self.connection_observer.clone(),
+            });
+        }
+        let mut builder = r2d2::Pool::builder().test_on_check_out(self.test_on_check_out);
+        if let Some(max_size) = self.pool_max_size {
+            builder = builder.max_size(max_size);
+        }
+        if let Some(timeout) = self.pool_connection_timeout {
+            builder = builder.connection_timeout(timeout);
         }
+        if let Some(ref observer) = self.connection_observer {
+            builder = builder.event_handler(Box::new(ObserverHandler(observer.clone())));
+        }
+        let pool: r2d2::Pool<ClientManager> = builder.build(client)?;
+        Ok(Connections::Pool(pool))
+    }
 
-        /// Set the prefix for the keys
-        pub fn set_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
-            self.prefix = generate_prefix(prefix.as_ref());
-            self
+    pub fn build(self) -> Result<RedisCache<K, V>, RedisCacheBuildError> {
+        validate_prefix(&self.prefix)?;
+        if let Some(ref fallback) = self.fallback_prefix {
+            validate_prefix(fallback)?;
+        }
+        if let Some(ref tag) = self.hash_tag {
+            validate_hash_tag(tag)?;
+        }
+        let env_namespace = load_env_namespace(self.env_namespace_from.as_deref())?;
+        let connections = self.create_connections()?;
+        // caches without a pool detect the version on first use instead, to connect lazily
+        let server_version = once_cell::sync::OnceCell::new();
+        if self.pooled || self.refresh_expire_option.is_some() {
+            let _ = server_version.set(parse_server_version(&connections.server_info()?));
+        }
+        let refresh_expire_option = self.refresh_expire_option.filter(|_| {
+            version_supports(
+                server_version.get().copied().flatten(),
+                EXPIRE_OPTIONS_VERSION,
+            )
+        });
+        let config_warnings = self.config_warnings();
+        Ok(RedisCache {
+            config_warnings,
+            server_version,
+            seconds: self.seconds,
+            refresh: self.refresh,
+            negative_seconds: self.negative_seconds.unwrap_or(self.seconds),
+            connection_string: self.connection_string()?,
+            connections,
+            fail_fast_on_exhaustion: self.fail_fast_on_exhaustion,
+            prefix: hash_tagged(
+                self.hash_tag.as_deref(),
+                generate_prefix(env_namespace.as_deref(), &self.prefix),
+            ),
+            fallback_prefix: self.fallback_prefix.as_deref().map(|fallback| {
+                hash_tagged(
+                    self.hash_tag.as_deref(),
+                    generate_prefix(env_namespace.as_deref(), fallback),
+                )
+            }),
+            name: self.name,
+            env_namespace,
+            hash_tag: self.hash_tag,
+            ignore_set_errors: self.ignore_set_errors,
+            event_sink: self.event_sink,
+            write_wait: self.write_wait,
+            health_cache_ttl: self.health_cache_ttl,
+            batch_chunk_size: self.batch_chunk_size,
+            health: std::sync::Mutex::new(None),
+            soft_remove: self.soft_remove,
+            lifespan_jitter: self.lifespan_jitter,
+            key_hasher: self.key_hasher,
+            ttl_fn: self.ttl_fn,
+            key_normalization: self.key_normalization,
+            max_keys: self.max_keys,
+            track_recency: self.track_recency,
+            value_chunk_size: self.value_chunk_size,
+            refresh_expire_option,
+            codec: Codec {
+                custom: self.codec,
+                fallback: self.fallback_codec,
+                json: self.json_options,
+                max_read_bytes: self.max_read_bytes,
+                checksum: self.checksum,
+                #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                compression: self.compression,
+                #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                compression_min_ratio: self.compression_min_ratio,
+                #[cfg(feature = "redis_zstd")]
+                dictionary: self
+                    .compression_dictionary
+                    .as_deref()
+                    .map(ZstdDictionary::new),
+                #[cfg(feature = "redis_encryption")]
+                cipher: self
+                    .encryption_key
+                    .map(|key| aes_gcm::KeyInit::new(&key.into())),
+            },
+            #[cfg(feature = "chaos")]
+            fault_injector: self.fault_injector,
+            stats: Stats::default(),
+            _phantom_k: self._phantom_k,
+            _phantom_v: self._phantom_v,
+        })
+    }
+
+    /// Build a `ShardedRedisCache`, spreading keys across a standalone redis instance per
+    /// connection string. Every shard is built with this builder's settings, including its
+    /// pool settings, in place of its connection string. A shard failing to build is reported
+    /// as `RedisCacheBuildError::Shard`, identifying it by index and connection string.
+    pub fn build_sharded(
+        self,
+        connection_strings: &[&str],
+    ) -> Result<ShardedRedisCache<K, V>, RedisCacheBuildError> {
+        if connection_strings.is_empty() {
+            return Err(RedisCacheBuildError::NoShards);
         }
+        let shards = connection_strings
+            .iter()
+            .enumerate()
+            .map(|(index, cs)| {
+                self.clone()
+                    .set_connection_string(cs)
+                    .build()
+                    .map_err(|error| RedisCacheBuildError::Shard {
+                        index,
+                        connection_string: redact_credentials(cs),
+                        error: Box::new(error),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ShardedRedisCache {
+            ring: HashRing::new(shards.len()),
+            shards,
+        })
+    }
 
-        /// Set the connection string for redis
-        pub fn set_connection_string(mut self, cs: &str) -> Self {
-            self.connection_string = Some(cs.to_string());
-            self
+    /// Build a `RedisListCache`, caching ordered lists of values under each key
+    pub fn build_list(self) -> Result<RedisListCache<K, V>, RedisCacheBuildError> {
+        Ok(RedisListCache {
+            max_len: self.max_list_len,
+            cache: self.build()?,
+        })
+    }
+
+    /// Build a `RedisStreamCache`, caching append-only streams of events under each key
+    pub fn build_stream(self) -> Result<RedisStreamCache<K, V>, RedisCacheBuildError> {
+        Ok(RedisStreamCache {
+            max_len: self.max_stream_len,
+            cache: self.build()?,
+        })
+    }
+}
+
+impl<K> RedisCacheBuilder<K, Vec<u8>>
+where
+    K: Display,
+{
+    /// Build a `RedisBytesCache`, storing `Bytes` values as is instead of serializing them
+    pub fn build_bytes(self) -> Result<RedisBytesCache<K>, RedisCacheBuildError> {
+        Ok(RedisBytesCache {
+            cache: self.build()?,
+        })
+    }
+}
+
+/// Snapshot of the operation counters of a redis cache store
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of `cache_get` calls that found a value
+    pub hits: u64,
+    /// Number of `cache_get` calls that found no value
+    pub misses: u64,
+    /// Number of successful `cache_set` calls
+    pub sets: u64,
+    /// Number of successful `cache_remove` calls
+    pub removes: u64,
+    /// Number of operations that returned an error
+    pub errors: u64,
+}
+
+/// Serializable point-in-time snapshot of a redis cache store, see `RedisCache::cache_stats_snapshot`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[non_exhaustive]
+pub struct StatsSnapshot {
+    /// Number of `cache_get` calls that found a value
+    pub hits: u64,
+    /// Number of `cache_get` calls that found no value
+    pub misses: u64,
+    /// Number of successful `cache_set` calls
+    pub sets: u64,
+    /// Number of successful `cache_remove` calls
+    pub removes: u64,
+    /// Number of operations that returned an error
+    pub errors: u64,
+    /// Number of connections acquired for operations, or opened for them without a pool
+    pub acquisitions: u64,
+    /// Number of acquisitions that found no idle pooled connection and had to wait for one.
+    /// A high share of waits, or a long `acquisition_micros` for each acquisition, points to
+    /// a pool too small for the load rather than a slow redis.
+    pub waited_acquisitions: u64,
+    /// Total time spent acquiring connections, in microseconds
+    pub acquisition_micros: u64,
+    /// Estimated number of keys of the cache, see `RedisCache::cache_approx_len`, or `None`
+    /// if redis couldn't be reached for the estimate
+    pub approx_len: Option<u64>,
+    /// Seconds since the cache was built or its counters were last reset
+    pub uptime_seconds: u64,
+}
+
+struct Stats {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    sets: AtomicU64,
+    removes: AtomicU64,
+    errors: AtomicU64,
+    acquisitions: AtomicU64,
+    waited_acquisitions: AtomicU64,
+    acquisition_micros: AtomicU64,
+    reset_at: std::sync::Mutex<Instant>,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats {
+            hits: AtomicU64::default(),
+            misses: AtomicU64::default(),
+            sets: AtomicU64::default(),
+            removes: AtomicU64::default(),
+            errors: AtomicU64::default(),
+            acquisitions: AtomicU64::default(),
+            waited_acquisitions: AtomicU64::default(),
+            acquisition_micros: AtomicU64::default(),
+            reset_at: std::sync::Mutex::new(Instant::now()),
         }
+    }
+}
 
-        /// Return the current connection string or load from the env var: CACHED_REDIS_CONNECTION_STRING
-        pub fn connection_string(&self) -> Result<String, RedisCacheBuildError> {
-            match self.connection_string {
-                Some(ref s) => Ok(s.to_string()),
-                None => std::env::var(ENV_KEY).map_err(|e| {
-                    RedisCacheBuildError::MissingConnectionString {
-                        env_key: ENV_KEY.to_string(),
-                        error: e,
-                    }
-                }),
+impl Stats {
+    fn record_get<V, E>(&self, res: &Result<Option<V>, E>) {
+        match res {
+            Ok(Some(_)) => self.hits.fetch_add(1, Ordering::Relaxed),
+            Ok(None) => self.misses.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    fn record_set<T, E>(&self, res: &Result<T, E>) {
+        match res {
+            Ok(_) => self.sets.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    fn record_remove<T, E>(&self, res: &Result<T, E>) {
+        match res {
+            Ok(_) => self.removes.fetch_add(1, Ordering::Relaxed),
+            Err(_) => self.errors.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    /// Record a connection acquisition that started at `started`
+    fn record_acquire(&self, started: Instant, waited: bool) {
+        self.acquisitions.fetch_add(1, Ordering::Relaxed);
+        if waited {
+            self.waited_acquisitions.fetch_add(1, Ordering::Relaxed);
+        }
+        self.acquisition_micros
+            .fetch_add(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            sets: self.sets.load(Ordering::Relaxed),
+            removes: self.removes.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Swap every counter with zero, so increments racing with the
+    /// snapshot are counted in either this window or the next, never lost.
+    fn take(&self) -> CacheStats {
+        *self.reset_at.lock().unwrap_or_else(|e| e.into_inner()) = Instant::now();
+        self.acquisitions.swap(0, Ordering::Relaxed);
+        self.waited_acquisitions.swap(0, Ordering::Relaxed);
+        self.acquisition_micros.swap(0, Ordering::Relaxed);
+        CacheStats {
+            hits: self.hits.swap(0, Ordering::Relaxed),
+            misses: self.misses.swap(0, Ordering::Relaxed),
+            sets: self.sets.swap(0, Ordering::Relaxed),
+            removes: self.removes.swap(0, Ordering::Relaxed),
+            errors: self.errors.swap(0, Ordering::Relaxed),
+        }
+    }
+
+    fn serializable(&self, approx_len: Option<u64>) -> StatsSnapshot {
+        let stats = self.snapshot();
+        let reset_at = *self.reset_at.lock().unwrap_or_else(|e| e.into_inner());
+        StatsSnapshot {
+            hits: stats.hits,
+            misses: stats.misses,
+            sets: stats.sets,
+            removes: stats.removes,
+            errors: stats.errors,
+            acquisitions: self.acquisitions.load(Ordering::Relaxed),
+            waited_acquisitions: self.waited_acquisitions.load(Ordering::Relaxed),
+            acquisition_micros: self.acquisition_micros.load(Ordering::Relaxed),
+            approx_len,
+            uptime_seconds: reset_at.elapsed().as_secs(),
+        }
+    }
+}
+
+/// The result of `cache_get_detailed`
+///
+/// Redis removes expired keys itself, so an expired value can't be told apart
+/// from one that was never set and both are reported as `Miss`.
+#[derive(Debug)]
+pub enum GetOutcome<V> {
+    /// A value was cached
+    Hit(V),
+    /// No value was cached
+    Miss,
+    /// The key was cached as known to have no value, see `cache_set_miss`
+    Negative,
+    /// The value was soft-removed and is within its grace period, see `RedisCacheBuilder::set_soft_remove`
+    Removed(V),
+    /// A value was cached but couldn't be deserialized
+    Corrupt(RedisCacheError),
+}
+
+impl<V> GetOutcome<V> {
+    /// Convert into the value, if there was a hit
+    pub fn into_hit(self) -> Option<V> {
+        match self {
+            GetOutcome::Hit(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    fn as_result(&self) -> Result<Option<()>, ()> {
+        match self {
+            GetOutcome::Hit(_) => Ok(Some(())),
+            GetOutcome::Miss | GetOutcome::Negative | GetOutcome::Removed(_) => Ok(None),
+            GetOutcome::Corrupt(_) => Err(()),
+        }
+    }
+}
+
+/// A key made of several components, such as `CompositeKey::new().push(user_id).push(page)`
+///
+/// Components are joined with `:`, escaping `:` and `\` within them, so distinct lists of
+/// components always format to distinct keys. Formatting a tuple instead can't tell
+/// `("a:b", "c")` and `("a", "b:c")` apart. Tuples of two to four components convert with `From`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct CompositeKey {
+    parts: Vec<String>,
+}
+
+impl CompositeKey {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a component
+    pub fn push<T: Display>(mut self, part: T) -> Self {
+        self.parts.push(part.to_string());
+        self
+    }
+
+    /// The components of the key
+    pub fn parts(&self) -> &[String] {
+        &self.parts
+    }
+}
+
+impl Display for CompositeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, part) in self.parts.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            for c in part.chars() {
+                if c == ':' || c == '\\' {
+                    f.write_str("\\")?;
+                }
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for CompositeKey {
+    type Err = std::convert::Infallible;
+
+    /// Split a formatted key back into its components
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = vec![String::new()];
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => parts.last_mut().unwrap().extend(chars.next()),
+                ':' => parts.push(String::new()),
+                c => parts.last_mut().unwrap().push(c),
+            }
+        }
+        Ok(CompositeKey { parts })
+    }
+}
+
+macro_rules! composite_key_from_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: Display),+> From<($($name,)+)> for CompositeKey {
+            #[allow(non_snake_case)]
+            fn from(($($name,)+): ($($name,)+)) -> Self {
+                CompositeKey::new()$(.push($name))+
             }
         }
+    };
+}
+
+composite_key_from_tuple!(A, B);
+composite_key_from_tuple!(A, B, C);
+composite_key_from_tuple!(A, B, C, D);
+
+/// Operations reported through a cache event sink
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheOp {
+    Get,
+    Set,
+    Remove,
+}
+
+/// An operation performed by a redis cache store, see `set_event_sink`
+#[derive(Debug, Clone)]
+pub struct CacheEvent {
+    /// The operation performed
+    pub op: CacheOp,
+    /// The full redis key, including the prefix
+    pub key: String,
+    /// Whether a value was present: a hit for `Get`, a replaced or removed value for `Set`/`Remove`
+    pub hit: bool,
+    /// Time taken by the operation, including acquiring a connection
+    pub duration: Duration,
+}
+
+/// Hook consulted before every operation when the `chaos` feature is enabled,
+/// see `set_fault_injector`
+#[cfg(feature = "chaos")]
+pub type FaultInjector = Arc<dyn Fn(CacheOp, &str) -> Option<RedisCacheError> + Send + Sync>;
+
+/// Changes in the connections of a redis cache store, see `set_connection_observer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// A new connection to redis was opened
+    Connected,
+    /// A connection was closed, or an operation found it dropped
+    Disconnected,
+}
+
+/// Callback receiving the `ConnectionEvent`s of a redis cache store
+pub type ConnectionObserver = Arc<dyn Fn(ConnectionEvent) + Send + Sync>;
+
+/// Hashes the formatted keys of a redis cache store, see `set_key_hasher`
+pub type KeyHasher = Arc<dyn Fn(&str) -> String + Send + Sync>;
+
+/// Computes the lifespan in seconds of a value from the value itself, see `set_ttl_fn`
+pub type TtlFn<V> = Arc<dyn Fn(&V) -> u64 + Send + Sync>;
+
+/// A coherent combination of the error handling settings of a redis cache store, see
+/// `set_resilience`
+///
+/// Start from a preset and adjust its fields, or the individual builder setters called
+/// after `set_resilience`, to fine-tune it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ResiliencePolicy {
+    /// Swallow errors of `cache_set`, see `set_ignore_set_errors`
+    pub ignore_set_errors: bool,
+    /// Fail instead of waiting when every pooled connection is in use, see
+    /// `RedisCacheBuilder::set_fail_fast_on_exhaustion`. Only applies to `RedisCache`.
+    pub fail_fast_on_exhaustion: bool,
+    /// Serve the last known value when a loader fails, see
+    /// `AsyncRedisCacheBuilder::set_serve_stale_on_loader_error`. Only applies to
+    /// `AsyncRedisCache`.
+    pub serve_stale_on_loader_error: bool,
+}
+
+impl ResiliencePolicy {
+    /// Every error is returned to the caller, the builders' defaults
+    pub const STRICT: ResiliencePolicy = ResiliencePolicy {
+        ignore_set_errors: false,
+        fail_fast_on_exhaustion: false,
+        serve_stale_on_loader_error: false,
+    };
+
+    /// The cache is optional: writes are best-effort and operations don't wait for busy
+    /// connections, so an unhealthy redis slows callers down as little as possible
+    pub const BEST_EFFORT: ResiliencePolicy = ResiliencePolicy {
+        ignore_set_errors: true,
+        fail_fast_on_exhaustion: true,
+        serve_stale_on_loader_error: false,
+    };
+
+    /// The cache shields callers from failures: writes are best-effort and stale values are
+    /// served when loaders fail, while operations wait for busy connections
+    pub const RESILIENT: ResiliencePolicy = ResiliencePolicy {
+        ignore_set_errors: true,
+        fail_fast_on_exhaustion: false,
+        serve_stale_on_loader_error: true,
+    };
+}
+
+impl Default for ResiliencePolicy {
+    fn default() -> Self {
+        ResiliencePolicy::STRICT
+    }
+}
+
+/// How the keys of a redis cache store are normalized, see `set_key_normalizer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Keys are used as formatted
+    None,
+    /// Keys are lowercased
+    Lowercase,
+}
+
+impl Normalization {
+    fn apply(self, key: String) -> String {
+        match self {
+            Normalization::None => key,
+            Normalization::Lowercase => key.to_lowercase(),
+        }
+    }
+}
+
+/// Format `key`, normalized and then hashed if a key hasher is set
+fn format_key<K: Display>(
+    normalization: Normalization,
+    hasher: &Option<KeyHasher>,
+    key: &K,
+) -> String {
+    let key = normalization.apply(key.to_string());
+    match hasher {
+        None => key,
+        Some(hasher) => hasher(&key),
+    }
+}
+
+/// The default key hasher, the hex encoded SHA-256 digest of `key`
+fn sha256_hex(key: &str) -> String {
+    use sha2::{Digest, Sha256};
+    Sha256::digest(key.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Where the sync caches take their connections from, see `RedisCacheBuilder::without_pool`
+enum Connections {
+    Pool(r2d2::Pool<ClientManager>),
+    Direct {
+        client: ClientManager,
+        observer: Option<ConnectionObserver>,
+    },
+}
+
+impl Connections {
+    /// Acquire a connection for a single operation, along with whether it had to wait for
+    /// a pooled connection because none was idle. With `fail_fast`, fail instead of waiting
+    /// when every pooled connection is in use.
+    fn get(&self, fail_fast: bool) -> (Result<Connection, RedisCacheError>, bool) {
+        match self {
+            Connections::Pool(pool) => match pool.try_get() {
+                Some(conn) => (Ok(Connection::Pooled(conn)), false),
+                None if fail_fast => (Err(RedisCacheError::PoolExhausted), false),
+                None => (pool.get().map(Connection::Pooled).map_err(Into::into), true),
+            },
+            Connections::Direct { client, observer } => {
+                let conn = match client.open() {
+                    Ok(conn) => conn,
+                    Err(e) => return (Err(e.into()), false),
+                };
+                if let Some(observer) = observer {
+                    observer(ConnectionEvent::Connected);
+                }
+                let conn = Connection::Direct(DirectConnection {
+                    conn,
+                    observer: observer.clone(),
+                });
+                (Ok(conn), false)
+            }
+        }
+    }
+
+    fn server_info(&self) -> Result<String, RedisCacheBuildError> {
+        let mut cmd = redis::cmd("INFO");
+        cmd.arg("server");
+        match self {
+            Connections::Pool(pool) => Ok(cmd.query(&mut *pool.get()?)?),
+            Connections::Direct { client, .. } => Ok(cmd.query(&mut client.open()?)?),
+        }
+    }
+}
+
+/// Opens connections with the command timeout of `RedisCacheBuilder::set_command_timeout`
+struct ClientManager {
+    client: redis::Client,
+    command_timeout: Option<Duration>,
+}
+
+impl ClientManager {
+    fn open(&self) -> redis::RedisResult<GuardedConnection> {
+        let conn = self.client.get_connection()?;
+        conn.set_read_timeout(self.command_timeout)?;
+        conn.set_write_timeout(self.command_timeout)?;
+        Ok(GuardedConnection {
+            conn,
+            timed_out: false,
+        })
+    }
+}
+
+impl r2d2::ManageConnection for ClientManager {
+    type Connection = GuardedConnection;
+    type Error = redis::RedisError;
+
+    fn connect(&self) -> redis::RedisResult<GuardedConnection> {
+        self.open()
+    }
+
+    fn is_valid(&self, conn: &mut GuardedConnection) -> redis::RedisResult<()> {
+        redis::cmd("PING").query(conn)
+    }
+
+    fn has_broken(&self, conn: &mut GuardedConnection) -> bool {
+        !redis::ConnectionLike::is_open(conn)
+    }
+}
+
+/// A connection that counts as closed once a command timed out, since the late reply would
+/// be read as the reply to the next command
+struct GuardedConnection {
+    conn: redis::Connection,
+    timed_out: bool,
+}
+
+impl GuardedConnection {
+    fn guard<T>(&mut self, res: redis::RedisResult<T>) -> redis::RedisResult<T> {
+        if matches!(res, Err(ref e) if e.is_timeout()) {
+            self.timed_out = true;
+        }
+        res
+    }
+}
+
+impl redis::ConnectionLike for GuardedConnection {
+    fn req_packed_command(&mut self, cmd: &[u8]) -> redis::RedisResult<redis::Value> {
+        let res = self.conn.req_packed_command(cmd);
+        self.guard(res)
+    }
+
+    fn req_packed_commands(
+        &mut self,
+        cmd: &[u8],
+        offset: usize,
+        count: usize,
+    ) -> redis::RedisResult<Vec<redis::Value>> {
+        let res = self.conn.req_packed_commands(cmd, offset, count);
+        self.guard(res)
+    }
+
+    fn get_db(&self) -> i64 {
+        self.conn.get_db()
+    }
+
+    fn check_connection(&mut self) -> bool {
+        !self.timed_out && self.conn.check_connection()
+    }
+
+    fn is_open(&self) -> bool {
+        !self.timed_out && self.conn.is_open()
+    }
+}
+
+/// A connection acquired from `Connections`
+enum Connection {
+    Pooled(r2d2::PooledConnection<ClientManager>),
+    Direct(DirectConnection),
+}
+
+/// A connection opened for a single operation, reporting its close to the observer
+struct DirectConnection {
+    conn: GuardedConnection,
+    observer: Option<ConnectionObserver>,
+}
+
+impl Drop for DirectConnection {
+    fn drop(&mut self) {
+        if let Some(ref observer) = self.observer {
+            observer(ConnectionEvent::Disconnected);
+        }
+    }
+}
+
+impl std::ops::Deref for Connection {
+    type Target = GuardedConnection;
+
+    fn deref(&self) -> &GuardedConnection {
+        match self {
+            Connection::Pooled(conn) => conn,
+            Connection::Direct(direct) => &direct.conn,
+        }
+    }
+}
+
+impl std::ops::DerefMut for Connection {
+    fn deref_mut(&mut self) -> &mut GuardedConnection {
+        match self {
+            Connection::Pooled(conn) => conn,
+            Connection::Direct(direct) => &mut direct.conn,
+        }
+    }
+}
+
+/// The keys watched on a connection, unwatched when dropped unless `EXEC` already did so, so
+/// that errors and panics between `WATCH` and `EXEC` don't leave them watched in the pool
+struct Watch<'a> {
+    conn: &'a mut GuardedConnection,
+    executed: bool,
+}
+
+impl<'a> Watch<'a> {
+    fn new(conn: &'a mut GuardedConnection, key: &str) -> Result<Self, RedisCacheError> {
+        redis::cmd("WATCH").arg(key).query::<()>(conn)?;
+        Ok(Watch {
+            conn,
+            executed: false,
+        })
+    }
+}
+
+impl Drop for Watch<'_> {
+    fn drop(&mut self) {
+        if !self.executed {
+            let _ = redis::cmd("UNWATCH").query::<()>(self.conn);
+        }
+    }
+}
+
+/// Forwards the events of a connection pool to a `ConnectionObserver`
+struct ObserverHandler(ConnectionObserver);
+
+impl std::fmt::Debug for ObserverHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ObserverHandler")
+    }
+}
+
+impl r2d2::HandleEvent for ObserverHandler {
+    fn handle_acquire(&self, _event: r2d2::event::AcquireEvent) {
+        (self.0)(ConnectionEvent::Connected)
+    }
+
+    fn handle_release(&self, _event: r2d2::event::ReleaseEvent) {
+        (self.0)(ConnectionEvent::Disconnected)
+    }
+}
+
+#[cfg(feature = "redis_bb8")]
+#[async_trait::async_trait]
+impl bb8::CustomizeConnection<redis::aio::Connection, redis::RedisError> for ObserverHandler {
+    async fn on_acquire(
+        &self,
+        _conn: &mut redis::aio::Connection,
+    ) -> Result<(), redis::RedisError> {
+        (self.0)(ConnectionEvent::Connected);
+        Ok(())
+    }
+}
+
+/// Update the stats for `op` and forward an event to the sink if one is configured.
+/// Events are dropped instead of blocking when the sink is full or disconnected.
+fn observe<T, E>(
+    stats: &Stats,
+    event_sink: &Option<SyncSender<CacheEvent>>,
+    op: CacheOp,
+    key: &str,
+    started: Instant,
+    res: &Result<Option<T>, E>,
+) {
+    match op {
+        CacheOp::Get => stats.record_get(res),
+        CacheOp::Set => stats.record_set(res),
+        CacheOp::Remove => stats.record_remove(res),
+    }
+    if let Some(sink) = event_sink {
+        let _ = sink.try_send(CacheEvent {
+            op,
+            key: key.to_string(),
+            hit: matches!(res, Ok(Some(_))),
+            duration: started.elapsed(),
+        });
+    }
+}
+
+/// Cache store backed by redis
+///
+/// Values have a ttl applied and enforced by redis.
+///
+/// Values are stored wrapped in an envelope, so a present key always reads as `Ok(Some(value))`,
+/// even for values serializing to `null` or to nothing, like `None` when `V` is an `Option`.
+/// Only missing keys, and negative entries of `cache_set_miss`, read as `Ok(None)`.
+pub struct RedisCache<K, V> {
+    pub(super) seconds: u64,
+    pub(super) refresh: bool,
+    negative_seconds: u64,
+    pub(super) prefix: String,
+    fallback_prefix: Option<String>,
+    name: Option<String>,
+    env_namespace: Option<String>,
+    hash_tag: Option<String>,
+    ignore_set_errors: bool,
+    event_sink: Option<SyncSender<CacheEvent>>,
+    write_wait: Option<(u32, Duration)>,
+    health_cache_ttl: Duration,
+    batch_chunk_size: Option<usize>,
+    /// When the last `is_healthy` ping was sent, and whether it succeeded
+    health: std::sync::Mutex<Option<(Instant, bool)>>,
+    soft_remove: Option<u64>,
+    lifespan_jitter: u64,
+    key_hasher: Option<KeyHasher>,
+    ttl_fn: Option<TtlFn<V>>,
+    key_normalization: Normalization,
+    max_keys: Option<u64>,
+    track_recency: bool,
+    value_chunk_size: Option<usize>,
+    refresh_expire_option: Option<ExpireOption>,
+    codec: Codec<V>,
+    #[cfg(feature = "chaos")]
+    fault_injector: Option<FaultInjector>,
+    connection_string: String,
+    connections: Connections,
+    fail_fast_on_exhaustion: bool,
+    /// The server's version, see `RedisCache::server_version`
+    server_version: once_cell::sync::OnceCell<Option<ServerVersion>>,
+    stats: Stats,
+    config_warnings: Vec<ConfigWarning>,
+    _phantom_k: PhantomData<K>,
+    _phantom_v: PhantomData<V>,
+}
+
+impl<K, V> RedisCache<K, V>
+where
+    K: Display,
+    V: Serialize + DeserializeOwned,
+{
+    #[allow(clippy::new_ret_no_self)]
+    /// Initialize a `RedisCacheBuilder`
+    pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> RedisCacheBuilder<K, V> {
+        RedisCacheBuilder::new(prefix, seconds)
+    }
+
+    /// The lifespan of `val` when set without an explicit one, see `set_ttl_fn`
+    fn lifespan_of(&self, val: &V) -> u64 {
+        match self.ttl_fn {
+            Some(ref ttl_fn) => ttl_fn(val).max(1),
+            None => self.seconds,
+        }
+    }
+
+    fn generate_key(&self, key: &K) -> String {
+        format!(
+            "{}{}",
+            self.prefix,
+            format_key(self.key_normalization, &self.key_hasher, key)
+        )
+    }
+
+    fn generate_key_in(&self, sub_prefix: &str, key: &K) -> String {
+        format!(
+            "{}{}",
+            hash_tagged(
+                self.hash_tag.as_deref(),
+                generate_prefix(self.env_namespace.as_deref(), sub_prefix),
+            ),
+            format_key(self.key_normalization, &self.key_hasher, key)
+        )
+    }
+
+    /// The key under the fallback prefix for `key`, if a fallback prefix is set and `key` is
+    /// under the cache's own prefix
+    fn fallback_key(&self, key: &str) -> Option<String> {
+        let fallback = self.fallback_prefix.as_ref()?;
+        let key = key.strip_prefix(self.prefix.as_str())?;
+        Some(format!("{}{}", fallback, key))
+    }
+
+    /// Acquire a connection for a single operation, see `RedisCacheBuilder::set_fail_fast_on_exhaustion`
+    fn connection(&self) -> Result<Connection, RedisCacheError> {
+        let started = Instant::now();
+        let (res, waited) = self.connections.get(self.fail_fast_on_exhaustion);
+        self.stats.record_acquire(started, waited);
+        res
+    }
+
+    fn observe<T, E>(&self, op: CacheOp, key: &str, started: Instant, res: &Result<Option<T>, E>) {
+        observe(&self.stats, &self.event_sink, op, key, started, res)
+    }
+
+    fn observe_outcome(
+        &self,
+        key: &str,
+        started: Instant,
+        outcome: &Result<GetOutcome<V>, RedisCacheError>,
+    ) {
+        let res = match outcome {
+            Ok(o) => o.as_result(),
+            Err(_) => Err(()),
+        };
+        self.observe(CacheOp::Get, key, started, &res)
+    }
+
+    /// Wrap the error of `res` with the cache's name, if it has one
+    fn named<T>(&self, res: Result<T, RedisCacheError>) -> Result<T, RedisCacheError> {
+        match self.name {
+            None => res,
+            Some(ref name) => res.map_err(|error| RedisCacheError::Named {
+                name: name.clone(),
+                source: Box::new(error),
+            }),
+        }
+    }
+
+    /// The name set with `set_name`
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn inject_fault(&self, _op: CacheOp, _key: &str) -> Result<(), RedisCacheError> {
+        #[cfg(feature = "chaos")]
+        if let Some(err) = self.fault_injector.as_ref().and_then(|f| f(_op, _key)) {
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Return the redis connection string used
+    pub fn connection_string(&self) -> String {
+        self.connection_string.clone()
+    }
+
+    /// Return the distinct prefixes of all `RedisCache`s with keys in the redis at `connection_string`,
+    /// in sorted order. For caches namespaced by environment (see `set_env_namespace_from`) the
+    /// environment is returned instead. The keyspace is walked with `SCAN`, so this is meant
+    /// for introspection rather than regular use.
+    pub fn discover_prefixes(connection_string: &str) -> Result<Vec<String>, RedisCacheError> {
+        let client = redis::Client::open(connection_string)?;
+        let mut conn = client.get_connection()?;
+        let namespace = format!("{}{}", PREFIX_NAMESPACE, KEY_SEPARATOR);
+        let keys: redis::Iter<String> =
+            redis::Commands::scan_match(&mut conn, format!("{}*", namespace))?;
+        let prefixes: std::collections::BTreeSet<String> = keys
+            .filter_map(|key| {
+                let rest = key.strip_prefix(&namespace)?;
+                rest.find(KEY_SEPARATOR).map(|end| rest[..end].to_string())
+            })
+            .collect();
+        Ok(prefixes.into_iter().collect())
+    }
+
+    /// Estimate the number of keys under this cache's prefix by sampling `RANDOMKEY` and
+    /// scaling by `DBSIZE`, in two round-trips instead of walking the keyspace. Estimates are
+    /// coarse, typically within ten percent of the database size, so they suit caches holding a
+    /// sizeable share of their database and dashboards rather than exact accounting.
+    pub fn cache_approx_len(&self) -> Result<u64, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let total: u64 = redis::cmd("DBSIZE").query(&mut *conn)?;
+        if total == 0 {
+            return Ok(0);
+        }
+        let sampled: Vec<Option<Vec<u8>>> = random_keys_pipe().query(&mut *conn)?;
+        Ok(estimate_len(total, &sampled, &self.prefix))
+    }
+
+    /// The server's version, detected once when the cache was built, or on first use for
+    /// caches without a pool. Methods relying on newer commands use a compatible
+    /// implementation on older servers, or return `RedisCacheError::UnsupportedServerVersion`.
+    fn server_version(&self) -> Result<Option<ServerVersion>, RedisCacheError> {
+        self.server_version
+            .get_or_try_init(|| {
+                let mut conn = self.connection()?;
+                let info: String = redis::cmd("INFO").arg("server").query(&mut *conn)?;
+                Ok(parse_server_version(&info))
+            })
+            .copied()
+    }
+
+    /// Send a `PING`, returning an error if redis can't be reached
+    pub fn ping(&self) -> Result<(), RedisCacheError> {
+        let mut conn = self.connection()?;
+        redis::cmd("PING").query::<()>(&mut *conn)?;
+        Ok(())
+    }
+
+    /// Return whether redis was reachable by the last `ping`, sent when the previous result
+    /// is older than the health cache ttl (see `RedisCacheBuilder::set_health_cache_ttl`).
+    /// Concurrent callers wait for a ping in progress instead of sending their own.
+    pub fn is_healthy(&self) -> bool {
+        let mut health = self.health.lock().unwrap_or_else(|e| e.into_inner());
+        match *health {
+            Some((checked, healthy)) if checked.elapsed() < self.health_cache_ttl => healthy,
+            _ => {
+                let healthy = self.ping().is_ok();
+                *health = Some((Instant::now(), healthy));
+                healthy
+            }
+        }
+    }
+
+    /// Return the likely misconfigurations noticed by `build`, see `ConfigWarning`
+    pub fn config_warnings(&self) -> &[ConfigWarning] {
+        &self.config_warnings
+    }
+
+    /// Return the current operation counters
+    pub fn cache_stats(&self) -> CacheStats {
+        self.stats.snapshot()
+    }
+
+    /// Return the current operation counters and reset them to zero
+    pub fn cache_take_stats(&self) -> CacheStats {
+        self.stats.take()
+    }
+
+    /// Reset the operation counters to zero
+    pub fn reset_stats(&self) {
+        self.stats.take();
+    }
+
+    /// Return a serializable snapshot of the operation counters along with the estimated
+    /// number of keys, e.g. for an admin endpoint. The estimate takes two round-trips, see
+    /// `cache_approx_len`, and is left out if redis can't be reached.
+    pub fn cache_stats_snapshot(&self) -> StatsSnapshot {
+        self.stats.serializable(self.cache_approx_len().ok())
+    }
+
+    /// Retrieve a cached value, distinguishing a miss from a value that failed to deserialize.
+    /// Errors talking to redis are still returned as `Err`.
+    pub fn cache_get_detailed(&self, key: &K) -> Result<GetOutcome<V>, RedisCacheError> {
+        let key = self.generate_key(key);
+        let started = Instant::now();
+        let outcome = self.get_raw(&key).map(|raw| self.codec.outcome(&key, raw));
+        self.observe_outcome(&key, started, &outcome);
+        self.named(outcome)
+    }
+
+    /// Retrieve a cached value, or `default` if there is none or it can't be deserialized.
+    /// Values that can't be deserialized are deleted, so the next `cache_set` replaces them
+    /// instead of every read falling back to `default` until they expire. Errors reaching
+    /// redis are still returned. Stats behave as they do for `cache_get_detailed`.
+    pub fn cache_get_or_default(&self, key: &K, default: V) -> Result<V, RedisCacheError> {
+        match self.cache_get_detailed(key)? {
+            GetOutcome::Hit(v) => Ok(v),
+            GetOutcome::Corrupt(_) => {
+                let mut conn = self.connection()?;
+                redis::cmd("DEL")
+                    .arg(self.generate_key(key))
+                    .query::<()>(&mut *conn)?;
+                Ok(default)
+            }
+            GetOutcome::Miss | GetOutcome::Negative | GetOutcome::Removed(_) => Ok(default),
+        }
+    }
+
+    /// Retrieve a cached value stored under `sub_prefix` instead of this cache's prefix.
+    /// Like the cache's own prefix, `sub_prefix` must not contain `:`, otherwise
+    /// `RedisCacheError::InvalidPrefix` is returned.
+    /// Lifespan, refresh, and stats behave as they do for `cache_get`.
+    pub fn cache_get_in(&self, sub_prefix: &str, key: &K) -> Result<Option<V>, RedisCacheError> {
+        check_prefix(sub_prefix)?;
+        let key = self.generate_key_in(sub_prefix, key);
+        let started = Instant::now();
+        let res = self.get_value(&key);
+        self.observe(CacheOp::Get, &key, started, &res);
+        self.named(res)
+    }
+
+    /// Insert a value under `sub_prefix` instead of this cache's prefix and return the previous value.
+    /// Lifespan, stats, and error handling behave as they do for `cache_set`, but an invalid
+    /// `sub_prefix`, see `cache_get_in`, is always an error.
+    pub fn cache_set_in(
+        &self,
+        sub_prefix: &str,
+        key: K,
+        val: V,
+    ) -> Result<Option<V>, RedisCacheError> {
+        check_prefix(sub_prefix)?;
+        let key = self.generate_key_in(sub_prefix, &key);
+        let started = Instant::now();
+        let seconds = self.lifespan_of(&val);
+        let res = self.set_value(&key, val, seconds);
+        self.observe(CacheOp::Set, &key, started, &res);
+        let res = self.named(res);
+        match res {
+            Err(_) if self.ignore_set_errors => Ok(None),
+            res => res,
+        }
+    }
+
+    /// Retrieve the value at `full_key` exactly as given, for keys named by another system.
+    /// This bypasses all prefix logic: neither the cache's prefix, its environment namespace,
+    /// nor the key hasher apply, and the fallback prefix is only consulted for keys that
+    /// happen to start with the cache's prefix. Values must still be in the cache's format.
+    /// Lifespan, refresh, and stats behave as they do for `cache_get`.
+    pub fn cache_get_raw_key(&self, full_key: &str) -> Result<Option<V>, RedisCacheError> {
+        let started = Instant::now();
+        let res = self.get_value(full_key);
+        self.observe(CacheOp::Get, full_key, started, &res);
+        self.named(res)
+    }
+
+    /// Retrieve the encoded bytes of the value at `key` without deserializing it, for serving
+    /// cached payloads verbatim: the value's JSON, or the output of the codec set with
+    /// `RedisCacheBuilder::set_codec`. Compression and encryption are still undone.
+    /// Lifespan, refresh, and stats behave as they do for `cache_get`.
+    pub fn cache_get_bytes(&self, key: &K) -> Result<Option<Vec<u8>>, RedisCacheError> {
+        let key = self.generate_key(key);
+        let started = Instant::now();
+        let res = match self.get_raw(&key) {
+            Ok(Some(raw)) => self.codec.value_bytes(&key, raw),
+            res => res,
+        };
+        self.observe(CacheOp::Get, &key, started, &res);
+        self.named(res)
+    }
+
+    /// Insert a value at `full_key` exactly as given and return the previous value, bypassing
+    /// all prefix logic like `cache_get_raw_key`. Lifespan, stats, and error handling behave
+    /// as they do for `cache_set`.
+    pub fn cache_set_raw_key(&self, full_key: &str, val: V) -> Result<Option<V>, RedisCacheError> {
+        let started = Instant::now();
+        let seconds = self.lifespan_of(&val);
+        let res = self.set_value(full_key, val, seconds);
+        self.observe(CacheOp::Set, full_key, started, &res);
+        let res = self.named(res);
+        match res {
+            Err(_) if self.ignore_set_errors => Ok(None),
+            res => res,
+        }
+    }
+
+    /// Cache `key` as known to have no value, for the negative lifespan (see
+    /// `RedisCacheBuilder::set_negative_lifespan`). `cache_get` treats the key as a miss,
+    /// while `cache_get_detailed` reports `GetOutcome::Negative`. Use
+    /// `cache_set_miss_with_lifespan` to give a single entry its own TTL.
+    pub fn cache_set_miss(&self, key: K) -> Result<(), RedisCacheError> {
+        self.cache_set_miss_with_lifespan(key, self.negative_seconds)
+    }
+
+    /// Cache `key` as known to have no value for `seconds`, see `cache_set_miss`
+    pub fn cache_set_miss_with_lifespan(
+        &self,
+        key: K,
+        seconds: u64,
+    ) -> Result<(), RedisCacheError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let res = self.set_negative(&key, seconds);
+        self.observe(CacheOp::Set, &key, started, &res);
+        let res = self.named(res);
+        match res {
+            Err(_) if self.ignore_set_errors => Ok(()),
+            res => res.map(|_| ()),
+        }
+    }
+
+    fn get_value(&self, key: &str) -> Result<Option<V>, RedisCacheError> {
+        let raw = match self.get_raw(key)? {
+            None => return Ok(None),
+            Some(raw) => raw,
+        };
+        match self.codec.entry_any(key, &raw)? {
+            None => Ok(None),
+            Some((value, migrate)) => {
+                if migrate && self.value_chunk_size.is_none() {
+                    // the value was read fine, so a failing rewrite is left for the next read
+                    let _ = self.rewrite_value(key, &raw, &value);
+                }
+                Ok(Some(value))
+            }
+        }
+    }
+
+    /// Write `value`, read from the entry `raw`, back to `key` in the configured format if `key`
+    /// still holds `raw`, keeping its TTL and recorded lifespan
+    fn rewrite_value(&self, key: &str, raw: &[u8], value: &V) -> Result<(), RedisCacheError> {
+        let keep_ttl = version_supports(self.server_version()?, KEEPTTL_VERSION);
+        let lifespan = self.codec.meta(key, raw)?.lifespan;
+        let val = self.codec.serialize(value, lifespan)?;
+        let mut conn = self.connection()?;
+        rewrite_cmd(key, raw, val, keep_ttl).query::<bool>(&mut *conn)?;
+        Ok(())
+    }
+
+    fn get_raw(&self, key: &str) -> Result<Option<Vec<u8>>, RedisCacheError> {
+        self.inject_fault(CacheOp::Get, key)?;
+        let mut conn = self.connection()?;
+        let get = get_cmd(key, self.fallback_key(key));
+        let raw = if !self.refresh {
+            get.query(&mut *conn)?
+        } else {
+            let mut pipe = redis::pipe();
+            pipe.add_command(get)
+                .add_command(refresh_cmd(key, self.seconds, self.refresh_expire_option))
+                .ignore();
+            // ugh: https://github.com/mitsuhiko/redis-rs/pull/388#issuecomment-910919137
+            let res: (Option<Vec<u8>>,) = pipe.query(&mut *conn)?;
+            res.0
+        };
+        match raw.as_deref().and_then(chunk_manifest) {
+            None => Ok(raw),
+            Some(manifest) => self.get_chunks(&mut conn, key, &manifest, self.refresh),
+        }
+    }
+
+    /// Replace the chunk manifest `raw` read from `key`, if it is one, with the value it points to
+    fn resolve_manifest(
+        &self,
+        conn: &mut GuardedConnection,
+        key: &str,
+        raw: Option<Vec<u8>>,
+    ) -> Result<Option<Vec<u8>>, RedisCacheError> {
+        match raw.as_deref().and_then(chunk_manifest) {
+            None => Ok(raw),
+            Some(manifest) => self.get_chunks(conn, key, &manifest, false),
+        }
+    }
+
+    /// Read and join the parts of the chunked value at `key`, refreshing their lifespan if
+    /// `refresh` is set. Returns `None` if any part is missing.
+    fn get_chunks(
+        &self,
+        conn: &mut GuardedConnection,
+        key: &str,
+        manifest: &ChunkManifest,
+        refresh: bool,
+    ) -> Result<Option<Vec<u8>>, RedisCacheError> {
+        let mut pipe = redis::pipe();
+        for index in 0..manifest.chunks {
+            let part_key = chunk_key(key, index);
+            pipe.get(&part_key);
+            if refresh {
+                pipe.add_command(refresh_cmd(
+                    &part_key,
+                    self.seconds,
+                    self.refresh_expire_option,
+                ))
+                .ignore();
+            }
+        }
+        let parts: Vec<Option<Vec<u8>>> = pipe.query(conn)?;
+        let mut joined = Vec::new();
+        for part in parts {
+            match part {
+                None => return Ok(None),
+                Some(part) => joined.extend(part),
+            }
+        }
+        Ok(Some(joined))
+    }
+
+    /// Decode the previous value `raw` replaced or removed at `key`. If it was a chunked
+    /// manifest, its parts are read and deleted.
+    fn take_previous(
+        &self,
+        conn: &mut GuardedConnection,
+        key: &str,
+        raw: Option<Vec<u8>>,
+    ) -> Result<Option<V>, RedisCacheError> {
+        let raw = match raw.as_deref().and_then(chunk_manifest) {
+            None => raw,
+            Some(manifest) => self.take_chunks(conn, key, &manifest)?,
+        };
+        match raw {
+            None => Ok(None),
+            Some(s) => self.codec.entry(key, s),
+        }
+    }
+
+    /// Read the chunked value at `key` and delete its parts, returning the joined value
+    fn take_chunks(
+        &self,
+        conn: &mut GuardedConnection,
+        key: &str,
+        manifest: &ChunkManifest,
+    ) -> Result<Option<Vec<u8>>, RedisCacheError> {
+        let joined = self.get_chunks(conn, key, manifest, false)?;
+        let part_keys: Vec<String> = (0..manifest.chunks).map(|i| chunk_key(key, i)).collect();
+        redis::cmd("DEL").arg(part_keys).query::<()>(conn)?;
+        Ok(joined)
+    }
+
+    /// Replace the chunk manifests among the `raw` replies to pipelined `ops` with the values
+    /// they point to. Like `remove_value` and `take_previous`, removed chunked values are
+    /// deleted even when soft removal is on, and the parts of removed and replaced ones are.
+    fn resolve_chunks(
+        &self,
+        conn: &mut GuardedConnection,
+        ops: &[(CacheOp, String)],
+        raw: Vec<Option<Vec<u8>>>,
+    ) -> Result<Vec<Option<Vec<u8>>>, RedisCacheError> {
+        ops.iter()
+            .zip(raw)
+            .map(|((op, key), raw)| {
+                let manifest = match raw.as_deref().and_then(chunk_manifest) {
+                    None => return Ok(raw),
+                    Some(manifest) => manifest,
+                };
+                match op {
+                    CacheOp::Get => self.get_chunks(conn, key, &manifest, self.refresh),
+                    CacheOp::Remove if self.soft_remove.is_some() => {
+                        redis::cmd("DEL").arg(key).query::<()>(&mut *conn)?;
+                        self.take_chunks(conn, key, &manifest)
+                    }
+                    CacheOp::Set | CacheOp::Remove => self.take_chunks(conn, key, &manifest),
+                }
+            })
+            .collect()
+    }
+
+    /// Retrieve the values at several already generated keys in one round-trip
+    fn get_generated_multi(&self, keys: &[String]) -> Result<Vec<Option<V>>, RedisCacheError> {
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut pipe = redis::pipe();
+        for key in keys {
+            pipe.get(key);
+            if self.refresh {
+                pipe.add_command(refresh_cmd(key, self.seconds, self.refresh_expire_option))
+                    .ignore();
+            }
+        }
+        let mut conn = self.connection()?;
+        let res: Vec<Option<Vec<u8>>> = pipe.query(&mut *conn)?;
+        keys.iter()
+            .zip(res)
+            .map(|(key, raw)| match raw {
+                None => Ok(None),
+                Some(s) => self.codec.entry(key, s),
+            })
+            .collect()
+    }
+
+    fn set_value(&self, key: &str, val: V, seconds: u64) -> Result<Option<V>, RedisCacheError> {
+        self.inject_fault(CacheOp::Set, key)?;
+        let bytes = self.codec.serialize(&val, Some(seconds))?;
+        if let Some(chunk_size) = self.value_chunk_size.filter(|size| bytes.len() > *size) {
+            return self.set_chunked(key, &bytes, seconds, chunk_size);
+        }
+        let mut conn = self.connection()?;
+        let mut pipe = redis::pipe();
+
+        pipe.get(key);
+        pipe.set_ex::<&str, Vec<u8>>(key, bytes, jittered(seconds, self.lifespan_jitter) as usize)
+            .ignore();
+        self.add_set_hooks(&mut pipe, key);
+
+        let prev = match self.write_wait {
+            None => {
+                let res: (Option<Vec<u8>>,) = pipe.query(&mut *conn)?;
+                res.0
+            }
+            Some((replicas, timeout)) => {
+                pipe.cmd("WAIT")
+                    .arg(replicas)
+                    .arg(timeout.as_millis() as u64);
+                let res: (Option<Vec<u8>>, u32) = pipe.query(&mut *conn)?;
+                check_write_wait(replicas, res.1)?;
+                res.0
+            }
+        };
+        self.take_previous(&mut conn, key, prev)
+    }
+
+    /// Wait for the replicas set with `set_write_wait`, if any, to acknowledge the writes so far
+    fn wait_for_replicas(&self, conn: &mut GuardedConnection) -> Result<(), RedisCacheError> {
+        if let Some((replicas, timeout)) = self.write_wait {
+            let acked: u32 = redis::cmd("WAIT")
+                .arg(replicas)
+                .arg(timeout.as_millis() as u64)
+                .query(conn)?;
+            check_write_wait(replicas, acked)?;
+        }
+        Ok(())
+    }
+
+    /// Add the key index and recency commands of a set of `key` to `pipe`
+    fn add_set_hooks(&self, pipe: &mut redis::Pipeline, key: &str) {
+        if let Some(max_keys) = self.max_keys {
+            let recent_key = self.track_recency.then(|| self.recent_key());
+            pipe.add_command(evict_cmd(&self.key_index_key(), recent_key, key, max_keys))
+                .ignore();
+        }
+        if self.track_recency {
+            pipe.zadd(self.recent_key(), key, unix_millis()).ignore();
+        }
+    }
+
+    /// Store the serialized value `bytes` at `key` in parts of at most `chunk_size` bytes
+    fn set_chunked(
+        &self,
+        key: &str,
+        bytes: &[u8],
+        seconds: u64,
+        chunk_size: usize,
+    ) -> Result<Option<V>, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let prev: Option<Vec<u8>> = redis::cmd("GET").arg(key).query(&mut *conn)?;
+        let prev_chunks = prev
+            .as_deref()
+            .and_then(chunk_manifest)
+            .map_or(0, |manifest| manifest.chunks);
+        let prev = match prev_chunks {
+            0 => prev,
+            chunks => self.get_chunks(&mut conn, key, &ChunkManifest { chunks }, false)?,
+        };
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        let ttl = jittered(seconds, self.lifespan_jitter) as usize;
+        add_chunked(&mut pipe, key, bytes, ttl, chunk_size, prev_chunks)?;
+        self.add_set_hooks(&mut pipe, key);
+        pipe.query::<()>(&mut *conn)?;
+        self.wait_for_replicas(&mut conn)?;
+        match prev {
+            None => Ok(None),
+            Some(s) => self.codec.entry(key, s),
+        }
+    }
+
+    fn set_negative(&self, key: &str, seconds: u64) -> Result<Option<()>, RedisCacheError> {
+        self.inject_fault(CacheOp::Set, key)?;
+        let mut conn = self.connection()?;
+        redis::cmd("SETEX")
+            .arg(key)
+            .arg(seconds)
+            .arg(NEGATIVE_ENTRY)
+            .query::<()>(&mut *conn)?;
+        Ok(None)
+    }
+
+    fn remove_value(&self, key: &str) -> Result<Option<V>, RedisCacheError> {
+        self.inject_fault(CacheOp::Remove, key)?;
+        let mut conn = self.connection()?;
+        let mut pipe = redis::pipe();
+
+        match self.soft_remove {
+            None => {
+                pipe.get(key);
+                pipe.del::<&str>(key).ignore();
+            }
+            Some(grace_seconds) => {
+                pipe.add_command(soft_remove_cmd(key, grace_seconds));
+            }
+        }
+        let res: (Option<Vec<u8>>,) = pipe.query(&mut *conn)?;
+        if self.soft_remove.is_some() && res.0.as_deref().and_then(chunk_manifest).is_some() {
+            // chunked values aren't soft-removed
+            redis::cmd("DEL").arg(key).query::<()>(&mut *conn)?;
+        }
+        self.take_previous(&mut conn, key, res.0)
+    }
+
+    /// Return whether a value is currently cached for `key`. Negative and soft-removed entries
+    /// don't count, as `cache_get` misses on them.
+    pub fn cache_exists(&self, key: &K) -> Result<bool, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let ttl: i64 = live_ttl_cmd(&self.generate_key(key)).query(&mut *conn)?;
+        Ok(ttl != -2)
+    }
+
+    /// Insert a value like `cache_set` and record it under each of `tags`, so it can be removed
+    /// along with every other key of a tag by `cache_invalidate_tag`. Tag sets are stored under
+    /// the cache prefix as `tag:<tag>` and are kept as long as their most recent value.
+    pub fn cache_set_tagged(
+        &self,
+        key: K,
+        val: V,
+        tags: &[String],
+    ) -> Result<Option<V>, RedisCacheError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let res = self.set_tagged_value(&key, val, tags);
+        self.observe(CacheOp::Set, &key, started, &res);
+        let res = self.named(res);
+        match res {
+            Err(_) if self.ignore_set_errors => Ok(None),
+            res => res,
+        }
+    }
+
+    fn set_tagged_value(
+        &self,
+        key: &str,
+        val: V,
+        tags: &[String],
+    ) -> Result<Option<V>, RedisCacheError> {
+        self.inject_fault(CacheOp::Set, key)?;
+        let mut conn = self.connection()?;
+        let tag_keys: Vec<String> = tags.iter().map(|tag| self.tag_key(tag)).collect();
+        let seconds = self.lifespan_of(&val);
+        let res: (Option<Vec<u8>>,) = set_tagged_pipe(
+            key,
+            self.codec.serialize(&val, Some(seconds))?,
+            jittered(seconds, self.lifespan_jitter),
+            &tag_keys,
+            seconds + self.lifespan_jitter,
+        )
+        .query(&mut *conn)?;
+        match res.0 {
+            None => Ok(None),
+            Some(s) => self.codec.entry(key, s),
+        }
+    }
+
+    /// Delete every value recorded under `tag` by `cache_set_tagged`, and the tag itself,
+    /// returning the number of values deleted. Values are deleted even if soft removal is
+    /// enabled. Values that already expired are cleaned up from the tag without being counted.
+    pub fn cache_invalidate_tag(&self, tag: &str) -> Result<u64, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let deleted: u64 = invalidate_tag_cmd(&self.tag_key(tag)).query(&mut *conn)?;
+        Ok(deleted)
+    }
+
+    fn tag_key(&self, tag: &str) -> String {
+        format!("{}{}{}", self.prefix, TAG_KEY_PREFIX, tag)
+    }
+
+    fn key_index_key(&self) -> String {
+        format!("{}{}", self.prefix, KEY_INDEX_KEY)
+    }
+
+    fn recent_key(&self) -> String {
+        format!("{}{}", self.prefix, RECENT_KEY)
+    }
+
+    /// Return up to `n` of the most recently set keys that are still cached, most recent first.
+    /// Requires `RedisCacheBuilder::set_track_recency`. Keys that expired or were removed are
+    /// trimmed from the recency set as they're found, and keys that can't be parsed as `K`,
+    /// like hashed keys, are skipped.
+    pub fn cache_recent(&self, n: usize) -> Result<Vec<K>, RedisCacheError>
+    where
+        K: std::str::FromStr,
+    {
+        let recent_key = self.recent_key();
+        let mut conn = self.connection()?;
+        let mut recent = Vec::new();
+        let mut start = 0;
+        while recent.len() < n {
+            let wanted = n - recent.len();
+            let keys: Vec<String> = redis::cmd("ZREVRANGE")
+                .arg(&recent_key)
+                .arg(start)
+                .arg(start + wanted - 1)
+                .query(&mut *conn)?;
+            if keys.is_empty() {
+                break;
+            }
+            let mut pipe = redis::pipe();
+            for key in &keys {
+                pipe.exists(key);
+            }
+            let exists: Vec<bool> = pipe.query(&mut *conn)?;
+            let (live, stale) = partition_live(keys, exists);
+            if !stale.is_empty() {
+                redis::cmd("ZREM")
+                    .arg(&recent_key)
+                    .arg(&stale)
+                    .query::<()>(&mut *conn)?;
+            }
+            start += live.len();
+            let exhausted = live.len() + stale.len() < wanted;
+            recent.extend(parse_keys(&self.prefix, live));
+            if exhausted {
+                break;
+            }
+        }
+        Ok(recent)
+    }
+
+    /// Count the keys in the key index of `RedisCacheBuilder::set_max_keys` with `SCARD`,
+    /// without it the index is empty and this returns 0. The count is exact for the index, but
+    /// keys that expired or were removed stay indexed until evicted or pruned, so it overcounts
+    /// the keys still cached until `cache_reconcile` is run.
+    pub fn cache_len(&self) -> Result<u64, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let len: u64 = redis::cmd("SCARD")
+            .arg(self.key_index_key())
+            .query(&mut *conn)?;
+        Ok(len)
+    }
+
+    /// Remove the keys that are no longer cached from the key index, so `cache_len` counts
+    /// live keys again, and return how many were removed. The index is walked with `SSCAN`
+    /// and checked with `EXISTS` in pipelines of up to 1000 keys.
+    pub fn cache_reconcile(&self) -> Result<u64, RedisCacheError> {
+        let index_key = self.key_index_key();
+        let mut conn = self.connection()?;
+        let mut cursor = 0u64;
+        let mut pruned = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SSCAN")
+                .arg(&index_key)
+                .arg(cursor)
+                .arg("COUNT")
+                .arg(RECONCILE_BATCH_SIZE)
+                .query(&mut *conn)?;
+            if !keys.is_empty() {
+                let mut pipe = redis::pipe();
+                for key in &keys {
+                    pipe.exists(key);
+                }
+                let exists: Vec<bool> = pipe.query(&mut *conn)?;
+                let (_, dead) = partition_live(keys, exists);
+                if !dead.is_empty() {
+                    let removed: u64 = redis::cmd("SREM")
+                        .arg(&index_key)
+                        .arg(&dead)
+                        .query(&mut *conn)?;
+                    pruned += removed;
+                }
+            }
+            if next == 0 {
+                return Ok(pruned);
+            }
+            cursor = next;
+        }
+    }
+
+    /// Move every key under this cache's prefix to `new_prefix`, keeping their remaining time
+    /// to live, and return how many were moved. The keyspace is walked with `SCAN` and keys are
+    /// renamed in pipelines of up to 1000 keys, so any number of keys can be migrated, but
+    /// keys set while migrating may be left behind and need another run. Keys already present
+    /// under `new_prefix` are kept and the old ones left in place. Tag sets are moved but still
+    /// list the old keys, so tags should be invalidated before migrating.
+    ///
+    /// This cache keeps using its own prefix, build another one for `new_prefix` afterwards.
+    pub fn migrate_prefix(&self, new_prefix: &str) -> Result<u64, RedisCacheError> {
+        check_prefix(new_prefix)?;
+        let new_prefix = hash_tagged(
+            self.hash_tag.as_deref(),
+            generate_prefix(self.env_namespace.as_deref(), new_prefix),
+        );
+        if new_prefix == self.prefix {
+            return Ok(0);
+        }
+        let mut conn = self.connection()?;
+        let mut cursor = 0u64;
+        let mut moved = 0;
+        loop {
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(format!("{}*", self.prefix))
+                .arg("COUNT")
+                .arg(MIGRATE_BATCH_SIZE)
+                .query(&mut *conn)?;
+            if !keys.is_empty() {
+                let mut pipe = redis::pipe();
+                for key in &keys {
+                    let rest = &key[self.prefix.len()..];
+                    pipe.add_command(rename_cmd(key, &format!("{}{}", new_prefix, rest)));
+                }
+                let renamed: Vec<bool> = pipe.query(&mut *conn)?;
+                moved += renamed.into_iter().filter(|&renamed| renamed).count() as u64;
+            }
+            if next == 0 {
+                return Ok(moved);
+            }
+            cursor = next;
+        }
+    }
+
+    /// Move the value cached for `from` to `to`, keeping its remaining time to live, using
+    /// `RENAMENX`. Returns `false` without changing anything if `from` is missing or a value
+    /// is already cached for `to`.
+    pub fn cache_rename(&self, from: &K, to: &K) -> Result<bool, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let renamed: bool =
+            rename_cmd(&self.generate_key(from), &self.generate_key(to)).query(&mut *conn)?;
+        Ok(renamed)
+    }
+
+    /// Atomically add `by` to the counter stored at `key` and return its new value, clamped to
+    /// `min..=max`. Missing counters start from zero and expire after the cache's lifespan,
+    /// existing ones keep their TTL. Counters are stored as plain integers rather than cached
+    /// values, so they should not be read with `cache_get`.
+    pub fn cache_incr_bounded(
+        &self,
+        key: &K,
+        by: i64,
+        min: i64,
+        max: i64,
+    ) -> Result<i64, RedisCacheError> {
+        let keep_ttl = version_supports(self.server_version()?, KEEPTTL_VERSION);
+        let mut conn = self.connection()?;
+        let n: i64 = incr_bounded_cmd(
+            &self.generate_key(key),
+            by,
+            min,
+            max,
+            self.seconds,
+            keep_ttl,
+        )
+        .query(&mut *conn)?;
+        Ok(n)
+    }
+
+    /// Return the remaining time to live of a cached value in seconds.
+    /// Returns `None` if the key does not exist or has no expiry, and for negative and
+    /// soft-removed entries, like `cache_exists`.
+    pub fn cache_ttl(&self, key: &K) -> Result<Option<u64>, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let ttl: i64 = live_ttl_cmd(&self.generate_key(key)).query(&mut *conn)?;
+        Ok(ttl_from_reply(ttl))
+    }
+
+    /// Return the approximate number of bytes `key` occupies on the server, including
+    /// redis' own overhead, using `MEMORY USAGE`. Returns `None` for missing keys.
+    pub fn cache_memory_usage(&self, key: &K) -> Result<Option<u64>, RedisCacheError> {
+        require_version(self.server_version()?, "MEMORY USAGE", MEMORY_USAGE_VERSION)?;
+        let mut conn = self.connection()?;
+        let usage: Option<u64> = redis::cmd("MEMORY")
+            .arg("USAGE")
+            .arg(self.generate_key(key))
+            .query(&mut *conn)?;
+        Ok(usage)
+    }
+
+    /// Retrieve a cached value and, only if it exists, reset its time to live to `seconds`,
+    /// atomically. Unlike `cache_get` on a refreshing cache, the TTL can't be set on a key
+    /// that expired between the read and the refresh. Stats behave as they do for `cache_get`.
+    pub fn cache_get_and_refresh(
+        &self,
+        key: &K,
+        seconds: u64,
+    ) -> Result<Option<V>, RedisCacheError> {
+        let key = self.generate_key(key);
+        let started = Instant::now();
+        let res = self.get_and_refresh_value(&key, seconds);
+        self.observe(CacheOp::Get, &key, started, &res);
+        self.named(res)
+    }
+
+    fn get_and_refresh_value(&self, key: &str, seconds: u64) -> Result<Option<V>, RedisCacheError> {
+        self.inject_fault(CacheOp::Get, key)?;
+        let mut conn = self.connection()?;
+        let raw: Option<Vec<u8>> = get_and_refresh_cmd(key, seconds).query(&mut *conn)?;
+        match self.resolve_manifest(&mut conn, key, raw)? {
+            None => Ok(None),
+            Some(raw) => self.codec.entry(key, raw),
+        }
+    }
+
+    /// Insert a value that expires after `seconds` instead of the cache's lifespan
+    /// and return the previous value. Stats and error handling behave as they do for `cache_set`.
+    pub fn cache_set_with_lifespan(
+        &self,
+        key: K,
+        val: V,
+        seconds: u64,
+    ) -> Result<Option<V>, RedisCacheError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let res = self.set_value(&key, val, seconds);
+        self.observe(CacheOp::Set, &key, started, &res);
+        let res = self.named(res);
+        match res {
+            Err(_) if self.ignore_set_errors => Ok(None),
+            res => res,
+        }
+    }
+
+    /// Replace the value at `key` without changing its remaining TTL and return the previous value.
+    /// `SET ... KEEPTTL` alone would leave a brand-new key without any expiry, so keys that don't
+    /// exist yet expire after the cache's lifespan instead, as they would with `cache_set`.
+    /// Stats and error handling behave as they do for `cache_set`.
+    pub fn cache_set_keep_ttl(&self, key: K, val: V) -> Result<Option<V>, RedisCacheError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let res = self.set_keep_ttl_value(&key, val);
+        self.observe(CacheOp::Set, &key, started, &res);
+        let res = self.named(res);
+        match res {
+            Err(_) if self.ignore_set_errors => Ok(None),
+            res => res,
+        }
+    }
+
+    fn set_keep_ttl_value(&self, key: &str, val: V) -> Result<Option<V>, RedisCacheError> {
+        self.inject_fault(CacheOp::Set, key)?;
+        let keep_ttl = version_supports(self.server_version()?, KEEPTTL_VERSION);
+        let mut conn = self.connection()?;
+        let seconds = self.lifespan_of(&val);
+        let prev: Option<Vec<u8>> = set_keep_ttl_cmd(
+            key,
+            self.codec.serialize(&val, Some(seconds))?,
+            jittered(seconds, self.lifespan_jitter),
+            keep_ttl,
+        )
+        .query(&mut *conn)?;
+        match prev {
+            None => Ok(None),
+            Some(s) => self.codec.entry(key, s),
+        }
+    }
+
+    /// Atomically replace the value at `key` with `f` applied to the current value, or `None` if
+    /// there is none, and return the new value, which is written as `cache_set` writes it.
+    /// The key is `WATCH`ed while `f` runs, and when another client changes it before the write,
+    /// the read and `f` are retried. `f` may therefore run several times, and after
+    /// 16 conflicts in a row `RedisCacheError::UpdateConflict` is returned.
+    /// Stats behave as they do for `cache_set`, but errors are never ignored since the returned
+    /// value would not have been written.
+    pub fn cache_update<F: FnMut(Option<V>) -> V>(
+        &self,
+        key: K,
+        f: F,
+    ) -> Result<V, RedisCacheError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let res = self.update_value(&key, f);
+        self.observe(
+            CacheOp::Set,
+            &key,
+            started,
+            &res.as_ref().map(|_| None::<V>),
+        );
+        self.named(res)
+    }
+
+    fn update_value<F: FnMut(Option<V>) -> V>(
+        &self,
+        key: &str,
+        mut f: F,
+    ) -> Result<V, RedisCacheError> {
+        self.inject_fault(CacheOp::Set, key)?;
+        let mut conn = self.connection()?;
+        for _ in 0..UPDATE_MAX_ATTEMPTS {
+            let mut watch = Watch::new(&mut conn, key)?;
+            let raw: Option<Vec<u8>> = redis::cmd("GET").arg(key).query(watch.conn)?;
+            let prev_chunks = raw
+                .as_deref()
+                .and_then(chunk_manifest)
+                .map_or(0, |manifest| manifest.chunks);
+            let raw = match prev_chunks {
+                0 => raw,
+                chunks => self.get_chunks(watch.conn, key, &ChunkManifest { chunks }, false)?,
+            };
+            let current = match raw {
+                None => None,
+                Some(raw) => self.codec.entry(key, raw)?,
+            };
+            let val = f(current);
+
+            let seconds = self.lifespan_of(&val);
+            let bytes = self.codec.serialize(&val, Some(seconds))?;
+            let ttl = jittered(seconds, self.lifespan_jitter) as usize;
+            let mut pipe = redis::pipe();
+            pipe.atomic();
+            match self.value_chunk_size.filter(|size| bytes.len() > *size) {
+                Some(chunk_size) => {
+                    add_chunked(&mut pipe, key, &bytes, ttl, chunk_size, prev_chunks)?;
+                }
+                None => {
+                    pipe.set_ex(key, bytes, ttl).ignore();
+                    for index in 0..prev_chunks {
+                        pipe.del(chunk_key(key, index)).ignore();
+                    }
+                }
+            }
+            self.add_set_hooks(&mut pipe, key);
+
+            let written: Option<()> = pipe.query(watch.conn)?;
+            watch.executed = true;
+            drop(watch);
+            if written.is_some() {
+                self.wait_for_replicas(&mut conn)?;
+                return Ok(val);
+            }
+        }
+        Err(RedisCacheError::UpdateConflict {
+            attempts: UPDATE_MAX_ATTEMPTS,
+        })
+    }
+
+    /// Check that `val` survives a round-trip through the cache's serialization, including
+    /// its codec and compression, without contacting redis
+    pub fn validate_roundtrip(&self, val: &V) -> Result<(), RedisCacheError> {
+        self.codec.roundtrip(val).map(|_| ())
+    }
+
+    /// Like `validate_roundtrip`, also returning whether the round-tripped value equals `val`
+    pub fn validate_roundtrip_eq(&self, val: &V) -> Result<bool, RedisCacheError>
+    where
+        V: PartialEq,
+    {
+        Ok(self.codec.roundtrip(val)? == *val)
+    }
+
+    /// Retrieve a cached value, or compute it with `f` and cache it for `seconds` on a miss.
+    /// The lifespan jitter, if set, is added to `seconds`, and `seconds` itself is recorded as
+    /// the value's original lifespan. Keys cached as having no value by `cache_set_miss` return
+    /// `RedisCacheError::NegativeCached` without calling `f`.
+    pub fn cache_get_or_set_with_ttl<F: FnOnce() -> V>(
+        &self,
+        key: K,
+        seconds: u64,
+        f: F,
+    ) -> Result<V, RedisCacheError>
+    where
+        V: Clone,
+    {
+        match self.cache_get_detailed(&key)? {
+            GetOutcome::Hit(v) => return Ok(v),
+            GetOutcome::Negative => return Err(RedisCacheError::NegativeCached),
+            GetOutcome::Corrupt(e) => return Err(e),
+            GetOutcome::Miss | GetOutcome::Removed(_) => {}
+        }
+        let v = f();
+        self.cache_set_with_lifespan(key, v.clone(), seconds)?;
+        Ok(v)
+    }
+
+    /// Return the lifespan `key` was stored with, as opposed to the time it has left (see `cache_ttl`).
+    /// Returns `None` for missing keys and for values stored without a recorded lifespan.
+    pub fn cache_original_lifespan(&self, key: &K) -> Result<Option<u64>, RedisCacheError> {
+        let key = self.generate_key(key);
+        let mut conn = self.connection()?;
+        let raw: Option<Vec<u8>> = redis::cmd("GET").arg(&key).query(&mut *conn)?;
+        match self.resolve_manifest(&mut conn, &key, raw)? {
+            None => Ok(None),
+            Some(raw) => Ok(self.codec.meta(&key, &raw)?.lifespan),
+        }
+    }
+
+    /// Return the metadata stored with `key`'s value without deserializing the value itself,
+    /// which keeps audits of many keys cheap on large values.
+    /// Returns `None` for missing keys, negative entries, and soft-removed entries.
+    pub fn cache_meta(&self, key: &K) -> Result<Option<EntryMeta>, RedisCacheError> {
+        let key = self.generate_key(key);
+        let mut conn = self.connection()?;
+        let raw: Option<Vec<u8>> = redis::cmd("GET").arg(&key).query(&mut *conn)?;
+        match self.resolve_manifest(&mut conn, &key, raw)? {
+            None => Ok(None),
+            Some(raw) => self.codec.entry_meta(&key, raw),
+        }
+    }
+
+    /// Deserialize `legacy_payload`, a value as stored by an earlier version, through the
+    /// cache's current codec, for pinning known-good payloads in tests to catch changes to `V`
+    /// or the cache settings that would break reading values already cached. Payloads are
+    /// the stored bytes of a value, e.g. as read with `redis-cli GET`, and are decompressed
+    /// and decrypted like cached values.
+    ///
+    /// ```rust,no_run
+    /// # use cached::RedisCache;
+    /// let cache: RedisCache<u32, Vec<String>> = RedisCache::new("users", 60)
+    ///     .without_pool()
+    ///     .build()
+    ///     .unwrap();
+    /// let v = cache
+    ///     .assert_compatible_with(r#"{"value":["alice"],"lifespan":60}"#)
+    ///     .unwrap();
+    /// assert_eq!(vec!["alice".to_string()], v);
+    /// ```
+    pub fn assert_compatible_with(&self, legacy_payload: &str) -> Result<V, RedisCacheError> {
+        self.codec.deserialize(legacy_payload.as_bytes())
+    }
+
+    /// Insert a value only if none is cached for `key`, failing with `InsertError::AlreadyExists`
+    /// otherwise, atomically with a script. Keys only holding a negative or soft-removed entry
+    /// count as missing, as they do for `cache_get`. Lifespan and stats behave as they do for
+    /// `cache_set`, but errors are never ignored.
+    pub fn cache_insert(&self, key: K, val: V) -> Result<(), InsertError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let res = self.insert_value(&key, val);
+        self.observe(
+            CacheOp::Set,
+            &key,
+            started,
+            &res.as_ref().map(|_| None::<V>),
+        );
+        match self.named(res)? {
+            true => Ok(()),
+            false => Err(InsertError::AlreadyExists),
+        }
+    }
+
+    fn insert_value(&self, key: &str, val: V) -> Result<bool, RedisCacheError> {
+        self.inject_fault(CacheOp::Set, key)?;
+        let mut conn = self.connection()?;
+        let seconds = self.lifespan_of(&val);
+        let inserted: bool = set_if_absent_cmd(
+            key,
+            &self.codec.serialize(&val, Some(seconds))?,
+            jittered(seconds, self.lifespan_jitter),
+            false,
+        )
+        .query(&mut *conn)?;
+        Ok(inserted)
+    }
+
+    /// Set a value only if none is cached for `key`, and refresh the lifespan of the cached
+    /// value otherwise without overwriting it, atomically with a script. Negative and
+    /// soft-removed entries are overwritten like missing values, and refreshing a chunked value
+    /// refreshes its parts too. Returns whether `val` was written. Stats count the call as a set, and errors are ignored as set with
+    /// `set_ignore_set_errors`.
+    pub fn cache_set_or_refresh(&self, key: K, val: V) -> Result<bool, RedisCacheError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let res = self.set_or_refresh_value(&key, val);
+        self.observe(
+            CacheOp::Set,
+            &key,
+            started,
+            &res.as_ref().map(|_| None::<V>),
+        );
+        match self.named(res) {
+            Err(_) if self.ignore_set_errors => Ok(false),
+            res => res,
+        }
+    }
+
+    fn set_or_refresh_value(&self, key: &str, val: V) -> Result<bool, RedisCacheError> {
+        self.inject_fault(CacheOp::Set, key)?;
+        let mut conn = self.connection()?;
+        let seconds = self.lifespan_of(&val);
+        let set: bool = set_if_absent_cmd(
+            key,
+            &self.codec.serialize(&val, Some(seconds))?,
+            jittered(seconds, self.lifespan_jitter),
+            true,
+        )
+        .query(&mut *conn)?;
+        Ok(set)
+    }
+
+    /// Insert several values in one round-trip, only for keys that aren't already cached, like
+    /// `cache_insert` does for a single value. Returns, in order, whether each entry was newly
+    /// inserted.
+    pub fn cache_set_multi_nx<I>(&self, entries: I) -> Result<Vec<bool>, RedisCacheError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut pipe = redis::pipe();
+        let mut count = 0;
+        for (key, val) in entries {
+            let key = self.generate_key(&key);
+            let seconds = self.lifespan_of(&val);
+            pipe.add_command(set_if_absent_cmd(
+                &key,
+                &self.codec.serialize(&val, Some(seconds))?,
+                jittered(seconds, self.lifespan_jitter),
+                false,
+            ));
+            self.add_set_hooks(&mut pipe, &key);
+            count += 1;
+        }
+        if count == 0 {
+            return Ok(vec![]);
+        }
+        let mut conn = self.connection()?;
+        Ok(pipe.query(&mut *conn)?)
+    }
+
+    /// Load precomputed entries, overwriting any cached values, and return how many were written.
+    /// Entries are serialized and sent in pipelines as they are taken from `entries`, see
+    /// `RedisCacheBuilder::set_batch_chunk_size`, so only one pipeline is held in memory at a
+    /// time. Entries are written like `cache_set` writes them, with chunked values taking a
+    /// round-trip of their own. If a pipeline fails, the entries of the previous ones stay
+    /// written.
+    pub fn warm<I>(&self, entries: I) -> Result<u64, RedisCacheError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let chunk_size = self.batch_chunk_size.unwrap_or(DEFAULT_WARM_CHUNK_SIZE);
+        let mut entries = entries.into_iter().peekable();
+        let mut written = 0;
+        while entries.peek().is_some() {
+            let batch: Vec<(String, V)> = entries
+                .by_ref()
+                .take(chunk_size)
+                .map(|(key, val)| (self.generate_key(&key), val))
+                .collect();
+            let count = batch.len() as u64;
+            self.set_batch(batch, false)?;
+            written += count;
+        }
+        Ok(written)
+    }
+
+    /// Write `entries` like `set_value` does, in one pipeline and a round-trip per chunked value,
+    /// and return the previous value of each entry if `previous` is set, or nothing otherwise
+    fn set_batch(
+        &self,
+        entries: Vec<(String, V)>,
+        previous: bool,
+    ) -> Result<Vec<Option<V>>, RedisCacheError> {
+        // previous values are also read to delete the parts of replaced chunked values
+        let read = previous || self.value_chunk_size.is_some();
+        let mut pipe = redis::pipe();
+        let mut plain = Vec::new();
+        let mut chunked = Vec::new();
+        for (position, (key, val)) in entries.into_iter().enumerate() {
+            let seconds = self.lifespan_of(&val);
+            let bytes = self.codec.serialize(&val, Some(seconds))?;
+            if let Some(chunk_size) = self.value_chunk_size.filter(|size| bytes.len() > *size) {
+                chunked.push((position, key, bytes, seconds, chunk_size));
+                continue;
+            }
+            if read {
+                pipe.get(&key);
+            }
+            let ttl = jittered(seconds, self.lifespan_jitter) as usize;
+            pipe.set_ex::<&str, Vec<u8>>(&key, bytes, ttl).ignore();
+            self.add_set_hooks(&mut pipe, &key);
+            plain.push(key);
+        }
+
+        let mut replaced = Vec::new();
+        if !plain.is_empty() {
+            let mut conn = self.connection()?;
+            let raw: Vec<Option<Vec<u8>>> = match read {
+                true => pipe.query(&mut *conn)?,
+                false => {
+                    pipe.query::<()>(&mut *conn)?;
+                    Vec::new()
+                }
+            };
+            self.wait_for_replicas(&mut conn)?;
+            for (key, raw) in plain.iter().zip(raw) {
+                if previous {
+                    replaced.push(self.take_previous(&mut conn, key, raw)?);
+                } else if let Some(manifest) = raw.as_deref().and_then(chunk_manifest) {
+                    let part_keys: Vec<String> =
+                        (0..manifest.chunks).map(|i| chunk_key(key, i)).collect();
+                    redis::cmd("DEL").arg(part_keys).query::<()>(&mut *conn)?;
+                }
+            }
+        }
+        for (position, key, bytes, seconds, chunk_size) in chunked {
+            let prev = self.set_chunked(&key, &bytes, seconds, chunk_size)?;
+            if previous {
+                replaced.insert(position, prev);
+            }
+        }
+        Ok(replaced)
+    }
+
+    /// Insert several values in one round-trip and return, in order, the previous value of
+    /// each entry, like `cache_set` does for a single value. Chunked values take a round-trip of
+    /// their own.
+    pub fn cache_replace_multi<I>(&self, entries: I) -> Result<Vec<Option<V>>, RedisCacheError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let entries = entries
+            .into_iter()
+            .map(|(key, val)| (self.generate_key(&key), val))
+            .collect();
+        self.set_batch(entries, true)
+    }
+
+    /// Return the remaining time to live of several cached values in one round-trip.
+    /// Results are in the same order as `keys`, see `cache_ttl` for the meaning of `None`.
+    pub fn cache_ttl_multi(&self, keys: &[K]) -> Result<Vec<Option<u64>>, RedisCacheError> {
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+        let mut conn = self.connection()?;
+        let mut pipe = redis::pipe();
+        for key in keys {
+            pipe.add_command(live_ttl_cmd(&self.generate_key(key)));
+        }
+        let ttls: Vec<i64> = pipe.query(&mut *conn)?;
+        Ok(ttls.into_iter().map(ttl_from_reply).collect())
+    }
+
+    /// Reset the time to live of a cached value, and of its parts if it's chunked, to the cache
+    /// lifespan without retrieving it. Returns `false` if the key does not exist.
+    pub fn cache_touch(&self, key: &K) -> Result<bool, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let touched: bool = touch_cmd(&self.generate_key(key), self.seconds).query(&mut *conn)?;
+        Ok(touched)
+    }
+
+    /// Retrieve a cached value along with its remaining time to live in seconds.
+    /// The ttl is read after any refresh, so refreshing caches report the full lifespan.
+    pub fn cache_get_with_ttl(&self, key: &K) -> Result<Option<(V, Option<u64>)>, RedisCacheError> {
+        let mut conn = self.connection()?;
+        let mut pipe = redis::pipe();
+        let key = self.generate_key(key);
+
+        pipe.get(key.clone());
+        if self.refresh {
+            pipe.add_command(refresh_cmd(&key, self.seconds, self.refresh_expire_option))
+                .ignore();
+        }
+        pipe.ttl(&key);
+        let (raw, ttl): (Option<Vec<u8>>, i64) = pipe.query(&mut *conn)?;
+        match raw {
+            None => Ok(None),
+            Some(s) => Ok(self.codec.entry(&key, s)?.map(|v| (v, ttl_from_reply(ttl)))),
+        }
+    }
+
+    /// Start a pipeline of mixed operations, executed in one round-trip
+    pub fn pipeline(&self) -> CachePipeline<'_, K, V> {
+        CachePipeline {
+            cache: self,
+            queued: QueuedOps::new(),
+            chunked: Vec::new(),
+        }
+    }
+}
+
+/// The reply to each operation executed by a `CachePipeline`, in the order they were queued
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipelineReply<V> {
+    /// The cached value
+    Get(Option<V>),
+    /// The previously cached value
+    Set(Option<V>),
+    /// The removed value
+    Remove(Option<V>),
+}
+
+impl<V> PipelineReply<V> {
+    /// Convert into the value replied, regardless of the operation
+    pub fn into_value(self) -> Option<V> {
+        match self {
+            PipelineReply::Get(v) | PipelineReply::Set(v) | PipelineReply::Remove(v) => v,
+        }
+    }
+}
+
+/// Operations queued by a pipeline. Every operation sends exactly one command replying
+/// with the key's current value, so replies line up with `ops`.
+struct QueuedOps {
+    pipe: redis::Pipeline,
+    ops: Vec<(CacheOp, String)>,
+    error: Option<RedisCacheError>,
+}
+
+impl QueuedOps {
+    fn new() -> Self {
+        Self {
+            pipe: redis::pipe(),
+            ops: Vec::new(),
+            error: None,
+        }
+    }
+
+    fn get(&mut self, key: String, refresh: Option<redis::Cmd>) {
+        self.pipe.get(&key);
+        if let Some(cmd) = refresh {
+            self.pipe.add_command(cmd).ignore();
+        }
+        self.ops.push((CacheOp::Get, key));
+    }
+
+    /// Queue a set of `key` to the serialized value `bytes`, expiring after `ttl` seconds.
+    /// The cache's set hooks are added to `pipe` by the caller.
+    fn set(&mut self, key: &str, bytes: Vec<u8>, ttl: u64) {
+        self.pipe.get(key);
+        self.pipe
+            .set_ex::<&str, Vec<u8>>(key, bytes, ttl as usize)
+            .ignore();
+        self.ops.push((CacheOp::Set, key.to_string()));
+    }
+
+    /// Record a failure to queue an operation, reported by `execute` before anything is sent
+    fn fail(&mut self, error: RedisCacheError) {
+        self.error.get_or_insert(error);
+    }
+
+    /// Queue a `WAIT` for the queued writes if `write_wait` is set and a set is queued,
+    /// returning the number of replicas to check the reply against
+    fn wait(&mut self, write_wait: Option<(u32, Duration)>) -> Option<u32> {
+        let (replicas, timeout) = write_wait?;
+        if !self.ops.iter().any(|(op, _)| *op == CacheOp::Set) {
+            return None;
+        }
+        self.pipe
+            .cmd("WAIT")
+            .arg(replicas)
+            .arg(timeout.as_millis() as u64);
+        Some(replicas)
+    }
+
+    /// The raw values replied to the queued operations, checking the reply to the `WAIT`
+    /// queued by `wait` for `replicas`, if any
+    fn raw_replies(
+        mut reply: Vec<redis::Value>,
+        replicas: Option<u32>,
+    ) -> Result<Vec<Option<Vec<u8>>>, RedisCacheError> {
+        if let Some(replicas) = replicas {
+            let acked: u32 = redis::from_redis_value(&reply.pop().unwrap_or(redis::Value::Nil))?;
+            check_write_wait(replicas, acked)?;
+        }
+        Ok(reply
+            .iter()
+            .map(redis::from_redis_value)
+            .collect::<redis::RedisResult<_>>()?)
+    }
+
+    fn remove(&mut self, key: String, soft_remove: Option<u64>) {
+        match soft_remove {
+            None => {
+                self.pipe.get(&key);
+                self.pipe.del::<&str>(&key).ignore();
+            }
+            Some(grace_seconds) => {
+                self.pipe.add_command(soft_remove_cmd(&key, grace_seconds));
+            }
+        }
+        self.ops.push((CacheOp::Remove, key));
+    }
+
+    fn replies<V>(
+        codec: &Codec<V>,
+        ops: Vec<(CacheOp, String)>,
+        raw: Vec<Option<Vec<u8>>>,
+    ) -> Result<Vec<PipelineReply<V>>, RedisCacheError>
+    where
+        V: Serialize + DeserializeOwned,
+    {
+        ops.into_iter()
+            .zip(raw)
+            .map(|((op, key), raw)| {
+                let v = match raw {
+                    None => None,
+                    Some(s) => codec.entry(&key, s)?,
+                };
+                Ok(match op {
+                    CacheOp::Get => PipelineReply::Get(v),
+                    CacheOp::Set => PipelineReply::Set(v),
+                    CacheOp::Remove => PipelineReply::Remove(v),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Mixed cache operations executed in a single redis pipeline, created with `RedisCache::pipeline`
+///
+/// Operations behave like their `IOCached` counterparts, except that they aren't
+/// counted in `CacheStats` or reported to the event sink. Sets apply the lifespan jitter,
+/// `set_ttl_fn`, the key index of `set_max_keys`, recency tracking, and the write wait.
+///
+/// With chunked storage, chunked values found by the pipeline are read, and the parts of
+/// removed or replaced ones deleted, in further round-trips. Values larger than the chunk size
+/// are written after the pipeline, each in their own round-trips as `cache_set` would, so
+/// operations queued after them don't see them.
+pub struct CachePipeline<'a, K, V> {
+    cache: &'a RedisCache<K, V>,
+    queued: QueuedOps,
+    /// The sets of values to be stored in chunks, with the position of their reply
+    chunked: Vec<(usize, String, Vec<u8>, u64)>,
+}
+
+impl<'a, K, V> CachePipeline<'a, K, V>
+where
+    K: Display,
+    V: Serialize + DeserializeOwned,
+{
+    /// Queue a get of `key`
+    pub fn get(mut self, key: &K) -> Self {
+        let key = self.cache.generate_key(key);
+        let refresh = self
+            .cache
+            .refresh
+            .then(|| refresh_cmd(&key, self.cache.seconds, self.cache.refresh_expire_option));
+        self.queued.get(key, refresh);
+        self
+    }
+
+    /// Queue a set of `key` to `val`
+    pub fn set(mut self, key: K, val: V) -> Self {
+        let cache = self.cache;
+        let key = cache.generate_key(&key);
+        let seconds = cache.lifespan_of(&val);
+        match cache.codec.serialize(&val, Some(seconds)) {
+            Err(e) => self.queued.fail(e),
+            Ok(bytes) if matches!(cache.value_chunk_size, Some(size) if bytes.len() > size) => {
+                let position = self.queued.ops.len() + self.chunked.len();
+                self.chunked.push((position, key, bytes, seconds));
+            }
+            Ok(bytes) => {
+                let ttl = jittered(seconds, cache.lifespan_jitter);
+                self.queued.set(&key, bytes, ttl);
+                cache.add_set_hooks(&mut self.queued.pipe, &key);
+            }
+        }
+        self
+    }
+
+    /// Queue a removal of `key`
+    pub fn remove(mut self, key: &K) -> Self {
+        self.queued
+            .remove(self.cache.generate_key(key), self.cache.soft_remove);
+        self
+    }
+
+    /// Execute the queued operations in one round-trip, returning a reply per operation.
+    /// Fails without sending anything if a queued value couldn't be serialized.
+    pub fn execute(mut self) -> Result<Vec<PipelineReply<V>>, RedisCacheError> {
+        let cache = self.cache;
+        let replicas = self.queued.wait(cache.write_wait);
+        let QueuedOps { pipe, ops, error } = self.queued;
+        if let Some(e) = error {
+            return Err(e);
+        }
+        let mut replies = if ops.is_empty() {
+            Vec::new()
+        } else {
+            let mut conn = cache.connection()?;
+            let raw = QueuedOps::raw_replies(pipe.query(&mut *conn)?, replicas)?;
+            let raw = cache.resolve_chunks(&mut conn, &ops, raw)?;
+            QueuedOps::replies(&cache.codec, ops, raw)?
+        };
+        for (position, key, bytes, seconds) in self.chunked {
+            let chunk_size = cache.value_chunk_size.unwrap_or(bytes.len());
+            let prev = cache.set_chunked(&key, &bytes, seconds, chunk_size)?;
+            replies.insert(position, PipelineReply::Set(prev));
+        }
+        Ok(replies)
+    }
+}
+
+/// List store backed by redis, created with `RedisCacheBuilder::build_list`
+///
+/// Each key holds an ordered list of values. The ttl applies to the whole list
+/// and is reset whenever a value is pushed. Lists are capped to the most recent
+/// values, see `RedisCacheBuilder::set_max_list_len`.
+pub struct RedisListCache<K, V> {
+    max_len: usize,
+    cache: RedisCache<K, V>,
+}
+
+impl<K, V> RedisListCache<K, V>
+where
+    K: Display,
+    V: Serialize + DeserializeOwned,
+{
+    /// Append a value to the end of the list cached under `key`, trimming the oldest
+    /// values beyond the maximum length, and return the new length
+    pub fn cache_push(&self, key: K, val: V) -> Result<u64, RedisCacheError> {
+        let mut conn = self.cache.connection()?;
+        let mut pipe = redis::pipe();
+        let key = self.cache.generate_key(&key);
+
+        pipe.rpush(&key, self.cache.codec.serialize(&val, None)?);
+        pipe.ltrim(&key, -(self.max_len as isize), -1).ignore();
+        pipe.expire(&key, self.cache.seconds as usize).ignore();
+        let res: (u64,) = pipe.query(&mut *conn)?;
+        Ok(res.0.min(self.max_len as u64))
+    }
+
+    /// Return the maximum number of values kept per list
+    pub fn cache_max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Return the values of the list cached under `key` between `start` and `stop`, inclusive.
+    /// Like redis `LRANGE`, negative indices count from the end of the list.
+    pub fn cache_range(
+        &self,
+        key: &K,
+        start: isize,
+        stop: isize,
+    ) -> Result<Vec<V>, RedisCacheError> {
+        let mut conn = self.cache.connection()?;
+        let mut pipe = redis::pipe();
+        let key = self.cache.generate_key(key);
+
+        pipe.lrange(&key, start, stop);
+        if self.cache.refresh {
+            pipe.add_command(refresh_cmd(
+                &key,
+                self.cache.seconds,
+                self.cache.refresh_expire_option,
+            ))
+            .ignore();
+        }
+        let res: (Vec<Vec<u8>>,) = pipe.query(&mut *conn)?;
+        res.0
+            .into_iter()
+            .map(|raw| {
+                self.cache
+                    .codec
+                    .deserialize(&raw)
+                    .map_err(|e| e.at_key(&key))
+            })
+            .collect()
+    }
+
+    /// Remove the list cached under `key`, returns `false` if there was none
+    pub fn cache_remove(&self, key: &K) -> Result<bool, RedisCacheError> {
+        let mut conn = self.cache.connection()?;
+        let removed: bool = redis::cmd("DEL")
+            .arg(self.cache.generate_key(key))
+            .query(&mut *conn)?;
+        Ok(removed)
+    }
+
+    /// Return the lifespan of cached lists
+    pub fn cache_lifespan(&self) -> u64 {
+        self.cache.seconds
+    }
+}
+
+/// Byte value store backed by redis, created with `RedisCacheBuilder::build_bytes`
+///
+/// Values are stored exactly as given, without serialization or the cache's own envelope,
+/// which suits blobs like response bodies and lets other clients read them as plain strings.
+/// Lifespan, refresh, stats, and the fallback prefix behave as they do for `RedisCache`, while
+/// the codec settings like compression, checksums, and encryption don't apply, and removals
+/// always delete.
+///
+/// ```rust,no_run
+/// # use cached::{IOCached, RedisBytesCache};
+/// let cache: RedisBytesCache<String> = RedisBytesCache::new("responses", 60)
+///     .build_bytes()
+///     .unwrap();
+/// cache
+///     .cache_set("/index.html".to_string(), bytes::Bytes::from_static(b"<html></html>"))
+///     .unwrap();
+/// ```
+pub struct RedisBytesCache<K> {
+    cache: RedisCache<K, Vec<u8>>,
+}
+
+impl<K> RedisBytesCache<K>
+where
+    K: Display,
+{
+    /// Initialize a `RedisCacheBuilder`, to be finished with `build_bytes`
+    #[allow(clippy::new_ret_no_self)]
+    pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> RedisCacheBuilder<K, Vec<u8>> {
+        RedisCacheBuilder::new(prefix, seconds)
+    }
+
+    fn set_bytes(&self, key: &str, val: Bytes) -> Result<Option<Bytes>, RedisCacheError> {
+        self.cache.inject_fault(CacheOp::Set, key)?;
+        let mut conn = self.cache.connection()?;
+        let mut pipe = redis::pipe();
+        pipe.get(key);
+        pipe.set_ex::<&str, &[u8]>(
+            key,
+            &val,
+            jittered(self.cache.seconds, self.cache.lifespan_jitter) as usize,
+        )
+        .ignore();
+        let res: (Option<Vec<u8>>,) = pipe.query(&mut *conn)?;
+        Ok(res.0.map(Bytes::from))
+    }
+
+    fn remove_bytes(&self, key: &str) -> Result<Option<Bytes>, RedisCacheError> {
+        self.cache.inject_fault(CacheOp::Remove, key)?;
+        let mut conn = self.cache.connection()?;
+        let mut pipe = redis::pipe();
+        pipe.get(key);
+        pipe.del::<&str>(key).ignore();
+        let res: (Option<Vec<u8>>,) = pipe.query(&mut *conn)?;
+        Ok(res.0.map(Bytes::from))
+    }
+
+    /// Return a snapshot of the operation counters, see `RedisCache::cache_stats`
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.cache_stats()
+    }
+}
+
+impl<K> IOCached<K, Bytes> for RedisBytesCache<K>
+where
+    K: Display,
+{
+    type Error = RedisCacheError;
+
+    fn cache_get(&self, key: &K) -> Result<Option<Bytes>, RedisCacheError> {
+        let key = self.cache.generate_key(key);
+        let started = Instant::now();
+        let res = self.cache.get_raw(&key).map(|raw| raw.map(Bytes::from));
+        self.cache.observe(CacheOp::Get, &key, started, &res);
+        self.cache.named(res)
+    }
+
+    fn cache_set(&self, key: K, val: Bytes) -> Result<Option<Bytes>, RedisCacheError> {
+        let key = self.cache.generate_key(&key);
+        let started = Instant::now();
+        let res = self.set_bytes(&key, val);
+        self.cache.observe(CacheOp::Set, &key, started, &res);
+        let res = self.cache.named(res);
+        match res {
+            Err(_) if self.cache.ignore_set_errors => Ok(None),
+            res => res,
+        }
+    }
+
+    fn cache_remove(&self, key: &K) -> Result<Option<Bytes>, RedisCacheError> {
+        let key = self.cache.generate_key(key);
+        let started = Instant::now();
+        let res = self.remove_bytes(&key);
+        self.cache.observe(CacheOp::Remove, &key, started, &res);
+        self.cache.named(res)
+    }
+
+    fn cache_lifespan(&self) -> Option<u64> {
+        IOCached::cache_lifespan(&self.cache)
+    }
+
+    fn cache_set_lifespan(&mut self, seconds: u64) -> Option<u64> {
+        IOCached::cache_set_lifespan(&mut self.cache, seconds)
+    }
+
+    fn cache_set_refresh(&mut self, refresh: bool) -> bool {
+        IOCached::cache_set_refresh(&mut self.cache, refresh)
+    }
+}
+
+/// Stream store backed by redis, created with `RedisCacheBuilder::build_stream`
+///
+/// Each key holds a redis stream of events, appended with `XADD` and never overwritten.
+/// The ttl applies to the whole stream and is reset whenever an event is appended.
+/// Streams are capped to the most recent events, see `RedisCacheBuilder::set_max_stream_len`.
+pub struct RedisStreamCache<K, V> {
+    max_len: usize,
+    cache: RedisCache<K, V>,
+}
+
+impl<K, V> RedisStreamCache<K, V>
+where
+    K: Display,
+    V: Serialize + DeserializeOwned,
+{
+    /// Append an event to the stream cached under `key`, trimming the oldest events beyond
+    /// the maximum length, and return the id redis assigned to it
+    pub fn cache_append(&self, key: K, val: V) -> Result<String, RedisCacheError> {
+        require_version(self.cache.server_version()?, "XADD", STREAMS_VERSION)?;
+        let mut conn = self.cache.connection()?;
+        let mut pipe = redis::pipe();
+        let key = self.cache.generate_key(&key);
+
+        pipe.cmd("XADD")
+            .arg(&key)
+            .arg("MAXLEN")
+            .arg(self.max_len)
+            .arg("*")
+            .arg(STREAM_VALUE_FIELD)
+            .arg(self.cache.codec.serialize(&val, None)?);
+        pipe.expire(&key, self.cache.seconds as usize).ignore();
+        let res: (String,) = pipe.query(&mut *conn)?;
+        Ok(res.0)
+    }
+
+    /// Return the maximum number of events kept per stream
+    pub fn cache_max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Return the last `count` events of the stream cached under `key`, oldest first
+    pub fn cache_events(&self, key: &K, count: usize) -> Result<Vec<V>, RedisCacheError> {
+        require_version(self.cache.server_version()?, "XREVRANGE", STREAMS_VERSION)?;
+        let mut conn = self.cache.connection()?;
+        let mut pipe = redis::pipe();
+        let key = self.cache.generate_key(key);
+
+        pipe.cmd("XREVRANGE")
+            .arg(&key)
+            .arg("+")
+            .arg("-")
+            .arg("COUNT")
+            .arg(count);
+        if self.cache.refresh {
+            pipe.add_command(refresh_cmd(
+                &key,
+                self.cache.seconds,
+                self.cache.refresh_expire_option,
+            ))
+            .ignore();
+        }
+        let res: (Vec<redis::Value>,) = pipe.query(&mut *conn)?;
+        let mut events = res
+            .0
+            .iter()
+            .map(|entry| {
+                let (_id, fields): (String, Vec<(String, Vec<u8>)>) =
+                    redis::from_redis_value(entry)?;
+                let raw = fields
+                    .into_iter()
+                    .find(|(field, _)| field == STREAM_VALUE_FIELD)
+                    .map(|(_, raw)| raw)
+                    .unwrap_or_default();
+                self.cache
+                    .codec
+                    .deserialize(&raw)
+                    .map_err(|e| e.at_key(&key))
+            })
+            .collect::<Result<Vec<V>, RedisCacheError>>()?;
+        events.reverse();
+        Ok(events)
+    }
+
+    /// Remove the stream cached under `key`, returns `false` if there was none
+    pub fn cache_remove(&self, key: &K) -> Result<bool, RedisCacheError> {
+        let mut conn = self.cache.connection()?;
+        let removed: bool = redis::cmd("DEL")
+            .arg(self.cache.generate_key(key))
+            .query(&mut *conn)?;
+        Ok(removed)
+    }
+
+    /// Return the lifespan of cached streams
+    pub fn cache_lifespan(&self) -> u64 {
+        self.cache.seconds
+    }
+}
+
+/// Number of points each shard gets on the hash ring, more points spread keys more evenly
+const RING_POINTS_PER_SHARD: usize = 160;
+
+/// A consistent hash ring mapping generated keys to shard indexes. Adding a shard only
+/// moves the keys that land on its points, about `1 / shards` of them.
+struct HashRing {
+    points: Vec<(u64, usize)>,
+}
+
+impl HashRing {
+    fn new(shards: usize) -> Self {
+        let mut points: Vec<(u64, usize)> = (0..shards)
+            .flat_map(|shard| {
+                (0..RING_POINTS_PER_SHARD)
+                    .map(move |point| (ring_hash(&format!("shard-{}-{}", shard, point)), shard))
+            })
+            .collect();
+        points.sort_unstable();
+        HashRing { points }
+    }
+
+    fn shard(&self, key: &str) -> usize {
+        let hash = ring_hash(key);
+        let i = self.points.partition_point(|&(point, _)| point < hash);
+        self.points[i % self.points.len()].1
+    }
+}
+
+/// Hash for the ring, stable across processes and releases unlike `std`'s hashers
+fn ring_hash(s: &str) -> u64 {
+    use sha2::Digest;
+    let digest = sha2::Sha256::digest(s.as_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+/// Cache store sharded across standalone redis instances, created with
+/// `RedisCacheBuilder::build_sharded`
+///
+/// Each generated key is routed to a shard by consistent hashing, so adding a shard
+/// only moves a fraction of the keys. Shards are identified by their position, so add
+/// new connection strings at the end. Batch operations group keys per shard and send
+/// one pipeline to each instance. Lifespan and refresh behave as they do for `RedisCache`.
+pub struct ShardedRedisCache<K, V> {
+    shards: Vec<RedisCache<K, V>>,
+    ring: HashRing,
+}
+
+impl<K, V> ShardedRedisCache<K, V>
+where
+    K: Display,
+    V: Serialize + DeserializeOwned,
+{
+    /// The shards, in the order of their connection strings
+    pub fn shards(&self) -> &[RedisCache<K, V>] {
+        &self.shards
+    }
+
+    /// The shard storing `key`
+    pub fn shard_for(&self, key: &K) -> &RedisCache<K, V> {
+        &self.shards[self.shard_index(key)]
+    }
+
+    fn shard_index(&self, key: &K) -> usize {
+        self.ring.shard(&self.shards[0].generate_key(key))
+    }
+
+    /// Split `(shard, item)` pairs into a list of items per shard
+    fn group<T>(&self, items: impl IntoIterator<Item = (usize, T)>) -> Vec<Vec<T>> {
+        let mut groups: Vec<Vec<T>> = self.shards.iter().map(|_| vec![]).collect();
+        for (shard, item) in items {
+            groups[shard].push(item);
+        }
+        groups
+    }
+
+    /// Retrieve several cached values, in the same order as `keys`, with one
+    /// round-trip per shard
+    pub fn cache_get_multi(&self, keys: &[K]) -> Result<Vec<Option<V>>, RedisCacheError> {
+        let groups = self.group(keys.iter().enumerate().map(|(i, key)| {
+            let key = self.shards[0].generate_key(key);
+            (self.ring.shard(&key), (i, key))
+        }));
+        let mut values: Vec<Option<V>> = keys.iter().map(|_| None).collect();
+        for (shard, group) in self.shards.iter().zip(groups) {
+            let (positions, keys): (Vec<usize>, Vec<String>) = group.into_iter().unzip();
+            for (i, value) in positions.into_iter().zip(shard.get_generated_multi(&keys)?) {
+                values[i] = value;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Insert several values with one round-trip per shard and return, in order, the
+    /// previous value of each entry, see `RedisCache::cache_replace_multi`
+    pub fn cache_replace_multi<I>(&self, entries: I) -> Result<Vec<Option<V>>, RedisCacheError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.scatter(entries, RedisCache::cache_replace_multi)
+    }
+
+    /// Insert several values with one round-trip per shard, only for keys that aren't already
+    /// cached. Returns, in order, whether each entry was newly inserted.
+    pub fn cache_set_multi_nx<I>(&self, entries: I) -> Result<Vec<bool>, RedisCacheError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+    {
+        self.scatter(entries, RedisCache::cache_set_multi_nx)
+    }
+
+    /// Send each shard its entries with `op` and put the results back in the order of `entries`
+    fn scatter<I, T, F>(&self, entries: I, op: F) -> Result<Vec<T>, RedisCacheError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        F: Fn(&RedisCache<K, V>, Vec<(K, V)>) -> Result<Vec<T>, RedisCacheError>,
+    {
+        let groups = self.group(
+            entries
+                .into_iter()
+                .enumerate()
+                .map(|(i, entry)| (self.shard_index(&entry.0), (i, entry))),
+        );
+        let len = groups.iter().map(Vec::len).sum();
+        let mut results: Vec<Option<T>> = (0..len).map(|_| None).collect();
+        for (shard, group) in self.shards.iter().zip(groups) {
+            let (positions, entries): (Vec<usize>, Vec<(K, V)>) = group.into_iter().unzip();
+            for (i, res) in positions.into_iter().zip(op(shard, entries)?) {
+                results[i] = Some(res);
+            }
+        }
+        Ok(results.into_iter().flatten().collect())
+    }
+}
+
+impl<K, V> IOCached<K, V> for ShardedRedisCache<K, V>
+where
+    K: Display,
+    V: Serialize + DeserializeOwned,
+{
+    type Error = RedisCacheError;
+
+    fn cache_get(&self, key: &K) -> Result<Option<V>, RedisCacheError> {
+        self.shard_for(key).cache_get(key)
+    }
+
+    fn cache_set(&self, key: K, val: V) -> Result<Option<V>, RedisCacheError> {
+        self.shard_for(&key).cache_set(key, val)
+    }
+
+    fn cache_remove(&self, key: &K) -> Result<Option<V>, RedisCacheError> {
+        self.shard_for(key).cache_remove(key)
+    }
+
+    fn cache_lifespan(&self) -> Option<u64> {
+        self.shards[0].cache_lifespan()
+    }
+
+    fn cache_set_lifespan(&mut self, seconds: u64) -> Option<u64> {
+        let mut old = None;
+        for shard in &mut self.shards {
+            old = shard.cache_set_lifespan(seconds);
+        }
+        old
+    }
+
+    fn cache_set_refresh(&mut self, refresh: bool) -> bool {
+        let mut old = false;
+        for shard in &mut self.shards {
+            old = shard.cache_set_refresh(refresh);
+        }
+        old
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum RedisCacheError {
+    #[error("redis error")]
+    RedisCacheError(redis::RedisError),
+    #[error("Timed out waiting for a reply from redis")]
+    Timeout,
+    #[error("The key is cached as having no value")]
+    NegativeCached,
+    #[error("Timed out waiting for a pooled redis connection, all of them are in use")]
+    PoolTimeout,
+    #[error("Could not open a pooled redis connection: {error}")]
+    PoolConnection { error: String },
+    #[error("No pooled redis connection available")]
+    PoolExhausted,
+    #[cfg(feature = "redis_bb8")]
+    #[error("redis bb8 pool error")]
+    Bb8PoolError(#[from] bb8::RunError<redis::RedisError>),
+    #[error("Error deserializing cached value {cached_value:?}: {error:?}")]
+    CacheDeserializationError {
+        cached_value: String,
+        error: serde_json::Error,
+    },
+    #[error("Cached value at {key:?} isn't valid UTF-8 JSON, it may have been written by another producer or corrupted")]
+    InvalidEncoding { key: String },
+    #[error("Error serializing cached value: {error:?}")]
+    CacheSerializationError { error: serde_json::Error },
+    #[error("Write acknowledged by {acked} of {required} required replicas before timing out")]
+    WriteWaitTimeout { acked: u32, required: u32 },
+    #[error("Error compressing or decompressing cached value: {error}")]
+    CompressionError { error: std::io::Error },
+    #[error("Error encrypting or decrypting cached value, it may be encrypted with another key")]
+    EncryptionError,
+    #[error("Cached value doesn't match its checksum, it was corrupted in redis")]
+    ChecksumMismatch,
+    #[error("Prefix {prefix:?} must not contain the key separator {separator:?}")]
+    InvalidPrefix { prefix: String, separator: char },
+    #[error("{command} requires redis {required} or later, the server runs {found}")]
+    UnsupportedServerVersion {
+        command: &'static str,
+        required: String,
+        found: String,
+    },
+    #[error("Value was changed concurrently on each of {attempts} update attempts")]
+    UpdateConflict { attempts: u32 },
+    #[error("Cached value of {len} bytes exceeds the read limit of {max} bytes")]
+    ValueTooLarge { len: usize, max: usize },
+    #[error("Error in cache {name:?}: {source}")]
+    Named {
+        name: String,
+        source: Box<RedisCacheError>,
+    },
+    #[error("Error in value codec: {error}")]
+    CodecError {
+        error: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("Keyspace notifications are disabled, `notify-keyspace-events` is {current:?} but must include `E` and `g` and `x`, or `EA`")]
+    KeyspaceNotificationsDisabled { current: String },
+}
+
+impl RedisCacheError {
+    /// The error without the cache name added by `set_name`, for matching on its kind
+    pub fn unnamed(&self) -> &RedisCacheError {
+        match self {
+            RedisCacheError::Named { source, .. } => source.unnamed(),
+            error => error,
+        }
+    }
+
+    /// Fill in the key of an `InvalidEncoding` error raised while decoding the value at `key`
+    fn at_key(self, key: &str) -> RedisCacheError {
+        match self {
+            RedisCacheError::InvalidEncoding { .. } => RedisCacheError::InvalidEncoding {
+                key: key.to_string(),
+            },
+            error => error,
+        }
+    }
+}
+
+/// Timeouts of `RedisCacheBuilder::set_command_timeout` are told apart from other errors
+impl From<redis::RedisError> for RedisCacheError {
+    fn from(error: redis::RedisError) -> Self {
+        if error.is_timeout() {
+            RedisCacheError::Timeout
+        } else {
+            RedisCacheError::RedisCacheError(error)
+        }
+    }
+}
+
+/// r2d2 only fails checkouts by timing out, reporting the last error opening a connection if
+/// there was one. Without an error, the pool was healthy but every connection stayed in use.
+impl From<r2d2::Error> for RedisCacheError {
+    fn from(error: r2d2::Error) -> Self {
+        let error = error.to_string();
+        match error.strip_prefix(R2D2_TIMEOUT_MESSAGE) {
+            Some("") => RedisCacheError::PoolTimeout,
+            Some(connection_error) => RedisCacheError::PoolConnection {
+                error: connection_error
+                    .strip_prefix(": ")
+                    .unwrap_or(connection_error)
+                    .to_string(),
+            },
+            None => RedisCacheError::PoolConnection { error },
+        }
+    }
+}
+
+/// The message of `r2d2::Error`, followed by the last connection error if any
+const R2D2_TIMEOUT_MESSAGE: &str = "timed out waiting for connection";
+
+/// Error returned by `cache_insert`
+#[derive(Error, Debug)]
+pub enum InsertError {
+    #[error("redis cache error")]
+    Cache(#[from] RedisCacheError),
+    #[error("a value is already cached for the key")]
+    AlreadyExists,
+}
+
+/// Error returned by read-through helpers that load missing values with a fallible loader
+#[derive(Error, Debug)]
+pub enum RedisCacheLoadError<E> {
+    #[error("redis cache error")]
+    Cache(#[from] RedisCacheError),
+    #[error("loader error")]
+    Loader(E),
+    #[error("the key is cached as having no value, or its loader failed recently")]
+    NegativeCached,
+}
+
+fn check_write_wait(required: u32, acked: u32) -> Result<(), RedisCacheError> {
+    if acked < required {
+        Err(RedisCacheError::WriteWaitTimeout { acked, required })
+    } else {
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CachedRedisValue<V> {
+    value: V,
+    /// The lifespan the value was set with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    lifespan: Option<u64>,
+}
+
+/// The metadata stored next to a cached value, see `RedisCache::cache_meta`
+///
+/// Only the metadata fields are deserialized, the stored value is skipped over.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct EntryMeta {
+    /// The lifespan the value was set with, `None` for values stored without one
+    #[serde(default)]
+    pub lifespan: Option<u64>,
+}
+
+/// Encodes and decodes cached values in place of JSON, see `RedisCacheBuilder::set_codec`
+///
+/// Unlike plain functions, a codec can hold state, like a handle to a schema registry.
+/// Encoded values are still wrapped in the cache's own envelope, so lifespans, negative
+/// entries, and soft removal work the same with any codec.
+pub trait ValueCodec<V>: Send + Sync {
+    /// Encode `value` for storage
+    fn encode(&self, value: &V) -> Result<String, RedisCacheError>;
+
+    /// Decode a value previously returned by `encode`
+    fn decode(&self, encoded: &str) -> Result<V, RedisCacheError>;
+}
+
+/// A `ValueCodec` for enums whose variants are stored as distinct types, see
+/// `RedisCacheBuilder::set_tagged_codec`
+///
+/// Each variant is registered with a tag, which is stored next to the variant's JSON and picks
+/// the type to deserialize on read. Tags identify stored values, so they must stay the same
+/// across releases, while variants can be reordered or renamed freely.
+///
+/// ```rust,no_run
+/// # use cached::TaggedCodec;
+/// # #[derive(serde::Serialize, serde::Deserialize)] struct User { name: String }
+/// # #[derive(serde::Serialize, serde::Deserialize)] struct Team { members: u32 }
+/// enum Profile {
+///     User(User),
+///     Team(Team),
+/// }
+///
+/// let codec = TaggedCodec::new()
+///     .variant("user", |p| match p { Profile::User(u) => Some(u), _ => None }, Profile::User)
+///     .variant("team", |p| match p { Profile::Team(t) => Some(t), _ => None }, Profile::Team);
+/// ```
+pub struct TaggedCodec<V> {
+    variants: Vec<TaggedVariant<V>>,
+}
+
+/// Encodes values of one variant, returning `None` for the other variants
+type EncodeVariant<V> = dyn Fn(&V) -> Option<serde_json::Result<serde_json::Value>> + Send + Sync;
+
+struct TaggedVariant<V> {
+    tag: String,
+    encode: Box<EncodeVariant<V>>,
+    decode: Box<dyn Fn(serde_json::Value) -> serde_json::Result<V> + Send + Sync>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TaggedValue {
+    tag: String,
+    value: serde_json::Value,
+}
+
+impl<V> TaggedCodec<V> {
+    /// A codec without any variants, add them with `variant`
+    pub fn new() -> Self {
+        TaggedCodec {
+            variants: Vec::new(),
+        }
+    }
+
+    /// Register a variant holding a `T` under `tag`. `project` returns the `T` of values of this
+    /// variant and `None` for the others, and `wrap` turns a `T` back into the variant.
+    pub fn variant<T>(mut self, tag: &str, project: fn(&V) -> Option<&T>, wrap: fn(T) -> V) -> Self
+    where
+        V: 'static,
+        T: Serialize + DeserializeOwned + 'static,
+    {
+        self.variants.push(TaggedVariant {
+            tag: tag.to_string(),
+            encode: Box::new(move |value| project(value).map(serde_json::to_value)),
+            decode: Box::new(move |value| serde_json::from_value(value).map(wrap)),
+        });
+        self
+    }
+}
+
+impl<V> Default for TaggedCodec<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> ValueCodec<V> for TaggedCodec<V> {
+    fn encode(&self, value: &V) -> Result<String, RedisCacheError> {
+        let (tag, encoded) = self
+            .variants
+            .iter()
+            .find_map(|variant| Some((&variant.tag, (variant.encode)(value)?)))
+            .ok_or_else(|| RedisCacheError::CodecError {
+                error: "value doesn't match any variant of the tagged codec".into(),
+            })?;
+        let tagged = TaggedValue {
+            tag: tag.clone(),
+            value: encoded.map_err(|e| RedisCacheError::CodecError { error: e.into() })?,
+        };
+        serde_json::to_string(&tagged).map_err(|e| RedisCacheError::CodecError { error: e.into() })
+    }
+
+    fn decode(&self, encoded: &str) -> Result<V, RedisCacheError> {
+        let tagged: TaggedValue = serde_json::from_str(encoded)
+            .map_err(|e| RedisCacheError::CodecError { error: e.into() })?;
+        let variant = self
+            .variants
+            .iter()
+            .find(|variant| variant.tag == tagged.tag)
+            .ok_or_else(|| RedisCacheError::CodecError {
+                error: format!("unknown tag {:?}", tagged.tag).into(),
+            })?;
+        (variant.decode)(tagged.value).map_err(|e| RedisCacheError::CodecError { error: e.into() })
+    }
+}
+
+/// Compression applied to cached values, see `RedisCacheBuilder::set_compression`
+///
+/// Compressed values start with the format's own magic bytes, so values are decompressed
+/// on read whichever format, if any, they were written with. This lets caches switching
+/// between formats, or shared with other producers, read each other's values.
+#[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionKind {
+    /// zstd at the default level
+    #[cfg(feature = "redis_zstd")]
+    Zstd,
+    /// gzip at the default level
+    #[cfg(feature = "redis_gzip")]
+    Gzip,
+}
+
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+/// Values compressed with a zstd dictionary are stored as these magic bytes, the
+/// dictionary's id, then the zstd frame
+const ZSTD_DICT_MAGIC: &[u8] = &[0x00, 0x43, 0x44, 0x01];
+#[cfg(feature = "redis_zstd")]
+const ZSTD_DICT_ID_LEN: usize = 4;
+/// Checksummed values are stored as these magic bytes, the big-endian CRC32 of the rest
+/// of the value, then the rest of the value
+const CHECKSUM_MAGIC: &[u8] = &[0x00, 0x43, 0x43, 0x01];
+const CHECKSUM_LEN: usize = 4;
+/// Encrypted values are stored as these magic bytes, the nonce, then the ciphertext
+const ENCRYPTED_MAGIC: &[u8] = &[0x00, 0x43, 0x45, 0x01];
+#[cfg(feature = "redis_encryption")]
+const NONCE_LEN: usize = 12;
+
+fn add_checksum(bytes: &[u8]) -> Vec<u8> {
+    let mut checksummed = Vec::with_capacity(CHECKSUM_MAGIC.len() + CHECKSUM_LEN + bytes.len());
+    checksummed.extend_from_slice(CHECKSUM_MAGIC);
+    checksummed.extend_from_slice(&crc32fast::hash(bytes).to_be_bytes());
+    checksummed.extend_from_slice(bytes);
+    checksummed
+}
+
+/// Verify and strip the checksum of `raw`, if it has one
+fn verify_checksum(raw: &[u8]) -> Result<&[u8], RedisCacheError> {
+    let rest = match raw.strip_prefix(CHECKSUM_MAGIC) {
+        None => return Ok(raw),
+        Some(rest) if rest.len() < CHECKSUM_LEN => return Err(RedisCacheError::ChecksumMismatch),
+        Some(rest) => rest,
+    };
+    let (checksum, bytes) = rest.split_at(CHECKSUM_LEN);
+    if checksum != crc32fast::hash(bytes).to_be_bytes() {
+        return Err(RedisCacheError::ChecksumMismatch);
+    }
+    Ok(bytes)
+}
+
+#[cfg(feature = "redis_encryption")]
+fn encrypt(cipher: &aes_gcm::Aes256Gcm, bytes: &[u8]) -> Result<Vec<u8>, RedisCacheError> {
+    use aes_gcm::aead::{Aead, AeadCore, OsRng};
+    let nonce = aes_gcm::Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, bytes)
+        .map_err(|_| RedisCacheError::EncryptionError)?;
+    let mut encrypted = Vec::with_capacity(ENCRYPTED_MAGIC.len() + NONCE_LEN + ciphertext.len());
+    encrypted.extend_from_slice(ENCRYPTED_MAGIC);
+    encrypted.extend_from_slice(&nonce);
+    encrypted.extend_from_slice(&ciphertext);
+    Ok(encrypted)
+}
+
+#[cfg(feature = "redis_encryption")]
+fn decrypt(cipher: Option<&aes_gcm::Aes256Gcm>, raw: &[u8]) -> Result<Vec<u8>, RedisCacheError> {
+    use aes_gcm::aead::Aead;
+    let cipher = cipher.ok_or(RedisCacheError::EncryptionError)?;
+    let rest = &raw[ENCRYPTED_MAGIC.len()..];
+    if rest.len() < NONCE_LEN {
+        return Err(RedisCacheError::EncryptionError);
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    cipher
+        .decrypt(aes_gcm::Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| RedisCacheError::EncryptionError)
+}
+
+#[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+fn compress(kind: CompressionKind, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    match kind {
+        #[cfg(feature = "redis_zstd")]
+        CompressionKind::Zstd => zstd::stream::encode_all(bytes, 0),
+        #[cfg(feature = "redis_gzip")]
+        CompressionKind::Gzip => {
+            use std::io::Write;
+            let mut encoder =
+                flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes)?;
+            encoder.finish()
+        }
+    }
+}
+
+/// A zstd dictionary set with `set_compression_dictionary`, prepared once for every value
+#[cfg(feature = "redis_zstd")]
+struct ZstdDictionary {
+    /// The first bytes of the dictionary's SHA-256 digest, recorded with each value
+    id: [u8; ZSTD_DICT_ID_LEN],
+    encoder: zstd::dict::EncoderDictionary<'static>,
+    decoder: zstd::dict::DecoderDictionary<'static>,
+}
+
+#[cfg(feature = "redis_zstd")]
+impl ZstdDictionary {
+    fn new(dictionary: &[u8]) -> Self {
+        use sha2::Digest;
+        let mut id = [0; ZSTD_DICT_ID_LEN];
+        id.copy_from_slice(&sha2::Sha256::digest(dictionary)[..ZSTD_DICT_ID_LEN]);
+        ZstdDictionary {
+            id,
+            encoder: zstd::dict::EncoderDictionary::copy(dictionary, 0),
+            decoder: zstd::dict::DecoderDictionary::copy(dictionary),
+        }
+    }
+
+    fn compress(&self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        let frame =
+            zstd::bulk::Compressor::with_prepared_dictionary(&self.encoder)?.compress(bytes)?;
+        let mut compressed =
+            Vec::with_capacity(ZSTD_DICT_MAGIC.len() + self.id.len() + frame.len());
+        compressed.extend_from_slice(ZSTD_DICT_MAGIC);
+        compressed.extend_from_slice(&self.id);
+        compressed.extend_from_slice(&frame);
+        Ok(compressed)
+    }
+
+    fn decompress(&self, raw: &[u8]) -> std::io::Result<Vec<u8>> {
+        use std::io::Read;
+        let rest = &raw[ZSTD_DICT_MAGIC.len()..];
+        if !rest.starts_with(&self.id) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "value is compressed with another zstd dictionary",
+            ));
+        }
+        let mut decoded = Vec::new();
+        zstd::stream::read::Decoder::with_prepared_dictionary(
+            std::io::BufReader::new(&rest[ZSTD_DICT_ID_LEN..]),
+            &self.decoder,
+        )?
+        .read_to_end(&mut decoded)?;
+        Ok(decoded)
+    }
+}
+
+/// Decompress `bytes` if they start with the magic bytes of a known format
+fn decompress(bytes: &[u8]) -> std::io::Result<std::borrow::Cow<'_, [u8]>> {
+    if bytes.starts_with(ZSTD_MAGIC) {
+        #[cfg(feature = "redis_zstd")]
+        return zstd::stream::decode_all(bytes).map(std::borrow::Cow::Owned);
+        #[cfg(not(feature = "redis_zstd"))]
+        return Err(unsupported_compression("zstd", "redis_zstd"));
+    }
+    if bytes.starts_with(GZIP_MAGIC) {
+        #[cfg(feature = "redis_gzip")]
+        {
+            use std::io::Read;
+            let mut decoded = Vec::new();
+            flate2::read::GzDecoder::new(bytes).read_to_end(&mut decoded)?;
+            return Ok(std::borrow::Cow::Owned(decoded));
+        }
+        #[cfg(not(feature = "redis_gzip"))]
+        return Err(unsupported_compression("gzip", "redis_gzip"));
+    }
+    Ok(std::borrow::Cow::Borrowed(bytes))
+}
+
+#[cfg(not(all(feature = "redis_zstd", feature = "redis_gzip")))]
+fn unsupported_compression(format: &str, feature: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "value is compressed with {}, enable the `{}` feature to read it",
+            format, feature
+        ),
+    )
+}
+
+/// The codec of a cache. Values are stored as JSON unless a `ValueCodec` is set,
+/// compressed if a `CompressionKind` is set, then encrypted if an encryption key is set.
+struct Codec<V> {
+    custom: Option<Arc<dyn ValueCodec<V>>>,
+    /// The format values `custom` fails to decode are read with, `Some(None)` for JSON
+    fallback: Option<Option<Arc<dyn ValueCodec<V>>>>,
+    json: JsonOptions,
+    max_read_bytes: Option<usize>,
+    checksum: bool,
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    compression: Option<CompressionKind>,
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    compression_min_ratio: Option<f32>,
+    #[cfg(feature = "redis_zstd")]
+    dictionary: Option<ZstdDictionary>,
+    #[cfg(feature = "redis_encryption")]
+    cipher: Option<aes_gcm::Aes256Gcm>,
+}
+
+impl<V> Codec<V>
+where
+    V: Serialize + DeserializeOwned,
+{
+    fn serialize(&self, value: &V, lifespan: Option<u64>) -> Result<Vec<u8>, RedisCacheError> {
+        let res = match self.custom {
+            None if self.json.non_finite_floats == NonFiniteFloats::String => {
+                serde_json::to_vec(&CachedRedisValue {
+                    value: json::FloatsAsStrings(value),
+                    lifespan,
+                })
+            }
+            None => serde_json::to_vec(&CachedRedisValue { value, lifespan }),
+            Some(ref codec) => serde_json::to_vec(&CachedRedisValue {
+                value: codec.encode(value)?,
+                lifespan,
+            }),
+        };
+        let bytes = res.map_err(|e| RedisCacheError::CacheSerializationError { error: e })?;
+        #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+        let bytes = match self.compression {
+            None => bytes,
+            Some(kind) => {
+                let compressed = self
+                    .compress(kind, &bytes)
+                    .map_err(|e| RedisCacheError::CompressionError { error: e })?;
+                match self.compression_min_ratio {
+                    Some(min) if (bytes.len() as f32) < compressed.len() as f32 * min => bytes,
+                    _ => compressed,
+                }
+            }
+        };
+        #[cfg(feature = "redis_encryption")]
+        let bytes = match self.cipher {
+            None => bytes,
+            Some(ref cipher) => encrypt(cipher, &bytes)?,
+        };
+        if self.checksum {
+            return Ok(add_checksum(&bytes));
+        }
+        Ok(bytes)
+    }
+
+    /// Decrypt and decompress `raw` as needed, returning the serialized value
+    fn decode<'a>(&self, raw: &'a [u8]) -> Result<std::borrow::Cow<'a, [u8]>, RedisCacheError> {
+        match self.max_read_bytes {
+            Some(max) if raw.len() > max => {
+                return Err(RedisCacheError::ValueTooLarge {
+                    len: raw.len(),
+                    max,
+                })
+            }
+            _ => {}
+        }
+        let raw = verify_checksum(raw)?;
+        if raw.starts_with(ENCRYPTED_MAGIC) {
+            #[cfg(feature = "redis_encryption")]
+            {
+                let decrypted = decrypt(self.cipher.as_ref(), raw)?;
+                return self
+                    .decompress(&decrypted)
+                    .map(|bytes| std::borrow::Cow::Owned(bytes.into_owned()))
+                    .map_err(|e| RedisCacheError::CompressionError { error: e });
+            }
+            #[cfg(not(feature = "redis_encryption"))]
+            return Err(RedisCacheError::EncryptionError);
+        }
+        self.decompress(raw)
+            .map_err(|e| RedisCacheError::CompressionError { error: e })
+    }
+
+    /// Decode `raw` like `decode`, checking the serialized value is UTF-8 as JSON must be.
+    /// Fails with an `InvalidEncoding` error without a key, filled in by `at_key`.
+    fn decode_text<'a>(
+        &self,
+        raw: &'a [u8],
+    ) -> Result<std::borrow::Cow<'a, [u8]>, RedisCacheError> {
+        let bytes = self.decode(raw)?;
+        match std::str::from_utf8(&bytes) {
+            Ok(_) => Ok(bytes),
+            Err(_) => Err(RedisCacheError::InvalidEncoding { key: String::new() }),
+        }
+    }
+
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    fn compress(&self, kind: CompressionKind, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        #[cfg(feature = "redis_zstd")]
+        if let (CompressionKind::Zstd, Some(dictionary)) = (kind, &self.dictionary) {
+            return dictionary.compress(bytes);
+        }
+        compress(kind, bytes)
+    }
+
+    /// Decompress `bytes`, including values compressed with the cache's zstd dictionary
+    fn decompress<'a>(&self, bytes: &'a [u8]) -> std::io::Result<std::borrow::Cow<'a, [u8]>> {
+        if bytes.starts_with(ZSTD_DICT_MAGIC) {
+            #[cfg(feature = "redis_zstd")]
+            return match self.dictionary {
+                Some(ref dictionary) => dictionary.decompress(bytes).map(std::borrow::Cow::Owned),
+                None => Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "value is compressed with a zstd dictionary, but none is set",
+                )),
+            };
+            #[cfg(not(feature = "redis_zstd"))]
+            return Err(unsupported_compression("zstd", "redis_zstd"));
+        }
+        decompress(bytes)
+    }
+
+    fn deserialize(&self, raw: &[u8]) -> Result<V, RedisCacheError> {
+        self.deserialize_any(raw).map(|(value, _)| value)
+    }
+
+    /// Deserialize `raw` like `deserialize`, also returning whether it was read with the
+    /// fallback format
+    fn deserialize_any(&self, raw: &[u8]) -> Result<(V, bool), RedisCacheError> {
+        let bytes = self.decode_text(raw)?;
+        match self.parse(self.custom.as_ref(), &bytes) {
+            Err(e) => match self.fallback {
+                Some(ref fallback) => self
+                    .parse(fallback.as_ref(), &bytes)
+                    .map(|value| (value, true))
+                    .map_err(|_| e),
+                None => Err(e),
+            },
+            Ok(value) => Ok((value, false)),
+        }
+    }
+
+    /// Parse a serialized value written with `custom`, or as JSON
+    fn parse(
+        &self,
+        custom: Option<&Arc<dyn ValueCodec<V>>>,
+        bytes: &[u8],
+    ) -> Result<V, RedisCacheError> {
+        match custom {
+            None if self.json.non_finite_floats != NonFiniteFloats::Null => Ok(
+                parse_cached_with::<CachedRedisValue<V>>(bytes, self.json.non_finite_floats)?.value,
+            ),
+            None => Ok(parse_cached::<CachedRedisValue<V>>(bytes)?.value),
+            Some(codec) => codec.decode(&parse_cached::<CachedRedisValue<String>>(bytes)?.value),
+        }
+    }
+
+    /// The encoded bytes of a cached entry's value, as `deserialize` would read them but without
+    /// deserializing a `V`: the value's JSON, or the output of the custom codec. Negative and
+    /// soft-removed entries are treated as missing values.
+    fn value_bytes(&self, key: &str, raw: Vec<u8>) -> Result<Option<Vec<u8>>, RedisCacheError> {
+        if raw == NEGATIVE_ENTRY.as_bytes() || raw.starts_with(SOFT_REMOVED_START.as_bytes()) {
+            return Ok(None);
+        }
+        let bytes = self.decode_text(&raw).map_err(|e| e.at_key(key))?;
+        match self.custom {
+            None => {
+                let cached: CachedRedisValue<Box<serde_json::value::RawValue>> =
+                    parse_cached(&bytes)?;
+                Ok(Some(cached.value.get().as_bytes().to_vec()))
+            }
+            Some(_) => Ok(Some(
+                parse_cached::<CachedRedisValue<String>>(&bytes)?
+                    .value
+                    .into_bytes(),
+            )),
+        }
+    }
+
+    /// Serialize and deserialize `value` again
+    fn roundtrip(&self, value: &V) -> Result<V, RedisCacheError> {
+        self.deserialize(&self.serialize(value, None)?)
+    }
+
+    /// Read the metadata of the cached entry at `key` without deserializing its value
+    fn meta(&self, key: &str, raw: &[u8]) -> Result<EntryMeta, RedisCacheError> {
+        let bytes = self.decode_text(raw).map_err(|e| e.at_key(key))?;
+        parse_cached(&bytes)
+    }
+
+    /// Read the metadata of a cached entry, negative and soft-removed entries are treated as
+    /// missing values
+    fn entry_meta(&self, key: &str, raw: Vec<u8>) -> Result<Option<EntryMeta>, RedisCacheError> {
+        if raw == NEGATIVE_ENTRY.as_bytes() || raw.starts_with(SOFT_REMOVED_START.as_bytes()) {
+            Ok(None)
+        } else {
+            self.meta(key, &raw).map(Some)
+        }
+    }
+
+    /// Deserialize the cached entry at `key`, negative and soft-removed entries are treated as
+    /// missing values
+    fn entry(&self, key: &str, raw: Vec<u8>) -> Result<Option<V>, RedisCacheError> {
+        if raw == NEGATIVE_ENTRY.as_bytes() || raw.starts_with(SOFT_REMOVED_START.as_bytes()) {
+            Ok(None)
+        } else {
+            self.deserialize(&raw).map(Some).map_err(|e| e.at_key(key))
+        }
+    }
+
+    /// Deserialize the cached entry at `key` like `entry`, also returning whether it was read
+    /// with the fallback format and should be rewritten
+    fn entry_any(&self, key: &str, raw: &[u8]) -> Result<Option<(V, bool)>, RedisCacheError> {
+        if raw == NEGATIVE_ENTRY.as_bytes() || raw.starts_with(SOFT_REMOVED_START.as_bytes()) {
+            Ok(None)
+        } else {
+            self.deserialize_any(raw)
+                .map(Some)
+                .map_err(|e| e.at_key(key))
+        }
+    }
+
+    /// Deserialize the value wrapped by a soft-removed entry
+    fn soft_removed(&self, raw: &[u8]) -> Result<V, RedisCacheError> {
+        let wrapped = raw
+            .strip_prefix(SOFT_REMOVED_START.as_bytes())
+            .and_then(|rest| rest.strip_suffix(b"}"))
+            .unwrap_or(raw);
+        self.deserialize(wrapped)
+    }
+
+    fn outcome(&self, key: &str, raw: Option<Vec<u8>>) -> GetOutcome<V> {
+        let res = match raw {
+            None => return GetOutcome::Miss,
+            Some(raw) if raw == NEGATIVE_ENTRY.as_bytes() => return GetOutcome::Negative,
+            Some(raw) if raw.starts_with(SOFT_REMOVED_START.as_bytes()) => {
+                self.soft_removed(&raw).map(GetOutcome::Removed)
+            }
+            Some(raw) => self.deserialize(&raw).map(GetOutcome::Hit),
+        };
+        res.unwrap_or_else(|e| GetOutcome::Corrupt(e.at_key(key)))
+    }
+}
+
+fn parse_cached<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, RedisCacheError> {
+    serde_json::from_slice(bytes).map_err(|e| deserialization_error(bytes, e))
+}
+
+/// Parse `bytes` like `parse_cached`, reading floats stored with `mode`
+fn parse_cached_with<T: DeserializeOwned>(
+    bytes: &[u8],
+    mode: NonFiniteFloats,
+) -> Result<T, RedisCacheError> {
+    let mut de = serde_json::Deserializer::from_slice(bytes);
+    T::deserialize(json::De::new(&mut de, mode))
+        .and_then(|value| de.end().map(|_| value))
+        .map_err(|e| deserialization_error(bytes, e))
+}
+
+fn deserialization_error(bytes: &[u8], error: serde_json::Error) -> RedisCacheError {
+    RedisCacheError::CacheDeserializationError {
+        cached_value: String::from_utf8_lossy(bytes).into_owned(),
+        error,
+    }
+}
+
+/// Conditions for the `EXPIRE` refreshing TTLs, see `RedisCacheBuilder::set_refresh_expire_option`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireOption {
+    /// Only set the TTL if the key has none
+    Nx,
+    /// Only set the TTL if the key already has one
+    Xx,
+    /// Only set the TTL if it's greater than the current one
+    Gt,
+    /// Only set the TTL if it's less than the current one
+    Lt,
+}
+
+impl ExpireOption {
+    fn as_arg(self) -> &'static str {
+        match self {
+            ExpireOption::Nx => "NX",
+            ExpireOption::Xx => "XX",
+            ExpireOption::Gt => "GT",
+            ExpireOption::Lt => "LT",
+        }
+    }
+}
+
+/// Build the command refreshing the TTL of `key` to `seconds` on a cache hit
+fn refresh_cmd(key: &str, seconds: u64, option: Option<ExpireOption>) -> redis::Cmd {
+    let mut cmd = redis::cmd("EXPIRE");
+    cmd.arg(key).arg(seconds);
+    if let Some(option) = option {
+        cmd.arg(option.as_arg());
+    }
+    cmd
+}
+
+/// A `(major, minor)` redis version
+type ServerVersion = (u32, u32);
+
+/// The version of `EXPIRE` options
+const EXPIRE_OPTIONS_VERSION: ServerVersion = (7, 0);
+/// The version of `SET ... KEEPTTL`
+const KEEPTTL_VERSION: ServerVersion = (6, 0);
+/// The version of `MEMORY USAGE`
+const MEMORY_USAGE_VERSION: ServerVersion = (4, 0);
+/// The version of streams
+const STREAMS_VERSION: ServerVersion = (5, 0);
+
+/// The version of the server described by the reply to `INFO server`, if it reports one
+fn parse_server_version(info: &str) -> Option<ServerVersion> {
+    let version = info
+        .lines()
+        .find_map(|line| line.strip_prefix("redis_version:"))?;
+    let mut parts = version
+        .trim()
+        .split('.')
+        .map(|part| part.parse::<u32>().ok());
+    Some((parts.next()??, parts.next().flatten().unwrap_or(0)))
+}
+
+/// Whether a server at `version` has a feature added in `required`. Servers that don't report
+/// a version are assumed not to, so they get the compatible implementation.
+fn version_supports(version: Option<ServerVersion>, required: ServerVersion) -> bool {
+    matches!(version, Some(version) if version >= required)
+}
+
+/// Fail with `UnsupportedServerVersion` if a server at `version` lacks `command`, added in
+/// `required`. Servers that don't report a version are let through.
+fn require_version(
+    version: Option<ServerVersion>,
+    command: &'static str,
+    required: ServerVersion,
+) -> Result<(), RedisCacheError> {
+    match version {
+        Some(found) if found < required => Err(RedisCacheError::UnsupportedServerVersion {
+            command,
+            required: format!("{}.{}", required.0, required.1),
+            found: format!("{}.{}", found.0, found.1),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Number of keys sampled by `cache_approx_len`
+const APPROX_LEN_SAMPLES: usize = 100;
+
+/// Build the pipeline sampling `APPROX_LEN_SAMPLES` random keys
+fn random_keys_pipe() -> redis::Pipeline {
+    let mut pipe = redis::pipe();
+    for _ in 0..APPROX_LEN_SAMPLES {
+        pipe.cmd("RANDOMKEY");
+    }
+    pipe
+}
+
+/// Scale the share of `sampled` keys under `prefix` to the `total` number of keys
+fn estimate_len(total: u64, sampled: &[Option<Vec<u8>>], prefix: &str) -> u64 {
+    if sampled.is_empty() {
+        return 0;
+    }
+    let matching = sampled
+        .iter()
+        .filter(|key| matches!(key, Some(key) if key.starts_with(prefix.as_bytes())))
+        .count() as u64;
+    total * matching / sampled.len() as u64
+}
+
+/// Extend `seconds` by a random `0..=max_jitter` seconds
+fn jittered(seconds: u64, max_jitter: u64) -> u64 {
+    if max_jitter == 0 {
+        return seconds;
+    }
+    // every `RandomState` is seeded differently, which is random enough to spread expiries
+    use std::hash::{BuildHasher, Hasher};
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    seconds + random % (max_jitter + 1)
+}
+
+/// Build the command getting `key` and refreshing its TTL to `seconds` if it exists
+fn get_and_refresh_cmd(key: &str, seconds: u64) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(GET_AND_REFRESH_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(seconds)
+        .arg(CHUNK_MANIFEST_START)
+        .arg(chunk_infix());
+    cmd
+}
+
+/// Build the command replying with the TTL of `key`, or `-2` if it's missing or only holds a
+/// negative or soft-removed entry
+fn live_ttl_cmd(key: &str) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(LIVE_TTL_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(NEGATIVE_ENTRY)
+        .arg(SOFT_REMOVED_START);
+    cmd
+}
+
+/// Build the command resetting the TTL of `key` and its chunks to `seconds`, replying with
+/// whether `key` exists
+fn touch_cmd(key: &str, seconds: u64) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(TOUCH_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(seconds)
+        .arg(CHUNK_MANIFEST_START)
+        .arg(chunk_infix());
+    cmd
+}
+
+/// Build the command getting `key`, moving `fallback_key` to `key` if only it exists
+fn get_with_fallback_cmd(key: &str, fallback_key: &str) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(GET_WITH_FALLBACK_SCRIPT)
+        .arg(2)
+        .arg(key)
+        .arg(fallback_key);
+    cmd
+}
+
+/// Build the command adding `by` to the counter at `key`, clamped to `[min, max]`
+fn incr_bounded_cmd(
+    key: &str,
+    by: i64,
+    min: i64,
+    max: i64,
+    seconds: u64,
+    keep_ttl: bool,
+) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(INCR_BOUNDED_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(by)
+        .arg(min)
+        .arg(max)
+        .arg(seconds)
+        .arg(keep_ttl as u8);
+    cmd
+}
+
+/// Build the command setting `key` to `val` without changing its TTL, replying with its previous value
+fn set_keep_ttl_cmd(key: &str, val: Vec<u8>, seconds: u64, keep_ttl: bool) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(SET_KEEP_TTL_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(val)
+        .arg(seconds)
+        .arg(keep_ttl as u8);
+    cmd
+}
+
+/// Build the command replacing the value `current` at `key` with `val`, keeping its TTL, and
+/// replying with whether `key` still held `current`
+fn rewrite_cmd(key: &str, current: &[u8], val: Vec<u8>, keep_ttl: bool) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(REWRITE_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(current)
+        .arg(val)
+        .arg(keep_ttl as u8);
+    cmd
+}
+
+/// Build the command setting `key` to `val` for `seconds` if it's missing or only holds a
+/// negative or soft-removed entry, and refreshing it otherwise if `refresh` is set, replying
+/// with whether it was set
+fn set_if_absent_cmd(key: &str, val: &[u8], seconds: u64, refresh: bool) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(SET_IF_ABSENT_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(val)
+        .arg(seconds)
+        .arg(NEGATIVE_ENTRY)
+        .arg(SOFT_REMOVED_START)
+        .arg(CHUNK_MANIFEST_START)
+        .arg(chunk_infix())
+        .arg(refresh as u8);
+    cmd
+}
+
+/// Tag sets are stored under the cache prefix, after this
+const TAG_KEY_PREFIX: &str = "tag:";
+
+/// Build the atomic pipeline setting `key` to `val` for `seconds` and adding it to each of the
+/// `tag_keys` sets, which are kept for `tag_seconds`. Replies with the previous value of `key`.
+fn set_tagged_pipe(
+    key: &str,
+    val: Vec<u8>,
+    seconds: u64,
+    tag_keys: &[String],
+    tag_seconds: u64,
+) -> redis::Pipeline {
+    let mut pipe = redis::pipe();
+    pipe.atomic();
+    pipe.get(key);
+    pipe.set_ex::<&str, Vec<u8>>(key, val, seconds as usize)
+        .ignore();
+    for tag_key in tag_keys {
+        pipe.sadd(tag_key, key).ignore();
+        pipe.expire(tag_key, tag_seconds as usize).ignore();
+    }
+    pipe
+}
+
+/// Chunked values of `set_chunked_storage` store a manifest under their key, starting with this.
+/// Values are always wrapped in a `CachedRedisValue`, so this can't collide with a real value.
+const CHUNK_MANIFEST_START: &str = r#"{"chunks":"#;
+
+/// The manifest of a value stored in parts by `set_chunked_storage`
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ChunkManifest {
+    chunks: usize,
+}
+
+/// Parse `raw` as the manifest of a chunked value, if it is one
+fn chunk_manifest(raw: &[u8]) -> Option<ChunkManifest> {
+    if !raw.starts_with(CHUNK_MANIFEST_START.as_bytes()) {
+        return None;
+    }
+    serde_json::from_slice(raw).ok()
+}
+
+/// The key of part `index` of the chunked value at `key`
+fn chunk_key(key: &str, index: usize) -> String {
+    format!("{}{}{}", key, chunk_infix(), index)
+}
+
+/// What separates the key of a chunked value from the index in the keys of its parts
+fn chunk_infix() -> String {
+    format!("{sep}part{sep}", sep = KEY_SEPARATOR)
+}
+
+/// Add the commands storing `bytes` at `key` in parts of at most `chunk_size` bytes to `pipe`,
+/// deleting the parts past the new ones of the `prev_chunks` parts of the previous value
+fn add_chunked(
+    pipe: &mut redis::Pipeline,
+    key: &str,
+    bytes: &[u8],
+    ttl: usize,
+    chunk_size: usize,
+    prev_chunks: usize,
+) -> Result<(), RedisCacheError> {
+    let chunks: Vec<&[u8]> = bytes.chunks(chunk_size).collect();
+    let manifest = serde_json::to_vec(&ChunkManifest {
+        chunks: chunks.len(),
+    })
+    .map_err(|e| RedisCacheError::CacheSerializationError { error: e })?;
+    for (index, chunk) in chunks.iter().enumerate() {
+        pipe.set_ex(chunk_key(key, index), *chunk, ttl).ignore();
+    }
+    for index in chunks.len()..prev_chunks {
+        pipe.del(chunk_key(key, index)).ignore();
+    }
+    pipe.set_ex(key, manifest, ttl).ignore();
+    Ok(())
+}
+
+/// The key index set of `set_max_keys` is stored under the cache prefix, as this
+const KEY_INDEX_KEY: &str = "index:keys";
+
+/// The recency set of `set_track_recency` is stored under the cache prefix, as this
+const RECENT_KEY: &str = "__recent";
+
+/// Milliseconds since the unix epoch, scoring keys in the recency set
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64)
+}
+
+/// Split `keys` into those that still exist and those that don't, by their `EXISTS` replies
+fn partition_live(keys: Vec<String>, exists: Vec<bool>) -> (Vec<String>, Vec<String>) {
+    let (live, stale): (Vec<_>, Vec<_>) = keys
+        .into_iter()
+        .zip(exists)
+        .partition(|(_, exists)| *exists);
+    (
+        live.into_iter().map(|(key, _)| key).collect(),
+        stale.into_iter().map(|(key, _)| key).collect(),
+    )
+}
+
+/// Parse the `keys` under `prefix` as `K`, skipping the others
+fn parse_keys<K: std::str::FromStr>(prefix: &str, keys: Vec<String>) -> Vec<K> {
+    keys.into_iter()
+        .filter_map(|key| key.strip_prefix(prefix)?.parse().ok())
+        .collect()
+}
+
+/// Build the command indexing `key` in `index_key` and evicting random keys beyond `max_keys`,
+/// replying with the number evicted
+fn evict_cmd(index_key: &str, recent_key: Option<String>, key: &str, max_keys: u64) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(EVICT_SCRIPT)
+        .arg(1 + recent_key.is_some() as usize)
+        .arg(index_key)
+        .arg(recent_key)
+        .arg(key)
+        .arg(max_keys)
+        .arg(CHUNK_MANIFEST_START)
+        .arg(chunk_infix());
+    cmd
+}
+
+/// Build the command deleting the keys in the tag set `tag_key`, replying with the number deleted
+fn invalidate_tag_cmd(tag_key: &str) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(INVALIDATE_TAG_SCRIPT).arg(1).arg(tag_key);
+    cmd
+}
+
+/// Build the command reading `key`, or `fallback_key` if `key` is missing and a fallback is set
+fn get_cmd(key: &str, fallback_key: Option<String>) -> redis::Cmd {
+    match fallback_key {
+        None => {
+            let mut cmd = redis::cmd("GET");
+            cmd.arg(key);
+            cmd
+        }
+        Some(fallback_key) => get_with_fallback_cmd(key, &fallback_key),
+    }
+}
+
+/// Build the command renaming `from` to `to`, replying with whether it was renamed
+fn rename_cmd(from: &str, to: &str) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(RENAME_SCRIPT).arg(2).arg(from).arg(to);
+    cmd
+}
+
+/// Build the command soft-removing `key` for `grace_seconds`, replying with its previous value
+fn soft_remove_cmd(key: &str, grace_seconds: u64) -> redis::Cmd {
+    let mut cmd = redis::cmd("EVAL");
+    cmd.arg(SOFT_REMOVE_SCRIPT)
+        .arg(1)
+        .arg(key)
+        .arg(grace_seconds);
+    cmd
+}
+
+/// Map the result of a redis `TTL` command to the remaining seconds.
+/// Redis replies `-2` for missing keys and `-1` for keys without an expiry.
+fn ttl_from_reply(ttl: i64) -> Option<u64> {
+    if ttl < 0 {
+        None
+    } else {
+        Some(ttl as u64)
+    }
+}
+
+impl<K, V> IOCached<K, V> for RedisCache<K, V>
+where
+    K: Display,
+    V: Serialize + DeserializeOwned,
+{
+    type Error = RedisCacheError;
+
+    fn cache_get(&self, key: &K) -> Result<Option<V>, RedisCacheError> {
+        let key = self.generate_key(key);
+        let started = Instant::now();
+        let res = self.get_value(&key);
+        self.observe(CacheOp::Get, &key, started, &res);
+        self.named(res)
+    }
+
+    fn cache_set(&self, key: K, val: V) -> Result<Option<V>, RedisCacheError> {
+        let key = self.generate_key(&key);
+        let started = Instant::now();
+        let seconds = self.lifespan_of(&val);
+        let res = self.set_value(&key, val, seconds);
+        self.observe(CacheOp::Set, &key, started, &res);
+        let res = self.named(res);
+        match res {
+            Err(_) if self.ignore_set_errors => Ok(None),
+            res => res,
+        }
+    }
+
+    fn cache_remove(&self, key: &K) -> Result<Option<V>, RedisCacheError> {
+        let key = self.generate_key(key);
+        let started = Instant::now();
+        let res = self.remove_value(&key);
+        self.observe(CacheOp::Remove, &key, started, &res);
+        self.named(res)
+    }
+
+    fn cache_lifespan(&self) -> Option<u64> {
+        Some(self.seconds)
+    }
+
+    fn cache_set_lifespan(&mut self, seconds: u64) -> Option<u64> {
+        let old = self.seconds;
+        self.seconds = seconds;
+        Some(old)
+    }
+
+    fn cache_set_refresh(&mut self, refresh: bool) -> bool {
+        let old = self.refresh;
+        self.refresh = refresh;
+        old
+    }
+}
+
+#[cfg(all(
+    feature = "async",
+    any(feature = "redis_async_std", feature = "redis_tokio")
+))]
+mod async_redis {
+    use super::*;
+    use std::collections::HashMap;
+    use std::hash::Hash;
+    use {crate::IOCachedAsync, async_trait::async_trait, futures::Future};
+
+    pub struct AsyncRedisCacheBuilder<K, V> {
+        seconds: u64,
+        refresh: bool,
+        negative_seconds: Option<u64>,
+        prefix: String,
+        fallback_prefix: Option<String>,
+        name: Option<String>,
+        connection_string: Option<String>,
+        env_namespace_from: Option<String>,
+        hash_tag: Option<String>,
+        ignore_set_errors: bool,
+        event_sink: Option<SyncSender<CacheEvent>>,
+        connection_observer: Option<ConnectionObserver>,
+        write_wait: Option<(u32, Duration)>,
+        soft_remove: Option<u64>,
+        lifespan_jitter: u64,
+        key_hasher: Option<KeyHasher>,
+        ttl_fn: Option<TtlFn<V>>,
+        key_normalization: Normalization,
+        max_keys: Option<u64>,
+        track_recency: bool,
+        refresh_expire_option: Option<ExpireOption>,
+        batch_chunk_size: Option<usize>,
+        negative_seconds_on_error: Option<u64>,
+        serve_stale_on_loader_error: bool,
+        #[cfg(feature = "redis_bb8")]
+        bb8_pool_size: Option<u32>,
+        codec: Option<Arc<dyn ValueCodec<V>>>,
+        fallback_codec: Option<Option<Arc<dyn ValueCodec<V>>>>,
+        json_options: JsonOptions,
+        max_read_bytes: Option<usize>,
+        checksum: bool,
+        #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+        compression: Option<CompressionKind>,
+        #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+        compression_min_ratio: Option<f32>,
+        #[cfg(feature = "redis_zstd")]
+        compression_dictionary: Option<Vec<u8>>,
+        #[cfg(feature = "redis_encryption")]
+        encryption_key: Option<[u8; 32]>,
+        #[cfg(feature = "chaos")]
+        fault_injector: Option<FaultInjector>,
+        _phantom_k: PhantomData<K>,
+        _phantom_v: PhantomData<V>,
+    }
+
+    impl<K, V> AsyncRedisCacheBuilder<K, V>
+    where
+        K: Display,
+        V: Serialize + DeserializeOwned,
+    {
+        /// Initialize a `RedisCacheBuilder`
+        pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> AsyncRedisCacheBuilder<K, V> {
+            Self {
+                seconds,
+                refresh: false,
+                negative_seconds: None,
+                prefix: prefix.as_ref().to_string(),
+                fallback_prefix: None,
+                name: None,
+                connection_string: None,
+                env_namespace_from: None,
+                hash_tag: None,
+                ignore_set_errors: false,
+                event_sink: None,
+                connection_observer: None,
+                write_wait: None,
+                soft_remove: None,
+                lifespan_jitter: 0,
+                key_hasher: None,
+                ttl_fn: None,
+                key_normalization: Normalization::None,
+                max_keys: None,
+                track_recency: false,
+                refresh_expire_option: None,
+                batch_chunk_size: None,
+                negative_seconds_on_error: None,
+                serve_stale_on_loader_error: false,
+                #[cfg(feature = "redis_bb8")]
+                bb8_pool_size: None,
+                codec: None,
+                fallback_codec: None,
+                json_options: JsonOptions::default(),
+                max_read_bytes: None,
+                checksum: false,
+                #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                compression: None,
+                #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                compression_min_ratio: None,
+                #[cfg(feature = "redis_zstd")]
+                compression_dictionary: None,
+                #[cfg(feature = "redis_encryption")]
+                encryption_key: None,
+                #[cfg(feature = "chaos")]
+                fault_injector: None,
+                _phantom_k: Default::default(),
+                _phantom_v: Default::default(),
+            }
+        }
+
+        /// Initialize a builder from the environment. The prefix is read from `CACHED_REDIS_PREFIX`,
+        /// the lifespan in seconds from `CACHED_REDIS_LIFESPAN`, and whether hits refresh the TTL from
+        /// `CACHED_REDIS_REFRESH` (`true` or `false`, defaults to `false`). As with `new`, the connection
+        /// string is read from `CACHED_REDIS_CONNECTION_STRING` by `build` unless set explicitly.
+        pub fn from_env() -> Result<Self, RedisCacheBuildError> {
+            let settings = load_env_settings()?;
+            Ok(Self::new(settings.prefix, settings.seconds).set_refresh(settings.refresh))
+        }
+
+        /// Specify the cache TTL/lifespan in seconds
+        pub fn set_lifespan(mut self, seconds: u64) -> Self {
+            self.seconds = seconds;
+            self
+        }
+
+        /// Specify whether cache hits refresh the TTL
+        pub fn set_refresh(mut self, refresh: bool) -> Self {
+            self.refresh = refresh;
+            self
+        }
+
+        /// Make the `EXPIRE` refreshing the TTL on cache hits conditional, e.g. `ExpireOption::Gt`
+        /// to never shorten the TTL of a key set with a longer lifespan. Requires redis 7,
+        /// `build` checks the server version and falls back to a plain `EXPIRE` on older servers.
+        pub fn set_refresh_expire_option(mut self, option: ExpireOption) -> Self {
+            self.refresh_expire_option = Some(option);
+            self
+        }
+
+        /// Specify the TTL in seconds of negative entries stored by `cache_set_miss`,
+        /// usually shorter than the lifespan. Defaults to the lifespan.
+        pub fn set_negative_lifespan(mut self, seconds: u64) -> Self {
+            self.negative_seconds = Some(seconds);
+            self
+        }
+
+        /// When the loader of `cache_try_get_or_set_with` fails, cache a negative entry for
+        /// `seconds`, during which calls for the key return `RedisCacheLoadError::NegativeCached`
+        /// instead of running the loader again. This shields a failing backend from retry storms,
+        /// but also delays recovery from transient errors by up to `seconds`, so keep it short,
+        /// typically a few seconds. Negative entries cached with `cache_set_miss` are reported
+        /// the same way.
+        pub fn set_negative_cache_on_error(mut self, seconds: u64) -> Self {
+            self.negative_seconds_on_error = Some(seconds);
+            self
+        }
+
+        /// When the loader of `cache_try_get_or_set_with` fails, return the last known value
+        /// of the key instead of the loader's error, however stale, if one is still stored.
+        /// Missing values only remain readable when they were soft-removed (see
+        /// `set_soft_remove`), so this serves soft-removed values for their grace period.
+        /// A stale value served this way isn't cached again, and takes precedence over
+        /// `set_negative_cache_on_error`. Defaults to `false`.
+        pub fn set_serve_stale_on_loader_error(mut self, serve_stale: bool) -> Self {
+            self.serve_stale_on_loader_error = serve_stale;
+            self
+        }
+
+        /// Configure the error handling settings of `policy` at once, see `ResiliencePolicy`.
+        /// `fail_fast_on_exhaustion` doesn't apply to `AsyncRedisCache`.
+        pub fn set_resilience(mut self, policy: ResiliencePolicy) -> Self {
+            self.ignore_set_errors = policy.ignore_set_errors;
+            self.serve_stale_on_loader_error = policy.serve_stale_on_loader_error;
+            self
+        }
+
+        /// Set the prefix for the keys. The prefix must not contain `:`, this is checked by `build`
+        pub fn set_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
+            self.prefix = prefix.as_ref().to_string();
+            self
+        }
+
+        /// On a `cache_get` miss, look the key up under `prefix` too and, if found, move the
+        /// value with its remaining TTL to the cache's own prefix. This keeps caches warm while
+        /// changing prefixes. Like the cache's own prefix, `prefix` must not contain `:`.
+        pub fn set_fallback_prefix<S: AsRef<str>>(mut self, prefix: S) -> Self {
+            self.fallback_prefix = Some(prefix.as_ref().to_string());
+            self
+        }
+
+        /// Name the cache in its errors, to tell caches apart in apps with several of them. Errors of
+        /// gets, sets, and removes are wrapped in `RedisCacheError::Named`, see `RedisCacheError::unnamed`.
+        pub fn set_name<S: AsRef<str>>(mut self, name: S) -> Self {
+            self.name = Some(name.as_ref().to_string());
+            self
+        }
+
+        /// Set the connection string for redis
+        pub fn set_connection_string(mut self, cs: &str) -> Self {
+            self.connection_string = Some(cs.to_string());
+            self
+        }
+
+        /// Wrap `tag` in braces in front of every key of the cache, so Redis Cluster hashes only the
+        /// tag and all the keys land in the same slot. This lets multi-key commands like `MGET`,
+        /// the scripts behind `set_max_keys` and `cache_set_tagged`, and fallback reads work on
+        /// a cluster, and keeps batch operations on one node. All of the cache's traffic and
+        /// memory then sits on a single node, which can turn it into a hot spot, so this suits
+        /// small groups of related keys rather than large caches. `build` fails if `tag` is empty
+        /// or contains a brace.
+        pub fn set_hash_tag<S: AsRef<str>>(mut self, tag: S) -> Self {
+            self.hash_tag = Some(tag.as_ref().to_string());
+            self
+        }
+
+        /// Namespace keys by the value of the env var `var` (e.g. `APP_ENV`), read by `build`,
+        /// so caches in different environments sharing a redis never see each other's keys.
+        /// `build` fails if the var is unset or its value contains `:`.
+        pub fn set_env_namespace_from(mut self, var: &str) -> Self {
+            self.env_namespace_from = Some(var.to_string());
+            self
+        }
+
+        /// Specify whether errors during `cache_set` are swallowed, treating the cache
+        /// as best-effort on writes. Ignored errors still count towards `CacheStats::errors`
+        /// and `cache_set` returns `Ok(None)` instead.
+        pub fn set_ignore_set_errors(mut self, ignore: bool) -> Self {
+            self.ignore_set_errors = ignore;
+            self
+        }
+
+        /// Send a `CacheEvent` to `sink` for every get, set, and remove. Sending never
+        /// blocks, events are dropped when the channel is full.
+        pub fn set_event_sink(mut self, sink: SyncSender<CacheEvent>) -> Self {
+            self.event_sink = Some(sink);
+            self
+        }
+
+        /// Call `observer` whenever a connection to redis is opened or lost, for visibility
+        /// into connection churn. Connections report `Connected` when opened, and every
+        /// operation failing on a dropped connection reports `Disconnected`.
+        pub fn set_connection_observer(mut self, observer: ConnectionObserver) -> Self {
+            self.connection_observer = Some(observer);
+            self
+        }
+
+        /// Make `cache_set` wait until the write is acknowledged by `num_replicas` replicas,
+        /// using redis `WAIT`. If fewer replicas acknowledge within `timeout`, `cache_set`
+        /// returns `RedisCacheError::WriteWaitTimeout`, though the value has already been
+        /// written to the primary. A zero `timeout` waits forever.
+        pub fn set_write_wait(mut self, num_replicas: u32, timeout: Duration) -> Self {
+            self.write_wait = Some((num_replicas, timeout));
+            self
+        }
+
+        /// Make `cache_remove` soft-remove values: instead of being deleted, a removed value is kept
+        /// for `grace_seconds` but treated as missing by `cache_get`, while `cache_get_detailed` reports
+        /// it as `GetOutcome::Removed` so in-flight readers can still use it. Setting the key again
+        /// with `cache_set` revives it with a full lifespan. `cache_exists` and `cache_ttl` treat
+        /// soft-removed values as missing too.
+        pub fn set_soft_remove(mut self, grace_seconds: u64) -> Self {
+            self.soft_remove = Some(grace_seconds);
+            self
+        }
+
+        /// Extend the TTL of values written by `cache_set` and its variants, the batch sets and
+        /// pipelined sets by a random `0..=max_seconds` seconds, so values cached together don't all
+        /// expire at once. The lifespan recorded with a value, read by `cache_original_lifespan`, is
+        /// the lifespan before jitter. Refreshes on `cache_get` reset the TTL to the cache's lifespan
+        /// without jitter.
+        pub fn set_lifespan_jitter(mut self, max_seconds: u64) -> Self {
+            self.lifespan_jitter = max_seconds;
+            self
+        }
+
+        /// Specify whether keys are hashed with SHA-256 before being prefixed, keeping keys short
+        /// and uniform however long their `Display` output is. Hashed keys can't be read back from
+        /// redis, so `watch_keyspace` can't report them.
+        pub fn set_hash_keys(mut self, hash: bool) -> Self {
+            self.key_hasher = if hash {
+                Some(Arc::new(sha256_hex))
+            } else {
+                None
+            };
+            self
+        }
+
+        /// Hash keys with `hasher` instead of SHA-256, see `set_hash_keys`. A faster
+        /// non-cryptographic hash suits keys that aren't chosen by adversaries.
+        pub fn set_key_hasher(mut self, hasher: KeyHasher) -> Self {
+            self.key_hasher = Some(hasher);
+            self
+        }
+
+        /// Compute the lifespan of each value the cache writes with `ttl_fn` in place of the
+        /// cache's lifespan, e.g. from a publish time stored in the value. Writes given a
+        /// lifespan, like `cache_set_with_lifespan`, keep it. Lifespans under a second are raised
+        /// to one second, as redis rejects them. Lifespan jitter still applies, while refreshes on
+        /// hits reset the TTL to the cache's lifespan.
+        pub fn set_ttl_fn(mut self, ttl_fn: TtlFn<V>) -> Self {
+            self.ttl_fn = Some(ttl_fn);
+            self
+        }
+
+        /// Normalize keys with `normalization` before hashing and prefixing them, so that keys
+        /// like `Foo` and `foo` share an entry in case-insensitive domains. Changing the
+        /// normalization of an existing cache invalidates the entries stored under keys it changes.
+        pub fn set_key_normalizer(mut self, normalization: Normalization) -> Self {
+            self.key_normalization = normalization;
+            self
+        }
+
+        /// Cap the number of keys of the cache at `max_keys`, evicting random keys once sets exceed
+        /// it. Keys set through `cache_set`, `cache_set_with_lifespan`, `cache_set_in`, and
+        /// `cache_set_raw_key` are recorded in a key index set stored under the cache prefix, and
+        /// evicted by a script sent in the same round-trip as the set, along with their entries of
+        /// `set_track_recency`. Eviction is best-effort: keys set by other means aren't indexed,
+        /// expired and removed keys linger in the index until picked, and an eviction may pick the
+        /// key just set, which stops it for that set, so the cap can be exceeded under concurrent
+        /// sets.
+        /// The index also gives an exact key count with `cache_len`, see `cache_reconcile`.
+        pub fn set_max_keys(mut self, max_keys: u64) -> Self {
+            self.max_keys = Some(max_keys);
+            self
+        }
+
+        /// Specify whether sets record their key with the current time in a sorted set stored
+        /// under the cache prefix, so the most recently set keys can be listed with `cache_recent`.
+        /// Like `set_max_keys`, this applies to keys set through `cache_set`,
+        /// `cache_set_with_lifespan`, `cache_set_in`, and `cache_set_raw_key`.
+        pub fn set_track_recency(mut self, track: bool) -> Self {
+            self.track_recency = track;
+            self
+        }
+
+        /// Encode and decode values with `codec` instead of storing them as JSON
+        pub fn set_codec(mut self, codec: Arc<dyn ValueCodec<V>>) -> Self {
+            self.codec = Some(codec);
+            self
+        }
+
+        /// Store the variants of `V` as distinct types, see `TaggedCodec`
+        pub fn set_tagged_codec(self, codec: TaggedCodec<V>) -> Self
+        where
+            V: 'static,
+        {
+            self.set_codec(Arc::new(codec))
+        }
+
+        /// Read values the configured codec fails to decode with `codec` instead, for instance while
+        /// moving keys over to a new codec. `cache_get` rewrites values read this way in the
+        /// configured format, keeping their remaining TTL and recorded lifespan, so keys migrate
+        /// as they're read. Keys changed since the read aren't rewritten, and failing rewrites
+        /// are ignored.
+        pub fn set_fallback_codec(mut self, codec: Arc<dyn ValueCodec<V>>) -> Self {
+            self.fallback_codec = Some(Some(codec));
+            self
+        }
+
+        /// Read values the configured codec fails to decode as JSON instead, like
+        /// `set_fallback_codec` does with a codec. For moving away from JSON with `set_codec`.
+        pub fn set_json_fallback(mut self) -> Self {
+            self.fallback_codec = Some(None);
+            self
+        }
+
+        /// Store values as JSON with `options`, see `JsonOptions`. Ignored when a codec is set.
+        pub fn set_json_options(mut self, options: JsonOptions) -> Self {
+            self.json_options = options;
+            self
+        }
+
+        /// Fail reads of values stored in more than `max` bytes with `RedisCacheError::ValueTooLarge`
+        /// before decompressing or deserializing them, to guard against oversized values written
+        /// by another producer. The limit applies to the stored bytes, after any compression.
+        pub fn set_max_read_bytes(mut self, max: usize) -> Self {
+            self.max_read_bytes = Some(max);
+            self
+        }
+
+        /// Store a CRC32 of each value with it, verified on read to detect values corrupted in
+        /// redis, which fail with `RedisCacheError::ChecksumMismatch`. Values stored without a
+        /// checksum are still read.
+        pub fn set_checksum(mut self, checksum: bool) -> Self {
+            self.checksum = checksum;
+            self
+        }
+
+        /// Compress values with `kind` before storing them. Values are decompressed on read
+        /// whichever enabled format they were written with, see `CompressionKind`.
+        #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+        pub fn set_compression(mut self, kind: CompressionKind) -> Self {
+            self.compression = Some(kind);
+            self
+        }
+
+        /// Only keep compressed values that are at least `min_ratio` times smaller than the
+        /// uncompressed ones, storing the others uncompressed, so incompressible values aren't
+        /// stored larger than they are. Reads detect either form from the value's header. A ratio
+        /// of `1.0` stores uncompressed values whenever compression doesn't make them smaller.
+        #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+        pub fn set_compression_min_ratio(mut self, min_ratio: f32) -> Self {
+            self.compression_min_ratio = Some(min_ratio);
+            self
+        }
+
+        /// Compress values with the zstd `dictionary`, trained on values like the cached ones,
+        /// when zstd compression is set. Values record the id of the dictionary they were compressed
+        /// with, so values compressed without a dictionary are still read, while values compressed
+        /// with another dictionary fail with `RedisCacheError::CompressionError`.
+        #[cfg(feature = "redis_zstd")]
+        pub fn set_compression_dictionary(mut self, dictionary: Vec<u8>) -> Self {
+            self.compression_dictionary = Some(dictionary);
+            self
+        }
+
+        /// Encrypt values with AES-256-GCM using `key` before storing them, after compression if
+        /// any. Each value is stored with its own random nonce. Values that aren't encrypted are
+        /// still read, so existing caches can switch to encryption, while values encrypted with
+        /// another key fail with `RedisCacheError::EncryptionError`.
+        #[cfg(feature = "redis_encryption")]
+        pub fn set_encryption(mut self, key: [u8; 32]) -> Self {
+            self.encryption_key = Some(key);
+            self
+        }
+
+        /// Split batch operations (`cache_get_multi`, `cache_set_multi`, `cache_set_multi_nx`,
+        /// and `cache_ttl_multi`) into pipelines of at most `chunk_size` keys, sent one after
+        /// another, to bound the size of each request and response. By default each batch is
+        /// sent as a single pipeline.
+        pub fn set_batch_chunk_size(mut self, chunk_size: usize) -> Self {
+            self.batch_chunk_size = Some(chunk_size.max(1));
+            self
+        }
+
+        /// Acquire a connection per operation from a bb8 pool of at most `max_size`
+        /// connections, instead of sharing a single multiplexed connection
+        #[cfg(feature = "redis_bb8")]
+        pub fn set_bb8_pool_size(mut self, max_size: u32) -> Self {
+            self.bb8_pool_size = Some(max_size.max(1));
+            self
+        }
+
+        /// Consult `injector` at the start of every get, set, and remove. Returning
+        /// `Some(error)` fails the operation with that error without contacting redis.
+        #[cfg(feature = "chaos")]
+        pub fn set_fault_injector(mut self, injector: FaultInjector) -> Self {
+            self.fault_injector = Some(injector);
+            self
+        }
+
+        /// Return the current connection string or load from the env var: CACHED_REDIS_CONNECTION_STRING
+        pub fn connection_string(&self) -> Result<String, RedisCacheBuildError> {
+            match self.connection_string {
+                Some(ref s) => Ok(s.to_string()),
+                None => std::env::var(ENV_KEY).map_err(|e| {
+                    RedisCacheBuildError::MissingConnectionString {
+                        env_key: ENV_KEY.to_string(),
+                        error: e,
+                    }
+                }),
+            }
+        }
+
+        /// The warnings about this builder's settings, kept by the cache it builds
+        fn config_warnings(&self) -> Vec<ConfigWarning> {
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            let unused = self.compression.is_none() && self.compression_min_ratio.is_some();
+            #[cfg(not(any(feature = "redis_zstd", feature = "redis_gzip")))]
+            let unused = false;
+            #[cfg(feature = "redis_zstd")]
+            let unused = unused
+                || (self.compression != Some(CompressionKind::Zstd)
+                    && self.compression_dictionary.is_some());
+            config_warnings(
+                self.seconds,
+                self.lifespan_jitter,
+                self.refresh,
+                self.refresh_expire_option.is_some(),
+                unused,
+            )
+        }
+
+        async fn create_connection_source(&self) -> Result<ConnectionSource, RedisCacheBuildError> {
+            let s = self.connection_string()?;
+            let observer = self.connection_observer.clone();
+            #[cfg(feature = "redis_bb8")]
+            if let Some(max_size) = self.bb8_pool_size {
+                let manager = bb8_redis::RedisConnectionManager::new(s)?;
+                let mut builder = bb8::Pool::builder().max_size(max_size);
+                if let Some(ref observer) = observer {
+                    builder =
+                        builder.connection_customizer(Box::new(ObserverHandler(observer.clone())));
+                }
+                let pool = builder.build(manager).await?;
+                return Ok(ConnectionSource {
+                    connections: Connections::Bb8(pool),
+                    observer,
+                });
+            }
+            let client = redis::Client::open(s)?;
+            let conn = client.get_multiplexed_async_connection().await?;
+            if let Some(ref observer) = observer {
+                observer(ConnectionEvent::Connected);
+            }
+            Ok(ConnectionSource {
+                connections: Connections::Multiplexed(conn),
+                observer,
+            })
+        }
+
+        pub async fn build(self) -> Result<AsyncRedisCache<K, V>, RedisCacheBuildError> {
+            validate_prefix(&self.prefix)?;
+            if let Some(ref fallback) = self.fallback_prefix {
+                validate_prefix(fallback)?;
+            }
+            if let Some(ref tag) = self.hash_tag {
+                validate_hash_tag(tag)?;
+            }
+            let env_namespace = load_env_namespace(self.env_namespace_from.as_deref())?;
+            let connection_source = self.create_connection_source().await?;
+            let server_version = parse_server_version(&connection_source.server_info().await?);
+            let refresh_expire_option = self
+                .refresh_expire_option
+                .filter(|_| version_supports(server_version, EXPIRE_OPTIONS_VERSION));
+            let config_warnings = self.config_warnings();
+            Ok(AsyncRedisCache {
+                config_warnings,
+                server_version,
+                seconds: self.seconds,
+                refresh: self.refresh,
+                negative_seconds: self.negative_seconds.unwrap_or(self.seconds),
+                connection_string: self.connection_string()?,
+                connection_source,
+                prefix: hash_tagged(
+                    self.hash_tag.as_deref(),
+                    generate_prefix(env_namespace.as_deref(), &self.prefix),
+                ),
+                fallback_prefix: self.fallback_prefix.as_deref().map(|fallback| {
+                    hash_tagged(
+                        self.hash_tag.as_deref(),
+                        generate_prefix(env_namespace.as_deref(), fallback),
+                    )
+                }),
+                name: self.name,
+                env_namespace,
+                hash_tag: self.hash_tag,
+                ignore_set_errors: self.ignore_set_errors,
+                event_sink: self.event_sink,
+                write_wait: self.write_wait,
+                soft_remove: self.soft_remove,
+                lifespan_jitter: self.lifespan_jitter,
+                key_hasher: self.key_hasher,
+                ttl_fn: self.ttl_fn,
+                key_normalization: self.key_normalization,
+                max_keys: self.max_keys,
+                track_recency: self.track_recency,
+                refresh_expire_option,
+                batch_chunk_size: self.batch_chunk_size,
+                negative_seconds_on_error: self.negative_seconds_on_error,
+                serve_stale_on_loader_error: self.serve_stale_on_loader_error,
+                codec: Codec {
+                    custom: self.codec,
+                    fallback: self.fallback_codec,
+                    json: self.json_options,
+                    max_read_bytes: self.max_read_bytes,
+                    checksum: self.checksum,
+                    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                    compression: self.compression,
+                    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                    compression_min_ratio: self.compression_min_ratio,
+                    #[cfg(feature = "redis_zstd")]
+                    dictionary: self
+                        .compression_dictionary
+                        .as_deref()
+                        .map(ZstdDictionary::new),
+                    #[cfg(feature = "redis_encryption")]
+                    cipher: self
+                        .encryption_key
+                        .map(|key| aes_gcm::KeyInit::new(&key.into())),
+                },
+                #[cfg(feature = "chaos")]
+                fault_injector: self.fault_injector,
+                stats: Stats::default(),
+                _phantom_k: self._phantom_k,
+                _phantom_v: self._phantom_v,
+            })
+        }
+    }
+
+    /// Where an `AsyncRedisCache` gets its connections from
+    struct ConnectionSource {
+        connections: Connections,
+        observer: Option<ConnectionObserver>,
+    }
+
+    enum Connections {
+        Multiplexed(redis::aio::MultiplexedConnection),
+        #[cfg(feature = "redis_bb8")]
+        Bb8(bb8::Pool<bb8_redis::RedisConnectionManager>),
+    }
+
+    impl ConnectionSource {
+        /// Acquire a connection for a single operation, along with whether it had to wait
+        /// for a pooled connection because none was idle
+        async fn get(&self) -> (Result<AsyncConnection, RedisCacheError>, bool) {
+            let (conn, waited) = match self.connections {
+                Connections::Multiplexed(ref conn) => {
+                    (Connection::Multiplexed(conn.clone()), false)
+                }
+                #[cfg(feature = "redis_bb8")]
+                Connections::Bb8(ref pool) => {
+                    let waited = pool.state().idle_connections == 0;
+                    match pool.get_owned().await {
+                        Ok(conn) => (Connection::Pooled(conn), waited),
+                        Err(e) => return (Err(e.into()), waited),
+                    }
+                }
+            };
+            let conn = AsyncConnection {
+                conn,
+                observer: self.observer.clone(),
+            };
+            (Ok(conn), waited)
+        }
+
+        async fn server_info(&self) -> Result<String, RedisCacheBuildError> {
+            let mut cmd = redis::cmd("INFO");
+            cmd.arg("server");
+            match self.connections {
+                Connections::Multiplexed(ref conn) => {
+                    Ok(cmd.query_async(&mut conn.clone()).await?)
+                }
+                #[cfg(feature = "redis_bb8")]
+                Connections::Bb8(ref pool) => Ok(cmd.query_async(&mut *pool.get().await?).await?),
+            }
+        }
+    }
+
+    /// A connection acquired from a `ConnectionSource`, reporting dropped
+    /// connections to the observer
+    struct AsyncConnection {
+        conn: Connection,
+        observer: Option<ConnectionObserver>,
+    }
+
+    enum Connection {
+        Multiplexed(redis::aio::MultiplexedConnection),
+        #[cfg(feature = "redis_bb8")]
+        Pooled(bb8::PooledConnection<'static, bb8_redis::RedisConnectionManager>),
+    }
+
+    /// Report `Disconnected` to `observer` if `fut` fails because its connection dropped
+    fn observe_dropped<'a, T: Send + 'a>(
+        observer: &Option<ConnectionObserver>,
+        fut: redis::RedisFuture<'a, T>,
+    ) -> redis::RedisFuture<'a, T> {
+        match observer {
+            None => fut,
+            Some(observer) => {
+                let observer = observer.clone();
+                Box::pin(async move {
+                    let res = fut.await;
+                    if matches!(res, Err(ref e) if e.is_connection_dropped()) {
+                        observer(ConnectionEvent::Disconnected);
+                    }
+                    res
+                })
+            }
+        }
+    }
+
+    impl redis::aio::ConnectionLike for AsyncConnection {
+        fn req_packed_command<'a>(
+            &'a mut self,
+            cmd: &'a redis::Cmd,
+        ) -> redis::RedisFuture<'a, redis::Value> {
+            let fut = match self.conn {
+                Connection::Multiplexed(ref mut conn) => conn.req_packed_command(cmd),
+                #[cfg(feature = "redis_bb8")]
+                Connection::Pooled(ref mut conn) => conn.req_packed_command(cmd),
+            };
+            observe_dropped(&self.observer, fut)
+        }
+
+        fn req_packed_commands<'a>(
+            &'a mut self,
+            cmd: &'a redis::Pipeline,
+            offset: usize,
+            count: usize,
+        ) -> redis::RedisFuture<'a, Vec<redis::Value>> {
+            let fut = match self.conn {
+                Connection::Multiplexed(ref mut conn) => {
+                    conn.req_packed_commands(cmd, offset, count)
+                }
+                #[cfg(feature = "redis_bb8")]
+                Connection::Pooled(ref mut conn) => conn.req_packed_commands(cmd, offset, count),
+            };
+            observe_dropped(&self.observer, fut)
+        }
+
+        fn get_db(&self) -> i64 {
+            match self.conn {
+                Connection::Multiplexed(ref conn) => conn.get_db(),
+                #[cfg(feature = "redis_bb8")]
+                Connection::Pooled(ref conn) => conn.get_db(),
+            }
+        }
+    }
+
+    /// Cache store backed by redis
+    ///
+    /// Values have a ttl applied and enforced by redis. Like with `RedisCache`, a present key
+    /// always reads as `Ok(Some(value))`, even for empty values like `None` when `V` is an `Option`.
+    pub struct AsyncRedisCache<K, V> {
+        pub(super) seconds: u64,
+        pub(super) refresh: bool,
+        negative_seconds: u64,
+        pub(super) prefix: String,
+        fallback_prefix: Option<String>,
+        name: Option<String>,
+        env_namespace: Option<String>,
+        hash_tag: Option<String>,
+        ignore_set_errors: bool,
+        event_sink: Option<SyncSender<CacheEvent>>,
+        write_wait: Option<(u32, Duration)>,
+        soft_remove: Option<u64>,
+        lifespan_jitter: u64,
+        key_hasher: Option<KeyHasher>,
+        ttl_fn: Option<TtlFn<V>>,
+        key_normalization: Normalization,
+        max_keys: Option<u64>,
+        track_recency: bool,
+        refresh_expire_option: Option<ExpireOption>,
+        batch_chunk_size: Option<usize>,
+        negative_seconds_on_error: Option<u64>,
+        serve_stale_on_loader_error: bool,
+        codec: Codec<V>,
+        #[cfg(feature = "chaos")]
+        fault_injector: Option<FaultInjector>,
+        connection_string: String,
+        connection_source: ConnectionSource,
+        /// The server's version, detected when the cache was built
+        server_version: Option<ServerVersion>,
+        stats: Stats,
+        config_warnings: Vec<ConfigWarning>,
+        _phantom_k: PhantomData<K>,
+        _phantom_v: PhantomData<V>,
+    }
+
+    impl<K, V> AsyncRedisCache<K, V>
+    where
+        K: Display + Send + Sync,
+        V: Serialize + DeserializeOwned + Send + Sync,
+    {
+        #[allow(clippy::new_ret_no_self)]
+        /// Initialize an `AsyncRedisCacheBuilder`
+        pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> AsyncRedisCacheBuilder<K, V> {
+            AsyncRedisCacheBuilder::new(prefix, seconds)
+        }
+
+        /// Acquire a connection, recording the acquisition in the stats
+        async fn connection(&self) -> Result<AsyncConnection, RedisCacheError> {
+            let started = Instant::now();
+            let (res, waited) = self.connection_source.get().await;
+            self.stats.record_acquire(started, waited);
+            res
+        }
+
+        /// The lifespan of `val` when set without an explicit one, see `set_ttl_fn`
+        fn lifespan_of(&self, val: &V) -> u64 {
+            match self.ttl_fn {
+                Some(ref ttl_fn) => ttl_fn(val).max(1),
+                None => self.seconds,
+            }
+        }
+
+        fn generate_key(&self, key: &K) -> String {
+            format!(
+                "{}{}",
+                self.prefix,
+                format_key(self.key_normalization, &self.key_hasher, key)
+            )
+        }
+
+        fn generate_key_in(&self, sub_prefix: &str, key: &K) -> String {
+            format!(
+                "{}{}",
+                hash_tagged(
+                    self.hash_tag.as_deref(),
+                    generate_prefix(self.env_namespace.as_deref(), sub_prefix),
+                ),
+                format_key(self.key_normalization, &self.key_hasher, key)
+            )
+        }
+
+        /// The key under the fallback prefix for `key`, if a fallback prefix is set and `key` is
+        /// under the cache's own prefix
+        fn fallback_key(&self, key: &str) -> Option<String> {
+            let fallback = self.fallback_prefix.as_ref()?;
+            let key = key.strip_prefix(self.prefix.as_str())?;
+            Some(format!("{}{}", fallback, key))
+        }
+
+        fn observe<T, E>(
+            &self,
+            op: CacheOp,
+            key: &str,
+            started: Instant,
+            res: &Result<Option<T>, E>,
+        ) {
+            observe(&self.stats, &self.event_sink, op, key, started, res)
+        }
+
+        fn observe_outcome(
+            &self,
+            key: &str,
+            started: Instant,
+            outcome: &Result<GetOutcome<V>, RedisCacheError>,
+        ) {
+            let res = match outcome {
+                Ok(o) => o.as_result(),
+                Err(_) => Err(()),
+            };
+            self.observe(CacheOp::Get, key, started, &res)
+        }
+
+        /// Wrap the error of `res` with the cache's name, if it has one
+        fn named<T>(&self, res: Result<T, RedisCacheError>) -> Result<T, RedisCacheError> {
+            match self.name {
+                None => res,
+                Some(ref name) => res.map_err(|error| RedisCacheError::Named {
+                    name: name.clone(),
+                    source: Box::new(error),
+                }),
+            }
+        }
+
+        /// The name set with `set_name`
+        pub fn name(&self) -> Option<&str> {
+            self.name.as_deref()
+        }
+
+        fn inject_fault(&self, _op: CacheOp, _key: &str) -> Result<(), RedisCacheError> {
+            #[cfg(feature = "chaos")]
+            if let Some(err) = self.fault_injector.as_ref().and_then(|f| f(_op, _key)) {
+                return Err(err);
+            }
+            Ok(())
+        }
+
+        /// Return the redis connection string used
+        pub fn connection_string(&self) -> String {
+            self.connection_string.clone()
+        }
+
+        /// Estimate the number of keys under this cache's prefix by sampling `RANDOMKEY` and
+        /// scaling by `DBSIZE`, in two round-trips instead of walking the keyspace. Estimates are
+        /// coarse, typically within ten percent of the database size, so they suit caches holding a
+        /// sizeable share of their database and dashboards rather than exact accounting.
+        pub async fn cache_approx_len(&self) -> Result<u64, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let total: u64 = redis::cmd("DBSIZE").query_async(&mut conn).await?;
+            if total == 0 {
+                return Ok(0);
+            }
+            let sampled: Vec<Option<Vec<u8>>> = random_keys_pipe().query_async(&mut conn).await?;
+            Ok(estimate_len(total, &sampled, &self.prefix))
+        }
+
+        /// Return the likely misconfigurations noticed by `build`, see `ConfigWarning`
+        pub fn config_warnings(&self) -> &[ConfigWarning] {
+            &self.config_warnings
+        }
+
+        /// Return the current operation counters
+        pub fn cache_stats(&self) -> CacheStats {
+            self.stats.snapshot()
+        }
+
+        /// Return the current operation counters and reset them to zero
+        pub fn cache_take_stats(&self) -> CacheStats {
+            self.stats.take()
+        }
+
+        /// Reset the operation counters to zero
+        pub fn reset_stats(&self) {
+            self.stats.take();
+        }
+
+        /// Return a serializable snapshot of the operation counters along with the estimated
+        /// number of keys, see `RedisCache::cache_stats_snapshot`
+        pub async fn cache_stats_snapshot(&self) -> StatsSnapshot {
+            let approx_len = self.cache_approx_len().await.ok();
+            self.stats.serializable(approx_len)
+        }
+
+        /// Retrieve a cached value, distinguishing a miss from a value that failed to deserialize.
+        /// Errors talking to redis are still returned as `Err`.
+        pub async fn cache_get_detailed(&self, key: &K) -> Result<GetOutcome<V>, RedisCacheError> {
+            let key = self.generate_key(key);
+            let started = Instant::now();
+            let outcome = self
+                .get_raw(&key)
+                .await
+                .map(|raw| self.codec.outcome(&key, raw));
+            self.observe_outcome(&key, started, &outcome);
+            self.named(outcome)
+        }
+
+        /// Retrieve a cached value, or `default` if there is none or it can't be deserialized.
+        /// Values that can't be deserialized are deleted, so the next `cache_set` replaces them
+        /// instead of every read falling back to `default` until they expire. Errors reaching
+        /// redis are still returned. Stats behave as they do for `cache_get_detailed`.
+        pub async fn cache_get_or_default(
+            &self,
+            key: &K,
+            default: V,
+        ) -> Result<V, RedisCacheError> {
+            match self.cache_get_detailed(key).await? {
+                GetOutcome::Hit(v) => Ok(v),
+                GetOutcome::Corrupt(_) => {
+                    let mut conn = self.connection().await?;
+                    redis::cmd("DEL")
+                        .arg(self.generate_key(key))
+                        .query_async::<_, ()>(&mut conn)
+                        .await?;
+                    Ok(default)
+                }
+                GetOutcome::Miss | GetOutcome::Negative | GetOutcome::Removed(_) => Ok(default),
+            }
+        }
+
+        /// Retrieve a cached value stored under `sub_prefix` instead of this cache's prefix.
+        /// Like the cache's own prefix, `sub_prefix` must not contain `:`, otherwise
+        /// `RedisCacheError::InvalidPrefix` is returned.
+        /// Lifespan, refresh, and stats behave as they do for `cache_get`.
+        pub async fn cache_get_in(
+            &self,
+            sub_prefix: &str,
+            key: &K,
+        ) -> Result<Option<V>, RedisCacheError> {
+            check_prefix(sub_prefix)?;
+            let key = self.generate_key_in(sub_prefix, key);
+            let started = Instant::now();
+            let res = self.get_value(&key).await;
+            self.observe(CacheOp::Get, &key, started, &res);
+            self.named(res)
+        }
+
+        /// Insert a value under `sub_prefix` instead of this cache's prefix and return the previous value.
+        /// Lifespan, stats, and error handling behave as they do for `cache_set`, but an invalid
+        /// `sub_prefix`, see `cache_get_in`, is always an error.
+        pub async fn cache_set_in(
+            &self,
+            sub_prefix: &str,
+            key: K,
+            val: V,
+        ) -> Result<Option<V>, RedisCacheError> {
+            check_prefix(sub_prefix)?;
+            let key = self.generate_key_in(sub_prefix, &key);
+            let started = Instant::now();
+            let seconds = self.lifespan_of(&val);
+            let res = self.set_value(&key, val, seconds).await;
+            self.observe(CacheOp::Set, &key, started, &res);
+            let res = self.named(res);
+            match res {
+                Err(_) if self.ignore_set_errors => Ok(None),
+                res => res,
+            }
+        }
+
+        /// Retrieve the value at `full_key` exactly as given, for keys named by another system.
+        /// This bypasses all prefix logic: neither the cache's prefix, its environment namespace,
+        /// nor the key hasher apply, and the fallback prefix is only consulted for keys that
+        /// happen to start with the cache's prefix. Values must still be in the cache's format.
+        /// Lifespan, refresh, and stats behave as they do for `cache_get`.
+        pub async fn cache_get_raw_key(
+            &self,
+            full_key: &str,
+        ) -> Result<Option<V>, RedisCacheError> {
+            let started = Instant::now();
+            let res = self.get_value(full_key).await;
+            self.observe(CacheOp::Get, full_key, started, &res);
+            self.named(res)
+        }
+
+        /// Retrieve the encoded bytes of the value at `key` without deserializing it, see
+        /// `RedisCache::cache_get_bytes`
+        pub async fn cache_get_bytes(&self, key: &K) -> Result<Option<Vec<u8>>, RedisCacheError> {
+            let key = self.generate_key(key);
+            let started = Instant::now();
+            let res = match self.get_raw(&key).await {
+                Ok(Some(raw)) => self.codec.value_bytes(&key, raw),
+                res => res,
+            };
+            self.observe(CacheOp::Get, &key, started, &res);
+            self.named(res)
+        }
+
+        /// Insert a value at `full_key` exactly as given and return the previous value, bypassing
+        /// all prefix logic like `cache_get_raw_key`. Lifespan, stats, and error handling behave
+        /// as they do for `cache_set`.
+        pub async fn cache_set_raw_key(
+            &self,
+            full_key: &str,
+            val: V,
+        ) -> Result<Option<V>, RedisCacheError> {
+            let started = Instant::now();
+            let seconds = self.lifespan_of(&val);
+            let res = self.set_value(full_key, val, seconds).await;
+            self.observe(CacheOp::Set, full_key, started, &res);
+            let res = self.named(res);
+            match res {
+                Err(_) if self.ignore_set_errors => Ok(None),
+                res => res,
+            }
+        }
+
+        /// Cache `key` as known to have no value, for the negative lifespan (see
+        /// `AsyncRedisCacheBuilder::set_negative_lifespan`). `cache_get` treats the key as a miss,
+        /// while `cache_get_detailed` reports `GetOutcome::Negative`. Use
+        /// `cache_set_miss_with_lifespan` to give a single entry its own TTL.
+        pub async fn cache_set_miss(&self, key: K) -> Result<(), RedisCacheError> {
+            self.cache_set_miss_with_lifespan(key, self.negative_seconds)
+                .await
+        }
+
+        /// Cache `key` as known to have no value for `seconds`, see `cache_set_miss`
+        pub async fn cache_set_miss_with_lifespan(
+            &self,
+            key: K,
+            seconds: u64,
+        ) -> Result<(), RedisCacheError> {
+            let key = self.generate_key(&key);
+            let started = Instant::now();
+            let res = self.set_negative(&key, seconds).await;
+            self.observe(CacheOp::Set, &key, started, &res);
+            let res = self.named(res);
+            match res {
+                Err(_) if self.ignore_set_errors => Ok(()),
+                res => res.map(|_| ()),
+            }
+        }
+
+        async fn get_value(&self, key: &str) -> Result<Option<V>, RedisCacheError> {
+            let raw = match self.get_raw(key).await? {
+                None => return Ok(None),
+                Some(raw) => raw,
+            };
+            match self.codec.entry_any(key, &raw)? {
+                None => Ok(None),
+                Some((value, migrate)) => {
+                    if migrate {
+                        // the value was read fine, so a failing rewrite is left for the next read
+                        let _ = self.rewrite_value(key, &raw, &value).await;
+                    }
+                    Ok(Some(value))
+                }
+            }
+        }
+
+        /// Write `value`, read from the entry `raw`, back to `key` in the configured format if
+        /// `key` still holds `raw`, keeping its TTL and recorded lifespan
+        async fn rewrite_value(
+            &self,
+            key: &str,
+            raw: &[u8],
+            value: &V,
+        ) -> Result<(), RedisCacheError> {
+            let keep_ttl = version_supports(self.server_version, KEEPTTL_VERSION);
+            let lifespan = self.codec.meta(key, raw)?.lifespan;
+            let val = self.codec.serialize(value, lifespan)?;
+            let mut conn = self.connection().await?;
+            rewrite_cmd(key, raw, val, keep_ttl)
+                .query_async::<_, bool>(&mut conn)
+                .await?;
+            Ok(())
+        }
+
+        async fn get_raw(&self, key: &str) -> Result<Option<Vec<u8>>, RedisCacheError> {
+            self.inject_fault(CacheOp::Get, key)?;
+            let mut conn = self.connection().await?;
+            let get = get_cmd(key, self.fallback_key(key));
+            if !self.refresh {
+                return Ok(get.query_async(&mut conn).await?);
+            }
+            let mut pipe = redis::pipe();
+            pipe.add_command(get)
+                .add_command(refresh_cmd(key, self.seconds, self.refresh_expire_option))
+                .ignore();
+            let res: (Option<Vec<u8>>,) = pipe.query_async(&mut conn).await?;
+            Ok(res.0)
+        }
+
+        async fn set_value(
+            &self,
+            key: &str,
+            val: V,
+            seconds: u64,
+        ) -> Result<Option<V>, RedisCacheError> {
+            self.inject_fault(CacheOp::Set, key)?;
+            let mut conn = self.connection().await?;
+            let mut pipe = redis::pipe();
+
+            pipe.get(key);
+            pipe.set_ex::<&str, Vec<u8>>(
+                key,
+                self.codec.serialize(&val, Some(seconds))?,
+                jittered(seconds, self.lifespan_jitter) as usize,
+            )
+            .ignore();
+            self.add_set_hooks(&mut pipe, key);
+
+            let prev = match self.write_wait {
+                None => {
+                    let res: (Option<Vec<u8>>,) = pipe.query_async(&mut conn).await?;
+                    res.0
+                }
+                Some((replicas, timeout)) => {
+                    pipe.cmd("WAIT")
+                        .arg(replicas)
+                        .arg(timeout.as_millis() as u64);
+                    let res: (Option<Vec<u8>>, u32) = pipe.query_async(&mut conn).await?;
+                    check_write_wait(replicas, res.1)?;
+                    res.0
+                }
+            };
+            match prev {
+                None => Ok(None),
+                Some(s) => self.codec.entry(key, s),
+            }
+        }
+
+        async fn set_negative(
+            &self,
+            key: &str,
+            seconds: u64,
+        ) -> Result<Option<()>, RedisCacheError> {
+            self.inject_fault(CacheOp::Set, key)?;
+            let mut conn = self.connection().await?;
+            redis::cmd("SETEX")
+                .arg(key)
+                .arg(seconds)
+                .arg(NEGATIVE_ENTRY)
+                .query_async::<_, ()>(&mut conn)
+                .await?;
+            Ok(None)
+        }
+
+        async fn remove_value(&self, key: &str) -> Result<Option<V>, RedisCacheError> {
+            self.inject_fault(CacheOp::Remove, key)?;
+            let mut conn = self.connection().await?;
+            let mut pipe = redis::pipe();
+
+            match self.soft_remove {
+                None => {
+                    pipe.get(key);
+                    pipe.del::<&str>(key).ignore();
+                }
+                Some(grace_seconds) => {
+                    pipe.add_command(soft_remove_cmd(key, grace_seconds));
+                }
+            }
+            let res: (Option<Vec<u8>>,) = pipe.query_async(&mut conn).await?;
+            match res.0 {
+                None => Ok(None),
+                Some(s) => self.codec.entry(key, s),
+            }
+        }
+
+        /// Return whether a value is currently cached for `key`. Negative and soft-removed
+        /// entries don't count, as `cache_get` misses on them.
+        pub async fn cache_exists(&self, key: &K) -> Result<bool, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let ttl: i64 = live_ttl_cmd(&self.generate_key(key))
+                .query_async(&mut conn)
+                .await?;
+            Ok(ttl != -2)
+        }
+
+        /// Insert a value like `cache_set` and record it under each of `tags`, so it can be removed
+        /// along with every other key of a tag by `cache_invalidate_tag`. Tag sets are stored under
+        /// the cache prefix as `tag:<tag>` and are kept as long as their most recent value.
+        pub async fn cache_set_tagged(
+            &self,
+            key: K,
+            val: V,
+            tags: &[String],
+        ) -> Result<Option<V>, RedisCacheError> {
+            let key = self.generate_key(&key);
+            let started = Instant::now();
+            let res = self.set_tagged_value(&key, val, tags).await;
+            self.observe(CacheOp::Set, &key, started, &res);
+            let res = self.named(res);
+            match res {
+                Err(_) if self.ignore_set_errors => Ok(None),
+                res => res,
+            }
+        }
+
+        async fn set_tagged_value(
+            &self,
+            key: &str,
+            val: V,
+            tags: &[String],
+        ) -> Result<Option<V>, RedisCacheError> {
+            self.inject_fault(CacheOp::Set, key)?;
+            let mut conn = self.connection().await?;
+            let tag_keys: Vec<String> = tags.iter().map(|tag| self.tag_key(tag)).collect();
+            let seconds = self.lifespan_of(&val);
+            let res: (Option<Vec<u8>>,) = set_tagged_pipe(
+                key,
+                self.codec.serialize(&val, Some(seconds))?,
+                jittered(seconds, self.lifespan_jitter),
+                &tag_keys,
+                seconds + self.lifespan_jitter,
+            )
+            .query_async(&mut conn)
+            .await?;
+            match res.0 {
+                None => Ok(None),
+                Some(s) => self.codec.entry(key, s),
+            }
+        }
+
+        /// Delete every value recorded under `tag` by `cache_set_tagged`, and the tag itself,
+        /// returning the number of values deleted. Values are deleted even if soft removal is
+        /// enabled. Values that already expired are cleaned up from the tag without being counted.
+        pub async fn cache_invalidate_tag(&self, tag: &str) -> Result<u64, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let deleted: u64 = invalidate_tag_cmd(&self.tag_key(tag))
+                .query_async(&mut conn)
+                .await?;
+            Ok(deleted)
+        }
+
+        fn tag_key(&self, tag: &str) -> String {
+            format!("{}{}{}", self.prefix, TAG_KEY_PREFIX, tag)
+        }
+
+        /// Wait for the replicas set with `set_write_wait`, if any, to acknowledge the writes so
+        /// far
+        async fn wait_for_replicas(
+            &self,
+            conn: &mut AsyncConnection,
+        ) -> Result<(), RedisCacheError> {
+            if let Some((replicas, timeout)) = self.write_wait {
+                let acked: u32 = redis::cmd("WAIT")
+                    .arg(replicas)
+                    .arg(timeout.as_millis() as u64)
+                    .query_async(conn)
+                    .await?;
+                check_write_wait(replicas, acked)?;
+            }
+            Ok(())
+        }
+
+        /// Add the key index and recency commands of a set of `key` to `pipe`
+        fn add_set_hooks(&self, pipe: &mut redis::Pipeline, key: &str) {
+            if let Some(max_keys) = self.max_keys {
+                let recent_key = self.track_recency.then(|| self.recent_key());
+                pipe.add_command(evict_cmd(&self.key_index_key(), recent_key, key, max_keys))
+                    .ignore();
+            }
+            if self.track_recency {
+                pipe.zadd(self.recent_key(), key, unix_millis()).ignore();
+            }
+        }
+
+        fn key_index_key(&self) -> String {
+            format!("{}{}", self.prefix, KEY_INDEX_KEY)
+        }
+
+        fn recent_key(&self) -> String {
+            format!("{}{}", self.prefix, RECENT_KEY)
+        }
+
+        /// Return up to `n` of the most recently set keys that are still cached, see
+        /// `RedisCache::cache_recent`
+        pub async fn cache_recent(&self, n: usize) -> Result<Vec<K>, RedisCacheError>
+        where
+            K: std::str::FromStr,
+        {
+            let recent_key = self.recent_key();
+            let mut conn = self.connection().await?;
+            let mut recent = Vec::new();
+            let mut start = 0;
+            while recent.len() < n {
+                let wanted = n - recent.len();
+                let keys: Vec<String> = redis::cmd("ZREVRANGE")
+                    .arg(&recent_key)
+                    .arg(start)
+                    .arg(start + wanted - 1)
+                    .query_async(&mut conn)
+                    .await?;
+                if keys.is_empty() {
+                    break;
+                }
+                let mut pipe = redis::pipe();
+                for key in &keys {
+                    pipe.exists(key);
+                }
+                let exists: Vec<bool> = pipe.query_async(&mut conn).await?;
+                let (live, stale) = partition_live(keys, exists);
+                if !stale.is_empty() {
+                    redis::cmd("ZREM")
+                        .arg(&recent_key)
+                        .arg(&stale)
+                        .query_async::<_, ()>(&mut conn)
+                        .await?;
+                }
+                start += live.len();
+                let exhausted = live.len() + stale.len() < wanted;
+                recent.extend(parse_keys(&self.prefix, live));
+                if exhausted {
+                    break;
+                }
+            }
+            Ok(recent)
+        }
+
+        /// Count the keys in the key index, see `RedisCache::cache_len`
+        pub async fn cache_len(&self) -> Result<u64, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let len: u64 = redis::cmd("SCARD")
+                .arg(self.key_index_key())
+                .query_async(&mut conn)
+                .await?;
+            Ok(len)
+        }
+
+        /// Remove the keys that are no longer cached from the key index and return how many
+        /// were removed, see `RedisCache::cache_reconcile`
+        pub async fn cache_reconcile(&self) -> Result<u64, RedisCacheError> {
+            let index_key = self.key_index_key();
+            let mut conn = self.connection().await?;
+            let mut cursor = 0u64;
+            let mut pruned = 0;
+            loop {
+                let (next, keys): (u64, Vec<String>) = redis::cmd("SSCAN")
+                    .arg(&index_key)
+                    .arg(cursor)
+                    .arg("COUNT")
+                    .arg(RECONCILE_BATCH_SIZE)
+                    .query_async(&mut conn)
+                    .await?;
+                if !keys.is_empty() {
+                    let mut pipe = redis::pipe();
+                    for key in &keys {
+                        pipe.exists(key);
+                    }
+                    let exists: Vec<bool> = pipe.query_async(&mut conn).await?;
+                    let (_, dead) = partition_live(keys, exists);
+                    if !dead.is_empty() {
+                        let removed: u64 = redis::cmd("SREM")
+                            .arg(&index_key)
+                            .arg(&dead)
+                            .query_async(&mut conn)
+                            .await?;
+                        pruned += removed;
+                    }
+                }
+                if next == 0 {
+                    return Ok(pruned);
+                }
+                cursor = next;
+            }
+        }
+
+        /// Move the value cached for `from` to `to`, keeping its remaining time to live, using
+        /// `RENAMENX`. Returns `false` without changing anything if `from` is missing or a value
+        /// is already cached for `to`.
+        pub async fn cache_rename(&self, from: &K, to: &K) -> Result<bool, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let renamed: bool = rename_cmd(&self.generate_key(from), &self.generate_key(to))
+                .query_async(&mut conn)
+                .await?;
+            Ok(renamed)
+        }
+
+        /// Atomically add `by` to the counter stored at `key` and return its new value, clamped to
+        /// `min..=max`. Missing counters start from zero and expire after the cache's lifespan,
+        /// existing ones keep their TTL. Counters are stored as plain integers rather than cached
+        /// values, so they should not be read with `cache_get`.
+        pub async fn cache_incr_bounded(
+            &self,
+            key: &K,
+            by: i64,
+            min: i64,
+            max: i64,
+        ) -> Result<i64, RedisCacheError> {
+            let keep_ttl = version_supports(self.server_version, KEEPTTL_VERSION);
+            let mut conn = self.connection().await?;
+            let n: i64 = incr_bounded_cmd(
+                &self.generate_key(key),
+                by,
+                min,
+                max,
+                self.seconds,
+                keep_ttl,
+            )
+            .query_async(&mut conn)
+            .await?;
+            Ok(n)
+        }
+
+        /// Return the remaining time to live of a cached value in seconds.
+        /// Returns `None` if the key does not exist or has no expiry, and for negative and
+        /// soft-removed entries, like `cache_exists`.
+        pub async fn cache_ttl(&self, key: &K) -> Result<Option<u64>, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let ttl: i64 = live_ttl_cmd(&self.generate_key(key))
+                .query_async(&mut conn)
+                .await?;
+            Ok(ttl_from_reply(ttl))
+        }
+
+        /// Return the approximate number of bytes `key` occupies on the server, including
+        /// redis' own overhead, using `MEMORY USAGE`. Returns `None` for missing keys.
+        pub async fn cache_memory_usage(&self, key: &K) -> Result<Option<u64>, RedisCacheError> {
+            require_version(self.server_version, "MEMORY USAGE", MEMORY_USAGE_VERSION)?;
+            let mut conn = self.connection().await?;
+            let usage: Option<u64> = redis::cmd("MEMORY")
+                .arg("USAGE")
+                .arg(self.generate_key(key))
+                .query_async(&mut conn)
+                .await?;
+            Ok(usage)
+        }
+
+        /// Retrieve a cached value and, only if it exists, reset its time to live to `seconds`,
+        /// atomically. Unlike `cache_get` on a refreshing cache, the TTL can't be set on a key
+        /// that expired between the read and the refresh. Stats behave as they do for `cache_get`.
+        pub async fn cache_get_and_refresh(
+            &self,
+            key: &K,
+            seconds: u64,
+        ) -> Result<Option<V>, RedisCacheError> {
+            let key = self.generate_key(key);
+            let started = Instant::now();
+            let res = self.get_and_refresh_value(&key, seconds).await;
+            self.observe(CacheOp::Get, &key, started, &res);
+            self.named(res)
+        }
+
+        async fn get_and_refresh_value(
+            &self,
+            key: &str,
+            seconds: u64,
+        ) -> Result<Option<V>, RedisCacheError> {
+            self.inject_fault(CacheOp::Get, key)?;
+            let mut conn = self.connection().await?;
+            let raw: Option<Vec<u8>> = get_and_refresh_cmd(key, seconds)
+                .query_async(&mut conn)
+                .await?;
+            match raw {
+                None => Ok(None),
+                Some(raw) => self.codec.entry(key, raw),
+            }
+        }
+
+        /// Insert a value that expires after `seconds` instead of the cache's lifespan
+        /// and return the previous value. Stats and error handling behave as they do for `cache_set`.
+        pub async fn cache_set_with_lifespan(
+            &self,
+            key: K,
+            val: V,
+            seconds: u64,
+        ) -> Result<Option<V>, RedisCacheError> {
+            let key = self.generate_key(&key);
+            let started = Instant::now();
+            let res = self.set_value(&key, val, seconds).await;
+            self.observe(CacheOp::Set, &key, started, &res);
+            let res = self.named(res);
+            match res {
+                Err(_) if self.ignore_set_errors => Ok(None),
+                res => res,
+            }
+        }
+
+        /// Replace the value at `key` without changing its remaining TTL and return the previous value.
+        /// `SET ... KEEPTTL` alone would leave a brand-new key without any expiry, so keys that don't
+        /// exist yet expire after the cache's lifespan instead, as they would with `cache_set`.
+        /// Stats and error handling behave as they do for `cache_set`.
+        pub async fn cache_set_keep_ttl(
+            &self,
+            key: K,
+            val: V,
+        ) -> Result<Option<V>, RedisCacheError> {
+            let key = self.generate_key(&key);
+            let started = Instant::now();
+            let res = self.set_keep_ttl_value(&key, val).await;
+            self.observe(CacheOp::Set, &key, started, &res);
+            let res = self.named(res);
+            match res {
+                Err(_) if self.ignore_set_errors => Ok(None),
+                res => res,
+            }
+        }
+
+        async fn set_keep_ttl_value(
+            &self,
+            key: &str,
+            val: V,
+        ) -> Result<Option<V>, RedisCacheError> {
+            self.inject_fault(CacheOp::Set, key)?;
+            let mut conn = self.connection().await?;
+            let seconds = self.lifespan_of(&val);
+            let keep_ttl = version_supports(self.server_version, KEEPTTL_VERSION);
+            let prev: Option<Vec<u8>> = set_keep_ttl_cmd(
+                key,
+                self.codec.serialize(&val, Some(seconds))?,
+                jittered(seconds, self.lifespan_jitter),
+                keep_ttl,
+            )
+            .query_async(&mut conn)
+            .await?;
+            match prev {
+                None => Ok(None),
+                Some(s) => self.codec.entry(key, s),
+            }
+        }
+
+        /// Check that `val` survives a round-trip through the cache's serialization, including
+        /// its codec and compression, without contacting redis
+        pub fn validate_roundtrip(&self, val: &V) -> Result<(), RedisCacheError> {
+            self.codec.roundtrip(val).map(|_| ())
+        }
+
+        /// Like `validate_roundtrip`, also returning whether the round-tripped value equals `val`
+        pub fn validate_roundtrip_eq(&self, val: &V) -> Result<bool, RedisCacheError>
+        where
+            V: PartialEq,
+        {
+            Ok(self.codec.roundtrip(val)? == *val)
+        }
+
+        /// Retrieve a cached value, or compute it with `f` and cache it for `seconds` on a miss.
+        /// The lifespan jitter, if set, is added to `seconds`, and `seconds` itself is recorded as
+        /// the value's original lifespan. Keys cached as having no value by `cache_set_miss`
+        /// return `RedisCacheError::NegativeCached` without calling `f`.
+        pub async fn cache_get_or_set_with_ttl<F, Fut>(
+            &self,
+            key: K,
+            seconds: u64,
+            f: F,
+        ) -> Result<V, RedisCacheError>
+        where
+            V: Clone,
+            F: FnOnce() -> Fut,
+            Fut: Future<Output = V>,
+        {
+            match self.cache_get_detailed(&key).await? {
+                GetOutcome::Hit(v) => return Ok(v),
+                GetOutcome::Negative => return Err(RedisCacheError::NegativeCached),
+                GetOutcome::Corrupt(e) => return Err(e),
+                GetOutcome::Miss | GetOutcome::Removed(_) => {}
+            }
+            let v = f().await;
+            self.cache_set_with_lifespan(key, v.clone(), seconds)
+                .await?;
+            Ok(v)
+        }
+
+        /// Retrieve a cached value, or await `fut` and cache its output on a miss. `fut` isn't
+        /// polled on a hit, so it can be a `futures::future::Shared` handed out by existing
+        /// request coalescing, awaited here once per miss alongside its other holders. Keys cached
+        /// as having no value return `RedisCacheError::NegativeCached`, see
+        /// `cache_get_or_set_with_ttl`.
+        pub async fn cache_get_or_set_with_future<Fut>(
+            &self,
+            key: K,
+            fut: Fut,
+        ) -> Result<V, RedisCacheError>
+        where
+            V: Clone,
+            Fut: Future<Output = V>,
+        {
+            match self.cache_get_detailed(&key).await? {
+                GetOutcome::Hit(v) => return Ok(v),
+                GetOutcome::Negative => return Err(RedisCacheError::NegativeCached),
+                GetOutcome::Corrupt(e) => return Err(e),
+                GetOutcome::Miss | GetOutcome::Removed(_) => {}
+            }
+            let v = fut.await;
+            IOCachedAsync::cache_set(self, key, v.clone()).await?;
+            Ok(v)
+        }
+
+        /// Send a `PING` and wait for its reply, so every command issued before it on the cache's
+        /// multiplexed connection has been answered.
+        ///
+        /// Every operation of the cache uses a clone of one multiplexed connection. Clones share
+        /// its socket, and commands are written in the order their futures are first polled, not
+        /// the order they were created, with replies read back in the same order. An awaited
+        /// operation is therefore always answered before the next one is sent, but operations of
+        /// concurrent tasks, or of futures created but not yet awaited, may interleave in any
+        /// order. Once `flush` returns, all commands written before its `PING`, from any clone,
+        /// have been processed by redis, which makes their writes visible to subsequent reads.
+        /// With `set_bb8_pool_size`, each operation uses its own pooled connection and `flush`
+        /// only orders the pooled connection it happens to acquire.
+        pub async fn flush(&self) -> Result<(), RedisCacheError> {
+            let mut conn = self.connection().await?;
+            redis::cmd("PING").query_async::<_, ()>(&mut conn).await?;
+            Ok(())
+        }
+
+        /// Run `f` with a clone of the cache's multiplexed connection, for issuing custom commands
+        /// alongside the cache's own. Keys written this way aren't managed by the cache, so they
+        /// should be kept outside its prefix. With `set_bb8_pool_size`, the cache has no
+        /// multiplexed connection and a new one is opened for each call.
+        pub async fn with_connection<F, Fut, R>(&self, f: F) -> Result<R, RedisCacheError>
+        where
+            F: FnOnce(redis::aio::MultiplexedConnection) -> Fut,
+            Fut: Future<Output = R>,
+        {
+            let conn = match self.connection_source.connections {
+                Connections::Multiplexed(ref conn) => conn.clone(),
+                #[cfg(feature = "redis_bb8")]
+                Connections::Bb8(_) => {
+                    redis::Client::open(self.connection_string.as_str())?
+                        .get_multiplexed_async_connection()
+                        .await?
+                }
+            };
+            Ok(f(conn).await)
+        }
+
+        /// Return the lifespan `key` was stored with, as opposed to the time it has left (see `cache_ttl`).
+        /// Returns `None` for missing keys and for values stored without a recorded lifespan.
+        pub async fn cache_original_lifespan(
+            &self,
+            key: &K,
+        ) -> Result<Option<u64>, RedisCacheError> {
+            let key = self.generate_key(key);
+            let mut conn = self.connection().await?;
+            let raw: Option<Vec<u8>> = redis::cmd("GET").arg(&key).query_async(&mut conn).await?;
+            match raw {
+                None => Ok(None),
+                Some(raw) => Ok(self.codec.meta(&key, &raw)?.lifespan),
+            }
+        }
+
+        /// Return the metadata stored with `key`'s value without deserializing the value itself.
+        /// Returns `None` for missing keys, negative entries, and soft-removed entries.
+        pub async fn cache_meta(&self, key: &K) -> Result<Option<EntryMeta>, RedisCacheError> {
+            let key = self.generate_key(key);
+            let mut conn = self.connection().await?;
+            let raw: Option<Vec<u8>> = redis::cmd("GET").arg(&key).query_async(&mut conn).await?;
+            match raw {
+                None => Ok(None),
+                Some(raw) => self.codec.entry_meta(&key, raw),
+            }
+        }
+
+        /// Deserialize `legacy_payload`, a value as stored by an earlier version, through the
+        /// cache's current codec, see `RedisCache::assert_compatible_with`
+        pub fn assert_compatible_with(&self, legacy_payload: &str) -> Result<V, RedisCacheError> {
+            self.codec.deserialize(legacy_payload.as_bytes())
+        }
+
+        /// Insert a value only if none is cached for `key`, failing with
+        /// `InsertError::AlreadyExists` otherwise.
+        pub async fn cache_insert(&self, key: K, val: V) -> Result<(), InsertError> {
+            let key = self.generate_key(&key);
+            let started = Instant::now();
+            let res = self.insert_value(&key, val).await;
+            self.observe(
+                CacheOp::Set,
+                &key,
+                started,
+                &res.as_ref().map(|_| None::<V>),
+            );
+            match self.named(res)? {
+                true => Ok(()),
+                false => Err(InsertError::AlreadyExists),
+            }
+        }
+
+        async fn insert_value(&self, key: &str, val: V) -> Result<bool, RedisCacheError> {
+            self.inject_fault(CacheOp::Set, key)?;
+            let mut conn = self.connection().await?;
+            let seconds = self.lifespan_of(&val);
+            let inserted: bool = set_if_absent_cmd(
+                key,
+                &self.codec.serialize(&val, Some(seconds))?,
+                jittered(seconds, self.lifespan_jitter),
+                false,
+            )
+            .query_async(&mut conn)
+            .await?;
+            Ok(inserted)
+        }
+
+        /// Set a value only if none is cached for `key`, and refresh the lifespan of the cached
+        /// value otherwise, see `RedisCache::cache_set_or_refresh`
+        pub async fn cache_set_or_refresh(&self, key: K, val: V) -> Result<bool, RedisCacheError> {
+            let key = self.generate_key(&key);
+            let started = Instant::now();
+            let res = self.set_or_refresh_value(&key, val).await;
+            self.observe(
+                CacheOp::Set,
+                &key,
+                started,
+                &res.as_ref().map(|_| None::<V>),
+            );
+            match self.named(res) {
+                Err(_) if self.ignore_set_errors => Ok(false),
+                res => res,
+            }
+        }
+
+        async fn set_or_refresh_value(&self, key: &str, val: V) -> Result<bool, RedisCacheError> {
+            self.inject_fault(CacheOp::Set, key)?;
+            let mut conn = self.connection().await?;
+            let seconds = self.lifespan_of(&val);
+            let set: bool = set_if_absent_cmd(
+                key,
+                &self.codec.serialize(&val, Some(seconds))?,
+                jittered(seconds, self.lifespan_jitter),
+                true,
+            )
+            .query_async(&mut conn)
+            .await?;
+            Ok(set)
+        }
+
+        /// Insert several values in one round-trip, only for keys that aren't already cached, like
+        /// `cache_insert` does for a single value. Returns, in order, whether each entry was newly
+        /// inserted.
+        pub async fn cache_set_multi_nx<I>(&self, entries: I) -> Result<Vec<bool>, RedisCacheError>
+        where
+            I: IntoIterator<Item = (K, V)>,
+        {
+            let entries = self.serialize_entries(entries)?;
+            let mut conn = self.connection().await?;
+            let mut inserted = Vec::with_capacity(entries.len());
+            for chunk in entries.chunks(self.chunk_size(entries.len())) {
+                let mut pipe = redis::pipe();
+                for (key, val, seconds) in chunk {
+                    pipe.add_command(set_if_absent_cmd(
+                        key,
+                        val,
+                        jittered(*seconds, self.lifespan_jitter),
+                        false,
+                    ));
+                    self.add_set_hooks(&mut pipe, key);
+                }
+                let res: Vec<bool> = pipe.query_async(&mut conn).await?;
+                inserted.extend(res);
+            }
+            Ok(inserted)
+        }
+
+        /// Insert several values, returning once all are written. Entries are sent in
+        /// pipelines, see `AsyncRedisCacheBuilder::set_batch_chunk_size`.
+        pub async fn cache_set_multi<I>(&self, entries: I) -> Result<(), RedisCacheError>
+        where
+            I: IntoIterator<Item = (K, V)>,
+        {
+            let entries = self.serialize_entries(entries)?;
+            let mut conn = self.connection().await?;
+            for chunk in entries.chunks(self.chunk_size(entries.len())) {
+                let mut pipe = redis::pipe();
+                for (key, val, seconds) in chunk {
+                    let ttl = jittered(*seconds, self.lifespan_jitter);
+                    pipe.set_ex::<&str, &[u8]>(key, val, ttl as usize).ignore();
+                }
+                pipe.query_async::<_, ()>(&mut conn).await?;
+            }
+            Ok(())
+        }
+
+        /// Insert several values and return, in order, the previous value of each entry, like
+        /// `cache_set` does for a single value. Entries are sent in pipelines, see
+        /// `AsyncRedisCacheBuilder::set_batch_chunk_size`.
+        pub async fn cache_replace_multi<I>(
+            &self,
+            entries: I,
+        ) -> Result<Vec<Option<V>>, RedisCacheError>
+        where
+            I: IntoIterator<Item = (K, V)>,
+        {
+            let entries = self.serialize_entries(entries)?;
+            let mut conn = self.connection().await?;
+            let mut previous = Vec::with_capacity(entries.len());
+            for chunk in entries.chunks(self.chunk_size(entries.len())) {
+                let mut pipe = redis::pipe();
+                for (key, val, seconds) in chunk {
+                    pipe.get(key);
+                    let ttl = jittered(*seconds, self.lifespan_jitter);
+                    pipe.set_ex::<&str, &[u8]>(key, val, ttl as usize).ignore();
+                    self.add_set_hooks(&mut pipe, key);
+                }
+                let res: Vec<Option<Vec<u8>>> = pipe.query_async(&mut conn).await?;
+                self.wait_for_replicas(&mut conn).await?;
+                for ((key, _, _), raw) in chunk.iter().zip(res) {
+                    previous.push(match raw {
+                        None => None,
+                        Some(s) => self.codec.entry(key, s)?,
+                    });
+                }
+            }
+            Ok(previous)
+        }
+
+        /// Retrieve several cached values, in the same order as `keys`. Keys are requested
+        /// in pipelines, see `AsyncRedisCacheBuilder::set_batch_chunk_size`. Lifespan and
+        /// refresh behave as they do for `cache_get`.
+        pub async fn cache_get_multi(&self, keys: &[K]) -> Result<Vec<Option<V>>, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let mut values = Vec::with_capacity(keys.len());
+            for chunk in keys.chunks(self.chunk_size(keys.len())) {
+                let mut pipe = redis::pipe();
+                let keys: Vec<String> = chunk.iter().map(|key| self.generate_key(key)).collect();
+                for key in &keys {
+                    pipe.get(key);
+                    if self.refresh {
+                        pipe.add_command(refresh_cmd(
+                            key,
+                            self.seconds,
+                            self.refresh_expire_option,
+                        ))
+                        .ignore();
+                    }
+                }
+                let res: Vec<Option<Vec<u8>>> = pipe.query_async(&mut conn).await?;
+                for (key, raw) in keys.iter().zip(res) {
+                    values.push(match raw {
+                        None => None,
+                        Some(s) => self.codec.entry(key, s)?,
+                    });
+                }
+            }
+            Ok(values)
+        }
+
+        /// Subscribe to the expirations, deletions, and evictions of this cache's keys, for keeping
+        /// an in-process cache coherent with redis. Keys that can't be parsed as `K` are skipped.
+        ///
+        /// This relies on keyspace notifications, which are disabled by default: the server's
+        /// `notify-keyspace-events` must include `Egx`, or `EA`, with `e` to also report
+        /// evictions. Returns `KeyspaceNotificationsDisabled` if the setting can be read and
+        /// lacks these flags. Notifications are fire-and-forget, so events sent while the
+        /// stream is reconnecting or not polled are lost.
+        pub async fn watch_keyspace(
+            &self,
+        ) -> Result<impl futures::Stream<Item = KeyEvent<K>>, RedisCacheError>
+        where
+            K: std::str::FromStr,
+        {
+            use futures::StreamExt;
+
+            let client = redis::Client::open(self.connection_string.as_str())?;
+            let db = client.get_connection_info().redis.db;
+            let mut conn = client.get_async_connection().await?;
+            // managed servers may not allow reading the config, assume it's enabled then
+            let config: Result<Vec<String>, _> = redis::cmd("CONFIG")
+                .arg("GET")
+                .arg("notify-keyspace-events")
+                .query_async(&mut conn)
+                .await;
+            if let Ok(config) = config {
+                let current = config.get(1).cloned().unwrap_or_default();
+                if !keyevents_enabled(&current) {
+                    return Err(RedisCacheError::KeyspaceNotificationsDisabled { current });
+                }
+            }
+
+            let mut pubsub = conn.into_pubsub();
+            for event in &["expired", "del", "evicted"] {
+                pubsub
+                    .subscribe(format!("__keyevent@{}__:{}", db, event))
+                    .await?;
+            }
+            let prefix = self.prefix.clone();
+            Ok(pubsub.into_on_message().filter_map(move |msg| {
+                let payload: Option<String> = msg.get_payload().ok();
+                let event =
+                    payload.and_then(|key| key_event(&prefix, msg.get_channel_name(), &key));
+                async move { event }
+            }))
+        }
+
+        /// Wait up to `timeout` for a value to be cached under `key`, for example by a sibling
+        /// task computing it, and return it, or `None` if it wasn't set in time.
+        ///
+        /// When the server reports sets in keyspace notifications (`notify-keyspace-events`
+        /// including `K$` or `KA`), the wait is woken by the notification for the key, with a
+        /// poll every second in case one is missed. Otherwise the key is polled every 50ms.
+        /// Each poll is a `cache_get`, so it counts in the stats.
+        pub async fn cache_await(
+            &self,
+            key: &K,
+            timeout: Duration,
+        ) -> Result<Option<V>, RedisCacheError> {
+            use futures::StreamExt;
+
+            let deadline = Instant::now() + timeout;
+            let (notifications, interval) = match self.subscribe_sets(&self.generate_key(key)).await
+            {
+                Some((notifications, true)) => {
+                    (notifications.left_stream(), AWAIT_RECHECK_INTERVAL)
+                }
+                Some((notifications, false)) => (notifications.left_stream(), AWAIT_POLL_INTERVAL),
+                None => (
+                    futures::stream::pending().right_stream(),
+                    AWAIT_POLL_INTERVAL,
+                ),
+            };
+            let mut wakeups = futures::stream::select(notifications, ticker(interval, deadline));
+            loop {
+                if let Some(value) = self.cache_get(key).await? {
+                    return Ok(Some(value));
+                }
+                if Instant::now() >= deadline || wakeups.next().await.is_none() {
+                    return Ok(None);
+                }
+            }
+        }
+
+        /// Subscribe to the keyspace notifications of `key`, along with whether the server
+        /// confirmed it reports sets. Returns `None` if the server doesn't report them.
+        async fn subscribe_sets(
+            &self,
+            key: &str,
+        ) -> Option<(impl futures::Stream<Item = ()>, bool)> {
+            use futures::StreamExt;
+
+            let client = redis::Client::open(self.connection_string.as_str()).ok()?;
+            let db = client.get_connection_info().redis.db;
+            let mut conn = client.get_async_connection().await.ok()?;
+            // managed servers may not allow reading the config, subscribe anyway then
+            let config: Result<Vec<String>, _> = redis::cmd("CONFIG")
+                .arg("GET")
+                .arg("notify-keyspace-events")
+                .query_async(&mut conn)
+                .await;
+            let confirmed = match config {
+                Ok(config) if !keyspace_sets_enabled(config.get(1)?) => return None,
+                Ok(_) => true,
+                Err(_) => false,
+            };
+            let mut pubsub = conn.into_pubsub();
+            pubsub
+                .subscribe(format!("__keyspace@{}__:{}", db, key))
+                .await
+                .ok()?;
+            Some((pubsub.into_on_message().map(|_| ()), confirmed))
+        }
+
+        /// Retrieve several cached values, returning the hits by key along with the keys
+        /// that missed, in the order they were given. See `cache_get_multi`.
+        pub async fn cache_get_partitioned(
+            &self,
+            keys: &[K],
+        ) -> Result<(HashMap<K, V>, Vec<K>), RedisCacheError>
+        where
+            K: Clone + Hash + Eq,
+        {
+            let values = self.cache_get_multi(keys).await?;
+            let mut hits = HashMap::with_capacity(keys.len());
+            let mut misses = Vec::new();
+            for (key, value) in keys.iter().zip(values) {
+                match value {
+                    Some(v) => {
+                        hits.insert(key.clone(), v);
+                    }
+                    None => misses.push(key.clone()),
+                }
+            }
+            Ok((hits, misses))
+        }
+
+        fn serialize_entries<I>(
+            &self,
+            entries: I,
+        ) -> Result<Vec<(String, Vec<u8>, u64)>, RedisCacheError>
+        where
+            I: IntoIterator<Item = (K, V)>,
+        {
+            entries
+                .into_iter()
+                .map(|(key, val)| {
+                    let seconds = self.lifespan_of(&val);
+                    Ok((
+                        self.generate_key(&key),
+                        self.codec.serialize(&val, Some(seconds))?,
+                        seconds,
+                    ))
+                })
+                .collect()
+        }
+
+        /// The number of keys sent per pipeline by batch operations over `len` keys
+        fn chunk_size(&self, len: usize) -> usize {
+            self.batch_chunk_size.unwrap_or(len).max(1)
+        }
+
+        /// Stream every value under this cache's prefix with its key, walking the keyspace with
+        /// `SCAN` and fetching each page of keys with one `MGET`. Pages are fetched as the stream
+        /// is polled, so the keyspace is never buffered whole. Pages hold about as many keys as
+        /// the batch chunk size, 100 by default. Keys that can't be parsed as `K`, such as hashed
+        /// keys, are skipped. The stream ends after the first error.
+        ///
+        /// As with `SCAN`, values set or removed while streaming may or may not be returned,
+        /// and a value may rarely be returned twice.
+        pub fn cache_stream(
+            &self,
+        ) -> impl futures::Stream<Item = Result<(K, V), RedisCacheError>> + '_
+        where
+            K: std::str::FromStr,
+        {
+            let pending = std::collections::VecDeque::new();
+            futures::stream::unfold(
+                (Some(0), pending),
+                move |(mut cursor, mut pending)| async move {
+                    loop {
+                        if let Some(pair) = pending.pop_front() {
+                            return Some((Ok(pair), (cursor, pending)));
+                        }
+                        match self.scan_page(cursor?).await {
+                            Ok((next, pairs)) => {
+                                pending.extend(pairs);
+                                cursor = (next != 0).then_some(next);
+                            }
+                            Err(e) => return Some((Err(e), (None, pending))),
+                        }
+                    }
+                },
+            )
+        }
+
+        /// Fetch the page of values at `cursor`, returning the next cursor
+        async fn scan_page(&self, cursor: u64) -> Result<(u64, Vec<(K, V)>), RedisCacheError>
+        where
+            K: std::str::FromStr,
+        {
+            let mut conn = self.connection().await?;
+            let (next, keys): (u64, Vec<String>) = redis::cmd("SCAN")
+                .arg(cursor)
+                .arg("MATCH")
+                .arg(format!("{}*", self.prefix))
+                .arg("COUNT")
+                .arg(self.chunk_size(DEFAULT_SCAN_COUNT))
+                .query_async(&mut conn)
+                .await?;
+            if keys.is_empty() {
+                return Ok((next, Vec::new()));
+            }
+            let values: Vec<Option<Vec<u8>>> =
+                redis::cmd("MGET").arg(&keys).query_async(&mut conn).await?;
+            let mut pairs = Vec::with_capacity(keys.len());
+            for (full_key, raw) in keys.iter().zip(values) {
+                let key = match full_key.strip_prefix(self.prefix.as_str()).map(str::parse) {
+                    Some(Ok(key)) => key,
+                    _ => continue,
+                };
+                let value = raw.map(|raw| self.codec.entry(full_key, raw)).transpose()?;
+                if let Some(value) = value.flatten() {
+                    pairs.push((key, value));
+                }
+            }
+            Ok((next, pairs))
+        }
+
+        /// Return the remaining time to live of several cached values in one round-trip.
+        /// Results are in the same order as `keys`, see `cache_ttl` for the meaning of `None`.
+        pub async fn cache_ttl_multi(
+            &self,
+            keys: &[K],
+        ) -> Result<Vec<Option<u64>>, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let mut ttls = Vec::with_capacity(keys.len());
+            for chunk in keys.chunks(self.chunk_size(keys.len())) {
+                let mut pipe = redis::pipe();
+                for key in chunk {
+                    pipe.add_command(live_ttl_cmd(&self.generate_key(key)));
+                }
+                let res: Vec<i64> = pipe.query_async(&mut conn).await?;
+                ttls.extend(res.into_iter().map(ttl_from_reply));
+            }
+            Ok(ttls)
+        }
+
+        /// Reset the time to live of a cached value to the cache lifespan without
+        /// retrieving it. Returns `false` if the key does not exist.
+        pub async fn cache_touch(&self, key: &K) -> Result<bool, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let touched: bool = redis::cmd("EXPIRE")
+                .arg(self.generate_key(key))
+                .arg(self.seconds)
+                .query_async(&mut conn)
+                .await?;
+            Ok(touched)
+        }
+
+        /// Retrieve a cached value, or load it with `f` and cache it on a miss.
+        /// If `f` fails, its error is returned as `RedisCacheLoadError::Loader` and nothing is
+        /// cached, unless `AsyncRedisCacheBuilder::set_negative_cache_on_error` or
+        /// `AsyncRedisCacheBuilder::set_serve_stale_on_loader_error` is set. Keys cached as having
+        /// no value, by `cache_set_miss` or a failed load, return
+        /// `RedisCacheLoadError::NegativeCached` without calling `f`.
+        pub async fn cache_try_get_or_set_with<F, Fut, E>(
+            &self,
+            key: K,
+            f: F,
+        ) -> Result<V, RedisCacheLoadError<E>>
+        where
+            V: Clone,
+            F: FnOnce() -> Fut,
+            Fut: Future<Output = Result<V, E>>,
+        {
+            let stale = match self.cache_get_detailed(&key).await? {
+                GetOutcome::Hit(v) => return Ok(v),
+                GetOutcome::Negative => return Err(RedisCacheLoadError::NegativeCached),
+                GetOutcome::Corrupt(e) => return Err(e.into()),
+                GetOutcome::Removed(v) if self.serve_stale_on_loader_error => Some(v),
+                GetOutcome::Miss | GetOutcome::Removed(_) => None,
+            };
+            let v = match f().await {
+                Ok(v) => v,
+                Err(e) => {
+                    if let Some(stale) = stale {
+                        return Ok(stale);
+                    }
+                    if let Some(seconds) = self.negative_seconds_on_error {
+                        // the loader's error is more useful to the caller than a failure
+                        // to cache it, which is still counted in the stats
+                        let _ = self.cache_set_miss_with_lifespan(key, seconds).await;
+                    }
+                    return Err(RedisCacheLoadError::Loader(e));
+                }
+            };
+            IOCachedAsync::cache_set(self, key, v.clone()).await?;
+            Ok(v)
+        }
+
+        /// Retrieve a cached value along with its remaining time to live in seconds.
+        /// The ttl is read after any refresh, so refreshing caches report the full lifespan.
+        pub async fn cache_get_with_ttl(
+            &self,
+            key: &K,
+        ) -> Result<Option<(V, Option<u64>)>, RedisCacheError> {
+            let mut conn = self.connection().await?;
+            let mut pipe = redis::pipe();
+            let key = self.generate_key(key);
+
+            pipe.get(key.clone());
+            if self.refresh {
+                pipe.add_command(refresh_cmd(&key, self.seconds, self.refresh_expire_option))
+                    .ignore();
+            }
+            pipe.ttl(&key);
+            let (raw, ttl): (Option<Vec<u8>>, i64) = pipe.query_async(&mut conn).await?;
+            match raw {
+                None => Ok(None),
+                Some(s) => Ok(self.codec.entry(&key, s)?.map(|v| (v, ttl_from_reply(ttl)))),
+            }
+        }
+
+        /// Start a pipeline of mixed operations, executed in one round-trip
+        pub fn pipeline(&self) -> AsyncCachePipeline<'_, K, V> {
+            AsyncCachePipeline {
+                cache: self,
+                queued: QueuedOps::new(),
+            }
+        }
+    }
+
+    /// Mixed cache operations executed in a single redis pipeline, created with `AsyncRedisCache::pipeline`
+    ///
+    /// Operations behave like their `IOCachedAsync` counterparts, except that they aren't
+    /// counted in `CacheStats` or reported to the event sink. Sets apply the lifespan jitter,
+    /// `set_ttl_fn`, the key index of `set_max_keys`, recency tracking, and the write wait.
+    pub struct AsyncCachePipeline<'a, K, V> {
+        cache: &'a AsyncRedisCache<K, V>,
+        queued: QueuedOps,
+    }
+
+    impl<'a, K, V> AsyncCachePipeline<'a, K, V>
+    where
+        K: Display + Send + Sync,
+        V: Serialize + DeserializeOwned + Send + Sync,
+    {
+        /// Queue a get of `key`
+        pub fn get(mut self, key: &K) -> Self {
+            let key = self.cache.generate_key(key);
+            let refresh = self
+                .cache
+                .refresh
+                .then(|| refresh_cmd(&key, self.cache.seconds, self.cache.refresh_expire_option));
+            self.queued.get(key, refresh);
+            self
+        }
+
+        /// Queue a set of `key` to `val`
+        pub fn set(mut self, key: K, val: V) -> Self {
+            let cache = self.cache;
+            let key = cache.generate_key(&key);
+            let seconds = cache.lifespan_of(&val);
+            match cache.codec.serialize(&val, Some(seconds)) {
+                Err(e) => self.queued.fail(e),
+                Ok(bytes) => {
+                    let ttl = jittered(seconds, cache.lifespan_jitter);
+                    self.queued.set(&key, bytes, ttl);
+                    cache.add_set_hooks(&mut self.queued.pipe, &key);
+                }
+            }
+            self
+        }
+
+        /// Queue a removal of `key`
+        pub fn remove(mut self, key: &K) -> Self {
+            self.queued
+                .remove(self.cache.generate_key(key), self.cache.soft_remove);
+            self
+        }
+
+        /// Execute the queued operations in one round-trip, returning a reply per operation.
+        /// Fails without sending anything if a queued value couldn't be serialized.
+        pub async fn execute(mut self) -> Result<Vec<PipelineReply<V>>, RedisCacheError> {
+            let replicas = self.queued.wait(self.cache.write_wait);
+            let QueuedOps { pipe, ops, error } = self.queued;
+            if let Some(e) = error {
+                return Err(e);
+            }
+            if ops.is_empty() {
+                return Ok(Vec::new());
+            }
+            let mut conn = self.cache.connection().await?;
+            let raw = QueuedOps::raw_replies(pipe.query_async(&mut conn).await?, replicas)?;
+            QueuedOps::replies(&self.cache.codec, ops, raw)
+        }
+    }
+
+    #[async_trait]
+    impl<K, V> IOCachedAsync<K, V> for AsyncRedisCache<K, V>
+    where
+        K: Display + Send + Sync,
+        V: Serialize + DeserializeOwned + Send + Sync,
+    {
+        type Error = RedisCacheError;
+
+        /// Get a cached value
+        async fn cache_get(&self, key: &K) -> Result<Option<V>, Self::Error> {
+            let key = self.generate_key(key);
+            let started = Instant::now();
+            let res = self.get_value(&key).await;
+            self.observe(CacheOp::Get, &key, started, &res);
+            self.named(res)
+        }
+
+        /// Set a cached value
+        async fn cache_set(&self, key: K, val: V) -> Result<Option<V>, Self::Error> {
+            let key = self.generate_key(&key);
+            let started = Instant::now();
+            let seconds = self.lifespan_of(&val);
+            let res = self.set_value(&key, val, seconds).await;
+            self.observe(CacheOp::Set, &key, started, &res);
+            let res = self.named(res);
+            match res {
+                Err(_) if self.ignore_set_errors => Ok(None),
+                res => res,
+            }
+        }
+
+        /// Remove a cached value
+        async fn cache_remove(&self, key: &K) -> Result<Option<V>, Self::Error> {
+            let key = self.generate_key(key);
+            let started = Instant::now();
+            let res = self.remove_value(&key).await;
+            self.observe(CacheOp::Remove, &key, started, &res);
+            self.named(res)
+        }
+
+        /// Set the flag to control whether cache hits refresh the ttl of cached values, returns the old flag value
+        fn cache_set_refresh(&mut self, refresh: bool) -> bool {
+            let old = self.refresh;
+            self.refresh = refresh;
+            old
+        }
+
+        /// Return the lifespan of cached values (time to eviction)
+        fn cache_lifespan(&self) -> Option<u64> {
+            Some(self.seconds)
+        }
+
+        /// Set the lifespan of cached values, returns the old value
+        fn cache_set_lifespan(&mut self, seconds: u64) -> Option<u64> {
+            let old = self.seconds;
+            self.seconds = seconds;
+            Some(old)
+        }
+    }
+
+    /// Keys requested per `SCAN` by `cache_stream` when no batch chunk size is set
+    const DEFAULT_SCAN_COUNT: usize = 100;
+
+    /// A change to a cached key reported by `AsyncRedisCache::watch_keyspace`
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum KeyEvent<K> {
+        /// The key's TTL ran out
+        Expired(K),
+        /// The key was deleted, by `cache_remove` or otherwise
+        Deleted(K),
+        /// The key was evicted by the server's `maxmemory` policy
+        Evicted(K),
+    }
+
+    /// Whether `notify-keyspace-events` flags report expired and deleted keys as key events
+    fn keyevents_enabled(flags: &str) -> bool {
+        flags.contains('E') && (flags.contains('A') || (flags.contains('g') && flags.contains('x')))
+    }
+
+    /// Whether the `notify-keyspace-events` `flags` report sets of string keys by key
+    fn keyspace_sets_enabled(flags: &str) -> bool {
+        flags.contains('K') && (flags.contains('A') || flags.contains('$'))
+    }
+
+    /// How often `cache_await` polls when keyspace notifications aren't available
+    const AWAIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+    /// How often `cache_await` polls in case a keyspace notification is missed
+    const AWAIT_RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+    /// A stream ticking every `interval` until `deadline`. It's driven by a thread rather
+    /// than a runtime timer, so it works with any async runtime.
+    fn ticker(interval: Duration, deadline: Instant) -> impl futures::Stream<Item = ()> {
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        std::thread::spawn(move || loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            std::thread::sleep(interval.min(deadline - now));
+            if tx.unbounded_send(()).is_err() {
+                break;
+            }
+        });
+        rx
+    }
+
+    /// Parse the key event notification of `key` on `channel`, if `key` is under `prefix`
+    fn key_event<K: std::str::FromStr>(
+        prefix: &str,
+        channel: &str,
+        key: &str,
+    ) -> Option<KeyEvent<K>> {
+        let key = key.strip_prefix(prefix)?.parse().ok()?;
+        match channel.rsplit(':').next()? {
+            "expired" => Some(KeyEvent::Expired(key)),
+            "del" => Some(KeyEvent::Deleted(key)),
+            "evicted" => Some(KeyEvent::Evicted(key)),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::thread::sleep;
+        use std::time::Duration;
+
+        #[test]
+        fn test_keyevents_enabled() {
+            assert!(keyevents_enabled("Egx"));
+            assert!(keyevents_enabled("AE"));
+            assert!(!keyevents_enabled(""));
+            assert!(!keyevents_enabled("Kgx"));
+            assert!(!keyevents_enabled("Ex"));
+        }
+
+        #[test]
+        fn test_key_event() {
+            let prefix = "cached-redis-store:users:";
+            assert_eq!(
+                Some(KeyEvent::Expired(1)),
+                key_event::<u32>(
+                    prefix,
+                    "__keyevent@0__:expired",
+                    "cached-redis-store:users:1"
+                )
+            );
+            assert_eq!(
+                Some(KeyEvent::Deleted(2)),
+                key_event::<u32>(prefix, "__keyevent@3__:del", "cached-redis-store:users:2")
+            );
+            assert_eq!(
+                None,
+                key_event::<u32>(prefix, "__keyevent@0__:del", "cached-redis-store:other:2")
+            );
+            assert_eq!(
+                None,
+                key_event::<u32>(prefix, "__keyevent@0__:del", "cached-redis-store:users:x")
+            );
+        }
+
+        fn now_millis() -> u128 {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_millis()
+        }
+
+        #[async_std::test]
+        async fn test_async_redis_cache() {
+            let mut c: AsyncRedisCache<u32, u32> =
+                AsyncRedisCache::new(format!("{}-async-redis-cache-test", now_millis()), 2)
+                    .build()
+                    .await
+                    .unwrap();
+
+            assert!(c.cache_get(&1).await.unwrap().is_none());
+
+            assert!(c.cache_set(1, 100).await.unwrap().is_none());
+            assert!(c.cache_get(&1).await.unwrap().is_some());
+
+            sleep(Duration::new(2, 500000));
+            assert!(c.cache_get(&1).await.unwrap().is_none());
+
+            let old = c.cache_set_lifespan(1).unwrap();
+            assert_eq!(2, old);
+            assert!(c.cache_set(1, 100).await.unwrap().is_none());
+            assert!(c.cache_get(&1).await.unwrap().is_some());
+
+            sleep(Duration::new(1, 600000));
+            assert!(c.cache_get(&1).await.unwrap().is_none());
+
+            c.cache_set_lifespan(10).unwrap();
+            assert!(c.cache_set(1, 100).await.unwrap().is_none());
+            assert!(c.cache_set(2, 100).await.unwrap().is_none());
+            assert_eq!(c.cache_get(&1).await.unwrap().unwrap(), 100);
+            assert_eq!(c.cache_get(&1).await.unwrap().unwrap(), 100);
+        }
+
+        #[async_std::test]
+        async fn test_async_exists_and_ttl() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-exists-ttl", now_millis()),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+
+            assert!(!c.cache_exists(&1).await.unwrap());
+            assert!(c.cache_ttl(&1).await.unwrap().is_none());
+            assert!(!c.cache_touch(&1).await.unwrap());
+            assert!(c.cache_get_with_ttl(&1).await.unwrap().is_none());
+
+            assert!(c.cache_set(1, 100).await.unwrap().is_none());
+            assert!(c.cache_exists(&1).await.unwrap());
+            assert!(c.cache_ttl(&1).await.unwrap().unwrap() <= 10);
+            assert!(c.cache_touch(&1).await.unwrap());
+
+            let (v, ttl) = c.cache_get_with_ttl(&1).await.unwrap().unwrap();
+            assert_eq!(100, v);
+            assert!(ttl.unwrap() <= 10);
+        }
+
+        #[cfg(feature = "redis_bb8")]
+        #[tokio::test]
+        async fn test_async_bb8_pool_concurrency() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-bb8-pool", now_millis()),
+                10,
+            )
+            .set_bb8_pool_size(4)
+            .build()
+            .await
+            .unwrap();
+
+            let sets = (0..64).map(|i| c.cache_set(i, i * 10));
+            for res in futures::future::join_all(sets).await {
+                assert_eq!(None, res.unwrap());
+            }
+            let keys: Vec<u32> = (0..64).collect();
+            let gets = keys.iter().map(|i| c.cache_get(i));
+            let values = futures::future::join_all(gets).await;
+            for (i, res) in values.into_iter().enumerate() {
+                assert_eq!(Some(i as u32 * 10), res.unwrap());
+            }
+        }
+
+        #[async_std::test]
+        async fn test_async_batch_chunks() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-batch-chunks", now_millis()),
+                10,
+            )
+            .set_batch_chunk_size(2)
+            .build()
+            .await
+            .unwrap();
+
+            assert!(c.cache_get_multi(&[]).await.unwrap().is_empty());
+            c.cache_set_multi((1..=5).map(|i| (i, i * 100)))
+                .await
+                .unwrap();
+            assert_eq!(
+                vec![true, false, true],
+                c.cache_set_multi_nx(vec![(0, 0), (1, 1), (6, 600)])
+                    .await
+                    .unwrap()
+            );
+
+            assert_eq!(
+                vec![Some(0), Some(100), Some(200)],
+                c.cache_replace_multi(vec![(0, 1), (1, 101), (2, 201)])
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                vec![Some(1), Some(101), Some(201)],
+                c.cache_replace_multi(vec![(0, 0), (1, 100), (2, 200)])
+                    .await
+                    .unwrap()
+            );
+
+            let keys: Vec<u32> = (0..=7).collect();
+            let values = c.cache_get_multi(&keys).await.unwrap();
+            assert_eq!(
+                vec![
+                    Some(0),
+                    Some(100),
+                    Some(200),
+                    Some(300),
+                    Some(400),
+                    Some(500),
+                    Some(600),
+                    None
+                ],
+                values
+            );
+            let ttls = c.cache_ttl_multi(&keys).await.unwrap();
+            assert_eq!(8, ttls.len());
+            assert!(ttls[7].is_none());
+
+            let (hits, misses) = c.cache_get_partitioned(&[1, 7, 2, 8]).await.unwrap();
+            assert_eq!(2, hits.len());
+            assert_eq!(Some(&100), hits.get(&1));
+            assert_eq!(Some(&200), hits.get(&2));
+            assert_eq!(vec![7, 8], misses);
+        }
+
+        #[async_std::test]
+        async fn test_async_flush() {
+            let c: AsyncRedisCache<u32, u32> =
+                AsyncRedisCache::new(format!("{}-async-redis-cache-test-flush", now_millis()), 10)
+                    .build()
+                    .await
+                    .unwrap();
+
+            let set = c.cache_set(1, 100);
+            c.flush().await.unwrap();
+            assert!(set.await.unwrap().is_none());
+            c.flush().await.unwrap();
+            assert_eq!(Some(100), c.cache_get(&1).await.unwrap());
+        }
+
+        #[async_std::test]
+        async fn test_async_with_connection() {
+            let prefix = format!("{}-async-redis-cache-test-with-connection", now_millis());
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(prefix.as_str(), 10)
+                .build()
+                .await
+                .unwrap();
+            let companion = format!("{}-companion", prefix);
+
+            let added: u32 = c
+                .with_connection(|mut conn| async move {
+                    redis::cmd("SADD")
+                        .arg(&companion)
+                        .arg(1)
+                        .arg(2)
+                        .query_async(&mut conn)
+                        .await
+                        .unwrap()
+                })
+                .await
+                .unwrap();
+            assert_eq!(2, added);
+            assert!(c.cache_set(1, 100).await.unwrap().is_none());
+            assert_eq!(100, c.cache_get(&1).await.unwrap().unwrap());
+        }
+
+        #[async_std::test]
+        async fn test_async_get_or_set_with_future() {
+            use futures::FutureExt;
+            use std::sync::atomic::AtomicU32;
+
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-get-or-set-future", now_millis()),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+            let loads = Arc::new(AtomicU32::new(0));
+            let counted = loads.clone();
+            let shared = async move {
+                counted.fetch_add(1, Ordering::SeqCst);
+                100
+            }
+            .shared();
+
+            assert_eq!(
+                100,
+                c.cache_get_or_set_with_future(1, shared.clone())
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(100, c.cache_get(&1).await.unwrap().unwrap());
+            assert_eq!(
+                100,
+                c.cache_get_or_set_with_future(1, async { unreachable!() })
+                    .await
+                    .unwrap()
+            );
+            assert_eq!(
+                100,
+                c.cache_get_or_set_with_future(2, shared).await.unwrap()
+            );
+            assert_eq!(1, loads.load(Ordering::SeqCst));
+        }
+
+        #[async_std::test]
+        async fn test_async_cache_await() {
+            let c: Arc<AsyncRedisCache<u32, u32>> = Arc::new(
+                AsyncRedisCache::new(format!("{}-async-redis-cache-test-await", now_millis()), 10)
+                    .build()
+                    .await
+                    .unwrap(),
+            );
+
+            assert!(c
+                .cache_await(&1, Duration::from_millis(100))
+                .await
+                .unwrap()
+                .is_none());
+
+            let producer = c.clone();
+            let produced = async_std::task::spawn(async move {
+                async_std::task::sleep(Duration::from_millis(200)).await;
+                producer.cache_set(1, 100).await.unwrap();
+            });
+            let started = Instant::now();
+            assert_eq!(
+                Some(100),
+                c.cache_await(&1, Duration::from_secs(5)).await.unwrap()
+            );
+            assert!(started.elapsed() < Duration::from_secs(2));
+            produced.await;
+        }
+
+        #[async_std::test]
+        async fn test_async_cache_stream() {
+            use futures::TryStreamExt;
+
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-stream", now_millis()),
+                10,
+            )
+            .set_batch_chunk_size(2)
+            .build()
+            .await
+            .unwrap();
+
+            assert!(c
+                .cache_stream()
+                .try_collect::<Vec<_>>()
+                .await
+                .unwrap()
+                .is_empty());
+            c.cache_set_multi((0..5).map(|i| (i, i * 100)))
+                .await
+                .unwrap();
+            c.cache_set_miss(5).await.unwrap();
+
+            let mut pairs: Vec<(u32, u32)> = c.cache_stream().try_collect().await.unwrap();
+            pairs.sort_unstable();
+            pairs.dedup();
+            assert_eq!(vec![(0, 0), (1, 100), (2, 200), (3, 300), (4, 400)], pairs);
+        }
+
+        #[async_std::test]
+        async fn test_async_watch_keyspace() {
+            use futures::StreamExt;
+
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-watch-keyspace", now_millis()),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+            let client = redis::Client::open(c.connection_string.as_str()).unwrap();
+            let mut conn = client.get_async_connection().await.unwrap();
+            redis::cmd("CONFIG")
+                .arg("SET")
+                .arg("notify-keyspace-events")
+                .arg("Egx")
+                .query_async::<_, ()>(&mut conn)
+                .await
+                .unwrap();
+
+            let events = c.watch_keyspace().await.unwrap();
+            futures::pin_mut!(events);
+            assert!(c.cache_set(1, 100).await.unwrap().is_none());
+            assert!(c.cache_remove(&1).await.unwrap().is_some());
+            assert_eq!(Some(KeyEvent::Deleted(1)), events.next().await);
+            c.cache_set_with_lifespan(2, 200, 1).await.unwrap();
+            assert_eq!(Some(KeyEvent::Expired(2)), events.next().await);
+        }
+
+        #[async_std::test]
+        async fn test_async_negative_cache_on_error() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-negative-on-error", now_millis()),
+                10,
+            )
+            .set_negative_cache_on_error(1)
+            .build()
+            .await
+            .unwrap();
+
+            let res = c
+                .cache_try_get_or_set_with(1, || async { Err::<u32, _>("db down") })
+                .await;
+            assert!(matches!(res, Err(RedisCacheLoadError::Loader("db down"))));
+            let res = c
+                .cache_try_get_or_set_with(1, || async { Ok::<_, ()>(100) })
+                .await;
+            assert!(matches!(res, Err(RedisCacheLoadError::NegativeCached)));
+
+            sleep(Duration::from_secs(2));
+            let v = c
+                .cache_try_get_or_set_with(1, || async { Ok::<_, ()>(100) })
+                .await
+                .unwrap();
+            assert_eq!(100, v);
+        }
+
+        #[async_std::test]
+        async fn test_async_get_or_set_negative() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!(
+                    "{}-async-redis-cache-test-get-or-set-negative",
+                    now_millis()
+                ),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+
+            c.cache_set_miss(1).await.unwrap();
+            let res = c
+                .cache_try_get_or_set_with(1, || async { Ok::<_, ()>(100) })
+                .await;
+            assert!(matches!(res, Err(RedisCacheLoadError::NegativeCached)));
+            let res = c
+                .cache_get_or_set_with_ttl(1, 10, || async { unreachable!() })
+                .await;
+            assert!(matches!(res, Err(RedisCacheError::NegativeCached)));
+            let res = c
+                .cache_get_or_set_with_future(1, async { unreachable!() })
+                .await;
+            assert!(matches!(res, Err(RedisCacheError::NegativeCached)));
+        }
+
+        #[async_std::test]
+        async fn test_async_serve_stale_on_loader_error() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-serve-stale", now_millis()),
+                10,
+            )
+            .set_soft_remove(10)
+            .set_serve_stale_on_loader_error(true)
+            .build()
+            .await
+            .unwrap();
+
+            let res = c
+                .cache_try_get_or_set_with(1, || async { Err::<u32, _>("db down") })
+                .await;
+            assert!(matches!(res, Err(RedisCacheLoadError::Loader("db down"))));
+
+            assert!(c.cache_set(1, 100).await.unwrap().is_none());
+            assert_eq!(Some(100), c.cache_remove(&1).await.unwrap());
+            let v = c
+                .cache_try_get_or_set_with(1, || async { Err::<u32, _>("db down") })
+                .await
+                .unwrap();
+            assert_eq!(100, v);
+            assert!(c.cache_get(&1).await.unwrap().is_none());
+
+            let v = c
+                .cache_try_get_or_set_with(1, || async { Ok::<_, ()>(200) })
+                .await
+                .unwrap();
+            assert_eq!(200, v);
+        }
+
+        #[async_std::test]
+        async fn test_async_try_get_or_set_with() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-try-get-or-set", now_millis()),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+
+            let res = c
+                .cache_try_get_or_set_with(1, || async { Err::<u32, _>("db down") })
+                .await;
+            assert!(matches!(res, Err(RedisCacheLoadError::Loader("db down"))));
+            assert!(!c.cache_exists(&1).await.unwrap());
+
+            let v = c
+                .cache_try_get_or_set_with(1, || async { Ok::<_, ()>(100) })
+                .await
+                .unwrap();
+            assert_eq!(100, v);
+
+            let v = c
+                .cache_try_get_or_set_with(1, || async { Ok::<_, ()>(200) })
+                .await
+                .unwrap();
+            assert_eq!(100, v);
+        }
+
+        #[async_std::test]
+        async fn test_async_insert() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-insert", now_millis()),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+
+            assert!(c.cache_insert(1, 100).await.is_ok());
+            assert!(matches!(
+                c.cache_insert(1, 200).await,
+                Err(InsertError::AlreadyExists)
+            ));
+            assert_eq!(100, c.cache_get(&1).await.unwrap().unwrap());
+        }
+
+        #[test]
+        fn resilience_policy() {
+            let builder = AsyncRedisCache::<u32, u32>::new("async-redis-cache-test-resilience", 10)
+                .set_resilience(ResiliencePolicy::RESILIENT);
+            assert!(builder.ignore_set_errors);
+            assert!(builder.serve_stale_on_loader_error);
+
+            let builder = builder.set_resilience(ResiliencePolicy::BEST_EFFORT);
+            assert!(builder.ignore_set_errors);
+            assert!(!builder.serve_stale_on_loader_error);
+        }
+
+        #[async_std::test]
+        async fn test_async_option_values() {
+            let c: AsyncRedisCache<u32, Option<u32>> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-option-values", now_millis()),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+
+            assert_eq!(None, c.cache_set(1, None).await.unwrap());
+            assert_eq!(None, c.cache_set(2, Some(2)).await.unwrap());
+            assert_eq!(Some(None), c.cache_get(&1).await.unwrap());
+            assert_eq!(
+                vec![Some(None), Some(Some(2)), None],
+                c.cache_get_multi(&[1, 2, 3]).await.unwrap()
+            );
+            assert_eq!(Some(None), c.cache_remove(&1).await.unwrap());
+            assert_eq!(None, c.cache_get(&1).await.unwrap());
+        }
+
+        #[async_std::test]
+        async fn test_async_set_or_refresh() {
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(
+                format!("{}-async-redis-cache-test-set-or-refresh", now_millis()),
+                10,
+            )
+            .build()
+            .await
+            .unwrap();
+
+            assert!(c.cache_set_or_refresh(1, 100).await.unwrap());
+            assert!(!c.cache_set_or_refresh(1, 200).await.unwrap());
+            assert_eq!(100, c.cache_get(&1).await.unwrap().unwrap());
+        }
+
+        #[async_std::test]
+        async fn test_async_fallback_codec() {
+            struct PrefixedCodec;
+
+            impl ValueCodec<u32> for PrefixedCodec {
+                fn encode(&self, value: &u32) -> Result<String, RedisCacheError> {
+                    Ok(format!("p/{}", value))
+                }
+
+                fn decode(&self, encoded: &str) -> Result<u32, RedisCacheError> {
+                    encoded
+                        .strip_prefix("p/")
+                        .and_then(|v| v.parse().ok())
+                        .ok_or_else(|| RedisCacheError::CodecError {
+                            error: format!("unexpected encoding {:?}", encoded).into(),
+                        })
+                }
+            }
+
+            let prefix = format!("{}-async-redis-cache-test-fallback-codec", now_millis());
+            let json: AsyncRedisCache<u32, u32> =
+                AsyncRedisCache::new(&prefix, 10).build().await.unwrap();
+            let raw: AsyncRedisCache<u32, String> =
+                AsyncRedisCache::new(&prefix, 10).build().await.unwrap();
+            let c: AsyncRedisCache<u32, u32> = AsyncRedisCache::new(&prefix, 10)
+                .set_codec(Arc::new(PrefixedCodec))
+                .set_json_fallback()
+                .build()
+                .await
+                .unwrap();
+
+            assert!(json.cache_set(1, 100).await.unwrap().is_none());
+            assert_eq!(Some(100), c.cache_get(&1).await.unwrap());
+            assert_eq!(Some("p/100".to_string()), raw.cache_get(&1).await.unwrap());
+        }
+    }
+}
+
+#[cfg(all(
+    feature = "async",
+    any(feature = "redis_async_std", feature = "redis_tokio")
+))]
+pub use async_redis::{AsyncCachePipeline, AsyncRedisCache, KeyEvent};
+
+#[cfg(test)]
+/// Cache store tests
+mod tests {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use super::*;
+
+    fn now_millis() -> u128 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+    }
+
+    #[test]
+    fn redis_cache() {
+        let mut c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test", now_millis()), 2)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_get(&1).unwrap().is_none());
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_get(&1).unwrap().is_some());
+
+        sleep(Duration::new(2, 500000));
+        assert!(c.cache_get(&1).unwrap().is_none());
+
+        let old = c.cache_set_lifespan(1).unwrap();
+        assert_eq!(2, old);
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_get(&1).unwrap().is_some());
+
+        sleep(Duration::new(1, 600000));
+        assert!(c.cache_get(&1).unwrap().is_none());
+
+        c.cache_set_lifespan(10).unwrap();
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_set(2, 100).unwrap().is_none());
+        assert_eq!(c.cache_get(&1).unwrap().unwrap(), 100);
+        assert_eq!(c.cache_get(&1).unwrap().unwrap(), 100);
+    }
+
+    #[test]
+    fn remove() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-remove", now_millis()), 3600)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_set(2, 200).unwrap().is_none());
+        assert!(c.cache_set(3, 300).unwrap().is_none());
+
+        assert_eq!(100, c.cache_remove(&1).unwrap().unwrap());
+    }
+
+    #[test]
+    fn exists_and_ttl() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-exists-ttl", now_millis()), 10)
+                .build()
+                .unwrap();
+
+        assert!(!c.cache_exists(&1).unwrap());
+        assert!(c.cache_ttl(&1).unwrap().is_none());
+        assert!(!c.cache_touch(&1).unwrap());
+        assert!(c.cache_get_with_ttl(&1).unwrap().is_none());
+        assert!(c.cache_memory_usage(&1).unwrap().is_none());
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_exists(&1).unwrap());
+        assert!(c.cache_memory_usage(&1).unwrap().unwrap() > 0);
+        assert!(c.cache_ttl(&1).unwrap().unwrap() <= 10);
+        assert!(c.cache_touch(&1).unwrap());
+
+        let (v, ttl) = c.cache_get_with_ttl(&1).unwrap().unwrap();
+        assert_eq!(100, v);
+        assert!(ttl.unwrap() <= 10);
+
+        let ttls = c.cache_ttl_multi(&[1, 2]).unwrap();
+        assert!(ttls[0].unwrap() <= 10);
+        assert!(ttls[1].is_none());
+        assert!(c.cache_ttl_multi(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn original_lifespan() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-original-lifespan", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.cache_original_lifespan(&1).unwrap().is_none());
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(Some(10), c.cache_original_lifespan(&1).unwrap());
+
+        assert!(c.cache_set_with_lifespan(2, 200, 60).unwrap().is_none());
+        assert_eq!(Some(60), c.cache_original_lifespan(&2).unwrap());
+        assert!(c.cache_ttl(&2).unwrap().unwrap() > 10);
+        assert_eq!(Some(200), c.cache_get(&2).unwrap());
+    }
+
+    #[test]
+    fn meta() {
+        let prefix = format!("{}-redis-cache-test-meta", now_millis());
+        let c: RedisCache<u32, Vec<u32>> = RedisCache::new(prefix.clone(), 10).build().unwrap();
+
+        assert!(c.cache_meta(&1).unwrap().is_none());
+        assert!(c.cache_set(1, (0..10_000).collect()).unwrap().is_none());
+        assert_eq!(Some(10), c.cache_meta(&1).unwrap().unwrap().lifespan);
+
+        // the value isn't deserialized, so metadata can be read with any value type
+        let other: RedisCache<u32, String> = RedisCache::new(prefix, 10).build().unwrap();
+        assert_eq!(Some(10), other.cache_meta(&1).unwrap().unwrap().lifespan);
+        assert!(other.cache_get(&1).is_err());
+    }
+
+    #[test]
+    fn get_and_refresh() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-get-and-refresh", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.cache_get_and_refresh(&1, 60).unwrap().is_none());
+        assert!(!c.cache_exists(&1).unwrap());
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get_and_refresh(&1, 60).unwrap());
+        assert!(c.cache_ttl(&1).unwrap().unwrap() > 10);
+        assert_eq!(1, c.cache_stats().hits);
+    }
+
+    #[test]
+    fn incr_bounded() {
+        let c: RedisCache<&str, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-incr-bounded", now_millis()),
+            60,
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(3, c.cache_incr_bounded(&"bucket", 3, 0, 5).unwrap());
+        assert!(c.cache_ttl(&"bucket").unwrap().unwrap() > 10);
+        assert_eq!(5, c.cache_incr_bounded(&"bucket", 3, 0, 5).unwrap());
+        assert_eq!(0, c.cache_incr_bounded(&"bucket", -10, 0, 5).unwrap());
+        assert_eq!(1, c.cache_incr_bounded(&"bucket", 1, 0, 5).unwrap());
+
+        assert!(c.cache_set("value", 1).unwrap().is_none());
+        assert!(c.cache_incr_bounded(&"value", 1, 0, 5).is_err());
+    }
+
+    #[test]
+    fn diagnose_invalid_connection_string() {
+        let diagnostics = RedisCache::<u32, u32>::new("diagnose", 60)
+            .set_connection_string("not-a-redis-url")
+            .diagnose();
+
+        assert!(diagnostics.connection_string.is_passed());
+        assert!(matches!(diagnostics.parse, DiagnosticCheck::Failed(_)));
+        assert_eq!(DiagnosticCheck::Skipped, diagnostics.reachable);
+        assert_eq!(DiagnosticCheck::Skipped, diagnostics.ping);
+        assert!(!diagnostics.is_ok());
+        assert_eq!("parse", diagnostics.first_failure().unwrap().0);
+    }
+
+    #[test]
+    fn diagnose() {
+        let diagnostics = RedisCache::<u32, u32>::new("diagnose", 60).diagnose();
+        assert!(diagnostics.is_ok(), "{}", diagnostics);
+        assert!(diagnostics.first_failure().is_none());
+    }
+
+    #[test]
+    fn named_errors() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-named", now_millis()), 10)
+                .set_name("profiles")
+                .set_max_read_bytes(1)
+                .build()
+                .unwrap();
+        assert_eq!(Some("profiles"), c.name());
+
+        c.cache_set(1, 100).unwrap();
+        let error = c.cache_get(&1).unwrap_err();
+        assert!(error.to_string().starts_with("Error in cache \"profiles\""));
+        assert!(matches!(
+            error.unnamed(),
+            RedisCacheError::ValueTooLarge { .. }
+        ));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn tags() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-tags", now_millis()), 10)
+                .build()
+                .unwrap();
+        let user = vec!["user-42".to_string()];
+        let both = vec!["user-42".to_string(), "team-7".to_string()];
+
+        assert!(c.cache_set_tagged(1, 100, &user).unwrap().is_none());
+        assert!(c.cache_set_tagged(2, 200, &both).unwrap().is_none());
+        assert!(c.cache_set_tagged(3, 300, &user).unwrap().is_none());
+        assert!(c.cache_set(4, 400).unwrap().is_none());
+        assert_eq!(Some(300), c.cache_remove(&3).unwrap());
+
+        assert_eq!(2, c.cache_invalidate_tag("user-42").unwrap());
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(c.cache_get(&2).unwrap().is_none());
+        assert_eq!(Some(400), c.cache_get(&4).unwrap());
+        assert_eq!(0, c.cache_invalidate_tag("user-42").unwrap());
+        assert_eq!(0, c.cache_invalidate_tag("team-7").unwrap());
+    }
+
+    #[test]
+    fn set_keep_ttl() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-set-keep-ttl", now_millis()),
+            60,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.cache_set_keep_ttl(1, 100).unwrap().is_none());
+        assert!(c.cache_ttl(&1).unwrap().unwrap() > 50);
+
+        assert!(c.cache_set_with_lifespan(2, 100, 10).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_set_keep_ttl(2, 200).unwrap());
+        assert!(c.cache_ttl(&2).unwrap().unwrap() <= 10);
+        assert_eq!(Some(200), c.cache_get(&2).unwrap());
+    }
+
+    #[test]
+    fn update() {
+        let c: Arc<RedisCache<u32, u32>> = Arc::new(
+            RedisCache::new(format!("{}-redis-cache-test-update", now_millis()), 60)
+                .build()
+                .unwrap(),
+        );
+
+        assert_eq!(1, c.cache_update(1, |v| v.map_or(1, |v| v + 1)).unwrap());
+        assert_eq!(2, c.cache_update(1, |v| v.map_or(1, |v| v + 1)).unwrap());
+        assert!(c.cache_ttl(&1).unwrap().unwrap() > 50);
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let c = c.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        c.cache_update(1, |v| v.map_or(1, |v| v + 1)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(Some(42), c.cache_get(&1).unwrap());
+    }
+
+    #[test]
+    fn update_chunked() {
+        let c: RedisCache<u32, String> = RedisCache::new(
+            format!("{}-redis-cache-test-update-chunked", now_millis()),
+            60,
+        )
+        .set_chunked_storage(8)
+        .set_pool_max_size(1)
+        .build()
+        .unwrap();
+        let long = "a".repeat(100);
+
+        assert_eq!(long, c.cache_update(1, |_| long.clone()).unwrap());
+        assert_eq!(Some(long.clone()), c.cache_get(&1).unwrap());
+        let appended = c.cache_update(1, |v| v.unwrap() + "b").unwrap();
+        assert_eq!(Some(appended), c.cache_get(&1).unwrap());
+        assert_eq!("c", c.cache_update(1, |_| "c".to_string()).unwrap());
+        let key = c.generate_key(&1);
+        let mut conn = c.connection().unwrap();
+        let part_exists: bool = redis::cmd("EXISTS")
+            .arg(chunk_key(&key, 0))
+            .query(&mut *conn)
+            .unwrap();
+        assert!(!part_exists);
+
+        // A failed update leaves no key watched on the pooled connection
+        redis::cmd("SET")
+            .arg(&key)
+            .arg("not a value")
+            .query::<()>(&mut *conn)
+            .unwrap();
+        drop(conn);
+        assert!(c.cache_update(1, |_| "d".to_string()).is_err());
+        let mut conn = c.connection().unwrap();
+        redis::cmd("SET")
+            .arg(&key)
+            .arg("changed")
+            .query::<()>(&mut *conn)
+            .unwrap();
+        let written: Option<()> = redis::pipe()
+            .atomic()
+            .set(&key, "e")
+            .ignore()
+            .query(&mut *conn)
+            .unwrap();
+        assert!(written.is_some());
+    }
+
+    #[test]
+    fn get_or_set_with_ttl_jitter() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-get-or-set-ttl-jitter", now_millis()),
+            10,
+        )
+        .set_lifespan_jitter(5)
+        .build()
+        .unwrap();
+
+        assert_eq!(100, c.cache_get_or_set_with_ttl(1, 60, || 100).unwrap());
+        let ttl = c.cache_ttl(&1).unwrap().unwrap();
+        assert!((55..=65).contains(&ttl));
+        assert_eq!(Some(60), c.cache_original_lifespan(&1).unwrap());
+        assert_eq!(
+            100,
+            c.cache_get_or_set_with_ttl(1, 60, || unreachable!())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn ttl_fn() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-ttl-fn", now_millis()), 10)
+                .set_ttl_fn(Arc::new(|val| u64::from(*val)))
+                .build()
+                .unwrap();
+
+        assert!(c.cache_set(1, 60).unwrap().is_none());
+        assert!(c.cache_set(2, 0).unwrap().is_none());
+        assert!(c.cache_set_with_lifespan(3, 60, 30).unwrap().is_none());
+        assert!((55..=60).contains(&c.cache_ttl(&1).unwrap().unwrap()));
+        assert_eq!(Some(60), c.cache_original_lifespan(&1).unwrap());
+        assert!(c.cache_ttl(&2).unwrap().unwrap() <= 1);
+        assert!((25..=30).contains(&c.cache_ttl(&3).unwrap().unwrap()));
+
+        // every write without an explicit lifespan goes through the ttl fn
+        assert!(c.cache_insert(4, 60).is_ok());
+        assert!(c.cache_set_or_refresh(5, 60).unwrap());
+        assert_eq!(2, c.warm(vec![(6, 60), (7, 60)]).unwrap());
+        assert_eq!(vec![true], c.cache_set_multi_nx(vec![(8, 60)]).unwrap());
+        for key in 4..=8 {
+            assert!((55..=60).contains(&c.cache_ttl(&key).unwrap().unwrap()));
+            assert_eq!(Some(60), c.cache_original_lifespan(&key).unwrap());
+        }
+    }
+
+    #[test]
+    fn get_or_set_negative() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-get-or-set-negative", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        c.cache_set_miss(1).unwrap();
+        assert!(matches!(
+            c.cache_get_or_set_with_ttl(1, 10, || unreachable!()),
+            Err(RedisCacheError::NegativeCached)
+        ));
+        assert_eq!(100, c.cache_get_or_set_with_ttl(2, 10, || 100).unwrap());
+    }
+
+    #[test]
+    fn jitter_bounds() {
+        assert_eq!(10, jittered(10, 0));
+        for _ in 0..100 {
+            assert!((10..=13).contains(&jittered(10, 3)));
+        }
+    }
+
+    #[test]
+    fn connection_observer() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let observed = events.clone();
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-connection-observer", now_millis()),
+            60,
+        )
+        .set_connection_observer(Arc::new(move |event| observed.lock().unwrap().push(event)))
+        .build()
+        .unwrap();
+
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(events.lock().unwrap().contains(&ConnectionEvent::Connected));
+    }
+
+    #[test]
+    fn approx_len() {
+        let sampled = vec![
+            Some(b"ns:a:1".to_vec()),
+            Some(b"ns:b:1".to_vec()),
+            Some(b"ns:a:2".to_vec()),
+            None,
+        ];
+        assert_eq!(500, estimate_len(1000, &sampled, "ns:a:"));
+        assert_eq!(0, estimate_len(1000, &sampled, "ns:c:"));
+        assert_eq!(0, estimate_len(1000, &[], "ns:a:"));
+
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-approx-len", now_millis()), 60)
+                .build()
+                .unwrap();
+        for i in 0..10 {
+            c.cache_set(i, i).unwrap();
+        }
+        assert!(c.cache_approx_len().is_ok());
+    }
+
+    #[test]
+    fn composite_key() {
+        let a = CompositeKey::from(("a:b", "c"));
+        let b = CompositeKey::from(("a", "b:c"));
+        assert_eq!("a\\:b:c", a.to_string());
+        assert_eq!("a:b\\:c", b.to_string());
+        assert_ne!(a.to_string(), b.to_string());
+        assert_ne!(
+            CompositeKey::from(("a\\", "b")).to_string(),
+            CompositeKey::new().push("a\\:b").to_string()
+        );
+
+        let key = CompositeKey::new().push("us\\er:1").push(42).push("");
+        assert_eq!(key, key.to_string().parse().unwrap());
+        assert_eq!(&["us\\er:1", "42", ""], key.parts());
+    }
+
+    #[test]
+    fn key_hashing() {
+        assert_eq!(
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824",
+            sha256_hex("hello")
+        );
+
+        let prefix = format!("{}-redis-cache-test-key-hashing", now_millis());
+        let hashed: RedisCache<&str, u32> = RedisCache::new(&prefix, 60)
+            .set_hash_keys(true)
+            .build()
+            .unwrap();
+        let plain: RedisCache<String, u32> = RedisCache::new(&prefix, 60).build().unwrap();
+        assert!(hashed.cache_set("hello", 1).unwrap().is_none());
+        assert_eq!(Some(1), hashed.cache_get(&"hello").unwrap());
+        assert_eq!(Some(1), plain.cache_get(&sha256_hex("hello")).unwrap());
+
+        let custom: RedisCache<&str, u32> = RedisCache::new(&prefix, 60)
+            .set_key_hasher(Arc::new(|key| key.len().to_string()))
+            .build()
+            .unwrap();
+        assert!(custom.cache_set("hello", 2).unwrap().is_none());
+        assert_eq!(Some(2), plain.cache_get(&"5".to_string()).unwrap());
+    }
+
+    #[test]
+    fn max_keys() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-max-keys", now_millis()), 60)
+                .set_max_keys(3)
+                .build()
+                .unwrap();
+
+        for i in 0..10 {
+            assert!(c.cache_set(i, i).unwrap().is_none());
+        }
+        assert_eq!(Some(9), c.cache_get(&9).unwrap());
+        let cached = (0..10).filter(|i| c.cache_exists(i).unwrap()).count();
+        assert_eq!(3, cached);
+
+        let mut conn = c.connection().unwrap();
+        let indexed: u64 = redis::cmd("SCARD")
+            .arg(c.key_index_key())
+            .query(&mut *conn)
+            .unwrap();
+        assert_eq!(3, indexed);
+        drop(conn);
+        assert_eq!(3, c.cache_len().unwrap());
+    }
+
+    #[test]
+    fn max_keys_chunked() {
+        let c: RedisCache<u32, String> = RedisCache::new(
+            format!("{}-redis-cache-test-max-keys-chunked", now_millis()),
+            60,
+        )
+        .set_max_keys(1)
+        .set_chunked_storage(8)
+        .set_track_recency(true)
+        .build()
+        .unwrap();
+        let long = "a".repeat(100);
+
+        assert!(c.cache_set(1, long.clone()).unwrap().is_none());
+        assert!(c.cache_set(2, long).unwrap().is_none());
+        assert!(!c.cache_exists(&1).unwrap());
+        let key = c.generate_key(&1);
+        let mut conn = c.connection().unwrap();
+        let part_exists: bool = redis::cmd("EXISTS")
+            .arg(chunk_key(&key, 0))
+            .query(&mut *conn)
+            .unwrap();
+        assert!(!part_exists);
+        let recent: Option<f64> = redis::cmd("ZSCORE")
+            .arg(c.recent_key())
+            .arg(&key)
+            .query(&mut *conn)
+            .unwrap();
+        assert!(recent.is_none());
+    }
+
+    #[test]
+    fn cache_reconcile() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-reconcile", now_millis()), 60)
+                .set_max_keys(10)
+                .build()
+                .unwrap();
+
+        for i in 0..5 {
+            assert!(c.cache_set(i, i).unwrap().is_none());
+        }
+        assert_eq!(Some(0), c.cache_remove(&0).unwrap());
+        assert_eq!(Some(1), c.cache_remove(&1).unwrap());
+        assert_eq!(5, c.cache_len().unwrap());
+        assert_eq!(2, c.cache_reconcile().unwrap());
+        assert_eq!(3, c.cache_len().unwrap());
+        assert_eq!(0, c.cache_reconcile().unwrap());
+    }
+
+    #[test]
+    fn chunked_storage() {
+        let c: RedisCache<u32, String> =
+            RedisCache::new(format!("{}-redis-cache-test-chunked", now_millis()), 60)
+                .set_chunked_storage(8)
+                .build()
+                .unwrap();
+        let long = "a".repeat(100);
+
+        assert!(c.cache_set(1, long.clone()).unwrap().is_none());
+        assert_eq!(Some(long.clone()), c.cache_get(&1).unwrap());
+        let key = c.generate_key(&1);
+        let mut conn = c.connection().unwrap();
+        let manifest: Vec<u8> = redis::cmd("GET").arg(&key).query(&mut *conn).unwrap();
+        let chunks = chunk_manifest(&manifest).unwrap().chunks;
+        assert!(chunks > 1);
+        let ttl: i64 = redis::cmd("TTL")
+            .arg(chunk_key(&key, chunks - 1))
+            .query(&mut *conn)
+            .unwrap();
+        assert!(ttl > 0);
+
+        assert_eq!(Some(long.clone()), c.cache_set(1, "b".into()).unwrap());
+        let part_exists: bool = redis::cmd("EXISTS")
+            .arg(chunk_key(&key, 0))
+            .query(&mut *conn)
+            .unwrap();
+        assert!(!part_exists);
+        assert_eq!(Some("b".to_string()), c.cache_get(&1).unwrap());
+
+        assert!(c.cache_set(2, long.clone()).unwrap().is_none());
+        assert_eq!(Some(long), c.cache_remove(&2).unwrap());
+        assert!(c.cache_get(&2).unwrap().is_none());
+        let part_exists: bool = redis::cmd("EXISTS")
+            .arg(chunk_key(&c.generate_key(&2), 0))
+            .query(&mut *conn)
+            .unwrap();
+        assert!(!part_exists);
+    }
+
+    #[test]
+    fn chunk_manifests() {
+        let manifest = serde_json::to_vec(&ChunkManifest { chunks: 3 }).unwrap();
+        assert_eq!(3, chunk_manifest(&manifest).unwrap().chunks);
+        assert!(chunk_manifest(br#"{"value":1,"version":1}"#).is_none());
+        assert!(chunk_manifest(NEGATIVE_ENTRY.as_bytes()).is_none());
+        assert_eq!("prefix:1:part:2", chunk_key("prefix:1", 2));
+    }
+
+    #[test]
+    fn track_recency() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-recency", now_millis()), 60)
+                .set_track_recency(true)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_recent(3).unwrap().is_empty());
+        for i in 0..5 {
+            assert!(c.cache_set(i, i).unwrap().is_none());
+            sleep(Duration::from_millis(2));
+        }
+        assert_eq!(vec![4, 3, 2], c.cache_recent(3).unwrap());
+
+        assert!(c.cache_remove(&3).unwrap().is_some());
+        assert_eq!(vec![4, 2, 1], c.cache_recent(3).unwrap());
+        assert_eq!(vec![4, 2, 1, 0], c.cache_recent(10).unwrap());
+        assert!(c.cache_recent(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn recency_helpers() {
+        let keys = vec!["p:1".to_string(), "p:2".to_string(), "q:3".to_string()];
+        let (live, stale) = partition_live(keys, vec![true, false, true]);
+        assert_eq!(vec!["p:1".to_string(), "q:3".to_string()], live);
+        assert_eq!(vec!["p:2".to_string()], stale);
+        assert_eq!(vec![1u32], parse_keys::<u32>("p:", live));
+    }
+
+    #[test]
+    fn key_normalizer() {
+        let prefix = format!("{}-redis-cache-test-key-normalizer", now_millis());
+        let c: RedisCache<&str, u32> = RedisCache::new(&prefix, 60)
+            .set_key_normalizer(Normalization::Lowercase)
+            .build()
+            .unwrap();
+        let plain: RedisCache<&str, u32> = RedisCache::new(&prefix, 60).build().unwrap();
+        assert!(c.cache_set("Foo", 1).unwrap().is_none());
+        assert_eq!(Some(1), c.cache_get(&"foo").unwrap());
+        assert_eq!(Some(1), c.cache_get(&"FOO").unwrap());
+        assert_eq!(Some(1), plain.cache_get(&"foo").unwrap());
+        assert_eq!(None, plain.cache_get(&"Foo").unwrap());
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!("Foo", Normalization::None.apply("Foo".to_string()));
+        assert_eq!("foo", Normalization::Lowercase.apply("Foo".to_string()));
+        assert_eq!(
+            sha256_hex("foo"),
+            format_key(
+                Normalization::Lowercase,
+                &Some(Arc::new(sha256_hex)),
+                &"FOO"
+            )
+        );
+    }
+
+    #[test]
+    fn fallback_prefix() {
+        let old_prefix = format!("{}-redis-cache-test-fallback-old", now_millis());
+        let old: RedisCache<u32, u32> = RedisCache::new(&old_prefix, 60).build().unwrap();
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-fallback-new", now_millis()),
+            60,
+        )
+        .set_fallback_prefix(&old_prefix)
+        .build()
+        .unwrap();
+
+        assert!(old.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+        assert!(c.cache_ttl(&1).unwrap().unwrap() > 10);
+        assert!(!old.cache_exists(&1).unwrap());
+        assert!(c.cache_get(&2).unwrap().is_none());
+
+        let invalid = RedisCache::<u32, u32>::new("valid", 60)
+            .set_fallback_prefix("in:valid")
+            .build();
+        assert!(matches!(
+            invalid,
+            Err(RedisCacheBuildError::InvalidPrefix { .. })
+        ));
+    }
+
+    #[test]
+    fn fail_fast_on_exhaustion() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-fail-fast", now_millis()), 60)
+                .set_fail_fast_on_exhaustion(true)
+                .build()
+                .unwrap();
+        assert!(c.cache_get(&1).unwrap().is_none());
+
+        let pool = match c.connections {
+            Connections::Pool(ref pool) => pool,
+            Connections::Direct { .. } => unreachable!(),
+        };
+        let held: Vec<_> = (0..pool.max_size()).map(|_| pool.get().unwrap()).collect();
+        assert!(matches!(
+            c.cache_get(&1),
+            Err(RedisCacheError::PoolExhausted)
+        ));
+        drop(held);
+        assert!(c.cache_get(&1).unwrap().is_none());
+    }
+
+    #[test]
+    fn is_healthy() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-healthy", now_millis()), 60)
+                .set_health_cache_ttl(Duration::from_secs(60))
+                .build()
+                .unwrap();
+        assert!(c.ping().is_ok());
+        assert!(c.is_healthy());
+        assert!(c.is_healthy());
+    }
+
+    #[test]
+    fn boxed_backend() {
+        fn backend(enabled: bool) -> Box<dyn IOCached<u32, u32, Error = RedisCacheError>> {
+            if enabled {
+                Box::new(
+                    RedisCache::new("unreachable", 60)
+                        .set_connection_string("redis://127.0.0.1:1")
+                        .without_pool()
+                        .build()
+                        .unwrap(),
+                )
+            } else {
+                Box::new(crate::NoopCache::new())
+            }
+        }
+
+        assert!(backend(true).cache_get(&1).is_err());
+        assert!(backend(false).cache_get(&1).unwrap().is_none());
+    }
+
+    #[test]
+    fn compatible_with() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct User {
+            name: String,
+            #[serde(default)]
+            admin: bool,
+        }
+
+        let c: RedisCache<u32, User> = RedisCache::new("compatible", 60)
+            .set_connection_string("redis://127.0.0.1:1")
+            .without_pool()
+            .build()
+            .unwrap();
+        let user = c
+            .assert_compatible_with(r#"{"value":{"name":"alice"},"lifespan":60}"#)
+            .unwrap();
+        assert_eq!(
+            User {
+                name: "alice".to_string(),
+                admin: false
+            },
+            user
+        );
+        assert!(matches!(
+            c.assert_compatible_with(r#"{"value":{"login":"alice"}}"#),
+            Err(RedisCacheError::CacheDeserializationError { .. })
+        ));
+    }
+
+    #[test]
+    fn is_healthy_unreachable() {
+        let c: RedisCache<u32, u32> = RedisCache::new("unreachable", 60)
+            .set_connection_string("redis://127.0.0.1:1")
+            .without_pool()
+            .set_health_cache_ttl(Duration::from_millis(100))
+            .build()
+            .unwrap();
+        assert!(c.ping().is_err());
+        assert!(!c.is_healthy());
+        let checked = c.health.lock().unwrap().unwrap().0;
+        assert!(!c.is_healthy());
+        assert_eq!(checked, c.health.lock().unwrap().unwrap().0);
+
+        sleep(Duration::from_millis(150));
+        assert!(!c.is_healthy());
+        assert!(c.health.lock().unwrap().unwrap().0 > checked);
+    }
+
+    #[test]
+    fn without_pool() {
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-without-pool", now_millis()),
+            60,
+        )
+        .without_pool()
+        .set_connection_observer(Arc::new(move |event| recorded.lock().unwrap().push(event)))
+        .build()
+        .unwrap();
+        assert!(matches!(c.connections, Connections::Direct { .. }));
+
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(c.cache_get(&1).unwrap(), Some(100));
+        assert_eq!(c.cache_remove(&1).unwrap(), Some(100));
+        let events = events.lock().unwrap();
+        assert_eq!(
+            events.len(),
+            8,
+            "each operation opens and closes its own connection"
+        );
+        assert!(events
+            .chunks(2)
+            .all(|pair| pair == [ConnectionEvent::Connected, ConnectionEvent::Disconnected]));
+    }
+
+    #[test]
+    fn rename() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-rename", now_millis()), 60)
+                .build()
+                .unwrap();
+
+        assert!(!c.cache_rename(&1, &2).unwrap());
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_rename(&1, &2).unwrap());
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get(&2).unwrap());
+        assert!(c.cache_ttl(&2).unwrap().unwrap() > 10);
+
+        assert!(c.cache_set(3, 300).unwrap().is_none());
+        assert!(!c.cache_rename(&3, &2).unwrap());
+        assert_eq!(Some(300), c.cache_get(&3).unwrap());
+        assert_eq!(Some(100), c.cache_get(&2).unwrap());
+    }
+
+    #[test]
+    fn migrate_prefix() {
+        let old_prefix = format!("{}-redis-cache-test-migrate-old", now_millis());
+        let new_prefix = format!("{}-redis-cache-test-migrate-new", now_millis());
+        let old: RedisCache<u32, u32> = RedisCache::new(&old_prefix, 60).build().unwrap();
+        let new: RedisCache<u32, u32> = RedisCache::new(&new_prefix, 60).build().unwrap();
+
+        for i in 0..2500 {
+            assert!(old.cache_set(i, i * 10).unwrap().is_none());
+        }
+        assert!(new.cache_set(0, 1).unwrap().is_none());
+
+        assert_eq!(2499, old.migrate_prefix(&new_prefix).unwrap());
+        assert_eq!(Some(1), new.cache_get(&0).unwrap());
+        assert_eq!(Some(0), old.cache_get(&0).unwrap());
+        assert_eq!(Some(24990), new.cache_get(&2499).unwrap());
+        assert!(old.cache_get(&2499).unwrap().is_none());
+        assert!(new.cache_ttl(&2499).unwrap().unwrap() > 50);
+
+        assert_eq!(0, old.migrate_prefix(&new_prefix).unwrap());
+        assert_eq!(0, old.migrate_prefix(&old_prefix).unwrap());
+        assert!(matches!(
+            old.migrate_prefix("a:b"),
+            Err(RedisCacheError::InvalidPrefix { .. })
+        ));
+    }
+
+    #[test]
+    fn take_stats() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-stats", now_millis()), 10)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_get(&1).unwrap().is_some());
+        assert!(c.cache_remove(&1).unwrap().is_some());
+
+        let stats = c.cache_take_stats();
+        assert_eq!(1, stats.hits);
+        assert_eq!(1, stats.misses);
+        assert_eq!(1, stats.sets);
+        assert_eq!(1, stats.removes);
+        assert_eq!(0, stats.errors);
+        assert_eq!(CacheStats::default(), c.cache_stats());
+    }
+
+    #[test]
+    fn stats_snapshot() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-stats-snapshot", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(c.cache_get(&1).unwrap().is_some());
+        let snapshot = c.cache_stats_snapshot();
+        assert_eq!(1, snapshot.hits);
+        assert_eq!(1, snapshot.sets);
+        assert!(snapshot.approx_len.is_some());
+        assert!(snapshot.acquisitions >= 2);
+        let json = serde_json::to_value(snapshot).unwrap();
+        assert_eq!(1, json["hits"]);
+        assert_eq!(0, json["misses"]);
+    }
+
+    #[test]
+    fn stats_uptime() {
+        let stats = Stats::default();
+        stats.record_get(&Ok::<_, ()>(Some(1)));
+        let snapshot = stats.serializable(None);
+        assert_eq!(1, snapshot.hits);
+        assert_eq!(None, snapshot.approx_len);
+        assert_eq!(0, snapshot.uptime_seconds);
+        assert_eq!(0, snapshot.acquisitions);
+
+        stats.record_acquire(Instant::now() - Duration::from_millis(2), true);
+        stats.record_acquire(Instant::now(), false);
+        let snapshot = stats.serializable(None);
+        assert_eq!(2, snapshot.acquisitions);
+        assert_eq!(1, snapshot.waited_acquisitions);
+        assert!(snapshot.acquisition_micros >= 2000);
+
+        stats.take();
+        let snapshot = stats.serializable(None);
+        assert_eq!(0, snapshot.hits);
+        assert_eq!(0, snapshot.acquisitions);
+        assert_eq!(0, snapshot.acquisition_micros);
+    }
+
+    #[test]
+    fn get_set_in_sub_prefix() {
+        let prefix = format!("{}-redis-cache-test-sub-prefix", now_millis());
+        let sibling = format!("{}-redis-cache-test-sub-prefix-sibling", now_millis());
+        let c: RedisCache<u32, u32> = RedisCache::new(&prefix, 10).build().unwrap();
+        let s: RedisCache<u32, u32> = RedisCache::new(&sibling, 10).build().unwrap();
+
+        assert!(c.cache_set_in(&sibling, 1, 100).unwrap().is_none());
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert_eq!(100, c.cache_get_in(&sibling, &1).unwrap().unwrap());
+        assert_eq!(100, s.cache_get(&1).unwrap().unwrap());
+        assert!(matches!(
+            c.cache_get_in("a:b", &1),
+            Err(RedisCacheError::InvalidPrefix { .. })
+        ));
+        assert!(matches!(
+            c.cache_set_in("a:b", 1, 200),
+            Err(RedisCacheError::InvalidPrefix { .. })
+        ));
+    }
+
+    #[test]
+    fn option_values() {
+        let c: RedisCache<u32, Option<u32>> = RedisCache::new(
+            format!("{}-redis-cache-test-option-values", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert_eq!(None, c.cache_set(1, None).unwrap());
+        assert_eq!(None, c.cache_set(2, Some(2)).unwrap());
+        assert_eq!(Some(None), c.cache_get(&1).unwrap());
+        assert_eq!(Some(Some(2)), c.cache_get(&2).unwrap());
+        assert_eq!(None, c.cache_get(&3).unwrap());
+        assert!(matches!(
+            c.cache_get_detailed(&1).unwrap(),
+            GetOutcome::Hit(None)
+        ));
+        assert!(c.cache_exists(&1).unwrap());
+
+        assert_eq!(Some(None), c.cache_set(1, Some(1)).unwrap());
+        assert_eq!(Some(Some(1)), c.cache_set(1, None).unwrap());
+        assert_eq!(Some(None), c.cache_remove(&1).unwrap());
+        assert_eq!(None, c.cache_remove(&1).unwrap());
+    }
+
+    #[test]
+    fn get_set_raw_key() {
+        let prefix = format!("{}-redis-cache-test-raw-key", now_millis());
+        let c: RedisCache<u32, u32> = RedisCache::new(&prefix, 10).build().unwrap();
+        let external = format!("{}-external:1", prefix);
+
+        assert!(c.cache_set_raw_key(&external, 100).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get_raw_key(&external).unwrap());
+        assert!(c.cache_get(&1).unwrap().is_none());
+
+        assert!(c.cache_set(1, 200).unwrap().is_none());
+        let full_key = c.generate_key(&1);
+        assert_eq!(Some(200), c.cache_get_raw_key(&full_key).unwrap());
+        assert_eq!(2, c.cache_stats().sets);
+    }
+
+    #[test]
+    fn get_bytes() {
+        let c: RedisCache<u32, Vec<String>> =
+            RedisCache::new(format!("{}-redis-cache-test-get-bytes", now_millis()), 10)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_get_bytes(&1).unwrap().is_none());
+        assert!(c
+            .cache_set(1, vec!["a".to_string(), "b".to_string()])
+            .unwrap()
+            .is_none());
+        assert_eq!(
+            Some(br#"["a","b"]"#.to_vec()),
+            c.cache_get_bytes(&1).unwrap()
+        );
+        c.cache_set_miss(2).unwrap();
+        assert!(c.cache_get_bytes(&2).unwrap().is_none());
+        assert_eq!(3, c.cache_stats().misses + c.cache_stats().hits);
+    }
+
+    #[test]
+    fn event_sink() {
+        let (sink, events) = std::sync::mpsc::sync_channel(2);
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-events", now_millis()), 10)
+                .set_event_sink(sink)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        // the channel is full, this event is dropped without blocking
+        assert!(c.cache_get(&1).unwrap().is_some());
+
+        let get = events.try_recv().unwrap();
+        assert_eq!(CacheOp::Get, get.op);
+        assert!(get.key.ends_with("redis-cache-test-events:1"));
+        assert!(!get.hit);
+        let set = events.try_recv().unwrap();
+        assert_eq!(CacheOp::Set, set.op);
+        assert!(!set.hit);
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn write_wait() {
+        // the test server has no replicas, so waiting for zero always succeeds
+        // and waiting for one always times out
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-write-wait", now_millis()), 10)
+                .set_write_wait(0, Duration::from_millis(10))
+                .build()
+                .unwrap();
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-write-wait", now_millis()), 10)
+                .set_write_wait(1, Duration::from_millis(10))
+                .build()
+                .unwrap();
+        assert!(matches!(
+            c.cache_set(1, 100),
+            Err(RedisCacheError::WriteWaitTimeout {
+                acked: 0,
+                required: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn set_multi_nx() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-set-multi-nx", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.cache_set(2, 200).unwrap().is_none());
+        let created = c
+            .cache_set_multi_nx(vec![(1, 100), (2, 201), (3, 300)])
+            .unwrap();
+        assert_eq!(vec![true, false, true], created);
+        assert_eq!(200, c.cache_get(&2).unwrap().unwrap());
+        assert_eq!(300, c.cache_get(&3).unwrap().unwrap());
+        assert!(c.cache_set_multi_nx(vec![]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn set_multi_nx_tombstones() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-set-multi-nx-tombstones", now_millis()),
+            10,
+        )
+        .set_soft_remove(30)
+        .set_max_keys(10)
+        .build()
+        .unwrap();
+
+        c.cache_set_miss(1).unwrap();
+        assert!(c.cache_set(2, 200).unwrap().is_none());
+        assert_eq!(Some(200), c.cache_remove(&2).unwrap());
+        assert!(c.cache_set(3, 300).unwrap().is_none());
+        let created = c
+            .cache_set_multi_nx(vec![(1, 100), (2, 201), (3, 301), (4, 400)])
+            .unwrap();
+        assert_eq!(vec![true, true, false, true], created);
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+        assert_eq!(Some(201), c.cache_get(&2).unwrap());
+        assert_eq!(4, c.cache_len().unwrap());
+    }
+
+    #[test]
+    fn insert() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-insert", now_millis()), 10)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_insert(1, 100).is_ok());
+        assert!(matches!(
+            c.cache_insert(1, 200),
+            Err(InsertError::AlreadyExists)
+        ));
+        assert_eq!(100, c.cache_get(&1).unwrap().unwrap());
+        assert!(c.cache_remove(&1).unwrap().is_some());
+        assert!(c.cache_insert(1, 300).is_ok());
+    }
+
+    #[test]
+    fn insert_tombstones() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-insert-tombstones", now_millis()),
+            10,
+        )
+        .set_soft_remove(30)
+        .build()
+        .unwrap();
+
+        c.cache_set_miss(1).unwrap();
+        assert!(c.cache_insert(1, 100).is_ok());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+
+        assert_eq!(Some(100), c.cache_remove(&1).unwrap());
+        assert!(c.cache_insert(1, 200).is_ok());
+        assert_eq!(Some(200), c.cache_get(&1).unwrap());
+        assert!(matches!(
+            c.cache_insert(1, 300),
+            Err(InsertError::AlreadyExists)
+        ));
+    }
+
+    #[test]
+    fn set_or_refresh() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-set-or-refresh", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.cache_set_or_refresh(1, 100).unwrap());
+        let mut conn = c.connection().unwrap();
+        redis::cmd("EXPIRE")
+            .arg(c.generate_key(&1))
+            .arg(2)
+            .query::<()>(&mut *conn)
+            .unwrap();
+        assert!(!c.cache_set_or_refresh(1, 200).unwrap());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+        assert!(c.cache_ttl(&1).unwrap().unwrap() > 2);
+    }
+
+    #[test]
+    fn set_or_refresh_tombstones() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!(
+                "{}-redis-cache-test-set-or-refresh-tombstones",
+                now_millis()
+            ),
+            10,
+        )
+        .set_soft_remove(30)
+        .build()
+        .unwrap();
+
+        c.cache_set_miss(1).unwrap();
+        assert!(c.cache_set_or_refresh(1, 100).unwrap());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+
+        assert_eq!(Some(100), c.cache_remove(&1).unwrap());
+        assert!(c.cache_set_or_refresh(1, 200).unwrap());
+        assert_eq!(Some(200), c.cache_get(&1).unwrap());
+    }
+
+    #[test]
+    fn chunked_meta_and_refresh() {
+        let c: RedisCache<u32, String> = RedisCache::new(
+            format!("{}-redis-cache-test-chunked-meta", now_millis()),
+            10,
+        )
+        .set_chunked_storage(8)
+        .build()
+        .unwrap();
+        let long = "a".repeat(100);
+
+        assert!(c.cache_set(1, long.clone()).unwrap().is_none());
+        assert_eq!(Some(10), c.cache_original_lifespan(&1).unwrap());
+        assert_eq!(Some(10), c.cache_meta(&1).unwrap().unwrap().lifespan);
+
+        let key = c.generate_key(&1);
+        let mut conn = c.connection().unwrap();
+        let part_ttl = |conn: &mut Connection| -> i64 {
+            redis::cmd("TTL")
+                .arg(chunk_key(&key, 0))
+                .query(&mut **conn)
+                .unwrap()
+        };
+        assert_eq!(Some(long.clone()), c.cache_get_and_refresh(&1, 30).unwrap());
+        assert!(part_ttl(&mut conn) > 10);
+        assert!(c.cache_touch(&1).unwrap());
+        assert!(part_ttl(&mut conn) <= 10);
+        assert!(!c.cache_touch(&2).unwrap());
+    }
+
+    #[test]
+    fn set_or_refresh_chunked() {
+        let c: RedisCache<u32, String> = RedisCache::new(
+            format!("{}-redis-cache-test-set-or-refresh-chunked", now_millis()),
+            10,
+        )
+        .set_chunked_storage(8)
+        .build()
+        .unwrap();
+        let long = "a".repeat(100);
+
+        assert!(c.cache_set(1, long.clone()).unwrap().is_none());
+        let key = c.generate_key(&1);
+        let mut conn = c.connection().unwrap();
+        redis::cmd("EXPIRE")
+            .arg(chunk_key(&key, 0))
+            .arg(2)
+            .query::<()>(&mut *conn)
+            .unwrap();
+        assert!(!c.cache_set_or_refresh(1, "b".to_string()).unwrap());
+        let ttl: i64 = redis::cmd("TTL")
+            .arg(chunk_key(&key, 0))
+            .query(&mut *conn)
+            .unwrap();
+        assert!(ttl > 2);
+        assert_eq!(Some(long), c.cache_get(&1).unwrap());
+    }
+
+    #[test]
+    fn hash_ring() {
+        let keys: Vec<String> = (0..1000).map(|i| format!("prefix:{}", i)).collect();
+        let three = HashRing::new(3);
+        let mut counts = [0; 3];
+        for key in &keys {
+            counts[three.shard(key)] += 1;
+        }
+        assert!(counts.iter().all(|&count| count > 200), "{:?}", counts);
+
+        let four = HashRing::new(4);
+        let moved = keys
+            .iter()
+            .filter(|key| three.shard(key) != four.shard(key))
+            .count();
+        assert!(moved < 400, "{} keys moved", moved);
+        assert!(keys
+            .iter()
+            .filter(|key| three.shard(key) != four.shard(key))
+            .all(|key| four.shard(key) == 3));
+    }
+
+    #[test]
+    fn sharded() {
+        let cs = std::env::var(ENV_KEY).unwrap();
+        let mut c: ShardedRedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-sharded", now_millis()), 10)
+                .build_sharded(&[&cs, &cs])
+                .unwrap();
+        assert_eq!(2, c.shards().len());
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+        assert_eq!(Some(100), c.shard_for(&1).cache_get(&1).unwrap());
+
+        let created = c
+            .cache_set_multi_nx((1..=10).map(|i| (i, i * 100)))
+            .unwrap();
+        assert!(!created[0]);
+        assert!(created[1..].iter().all(|&created| created));
+        let values = c.cache_get_multi(&[3, 11, 7]).unwrap();
+        assert_eq!(vec![Some(300), None, Some(700)], values);
+        let previous = c.cache_replace_multi(vec![(2, 201), (12, 1200)]).unwrap();
+        assert_eq!(vec![Some(200), None], previous);
+
+        assert_eq!(Some(100), c.cache_remove(&1).unwrap());
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert_eq!(Some(10), c.cache_set_lifespan(60));
+
+        assert!(matches!(
+            RedisCache::<u32, u32>::new("sharded", 10).build_sharded(&[]),
+            Err(RedisCacheBuildError::NoShards)
+        ));
+    }
+
+    #[test]
+    fn bytes_cache() {
+        let c: RedisBytesCache<u32> =
+            RedisBytesCache::new(format!("{}-redis-cache-test-bytes", now_millis()), 10)
+                .build_bytes()
+                .unwrap();
+        let body = Bytes::from_static(b"\x00<html>\xff");
+
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(c.cache_set(1, body.clone()).unwrap().is_none());
+        assert_eq!(Some(body.clone()), c.cache_get(&1).unwrap());
+
+        let mut conn = c.cache.connection().unwrap();
+        let raw: Vec<u8> = redis::cmd("GET")
+            .arg(c.cache.generate_key(&1))
+            .query(&mut *conn)
+            .unwrap();
+        assert_eq!(&body[..], &raw[..]);
+        assert!(c.cache.cache_ttl(&1).unwrap().unwrap() <= 10);
+
+        assert_eq!(Some(body), c.cache_remove(&1).unwrap());
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert_eq!(1, c.cache_stats().hits);
+    }
+
+    #[test]
+    fn sharded_build_error() {
+        let res = RedisCache::<u32, u32>::new("sharded", 10)
+            .set_pool_max_size(2)
+            .set_pool_connection_timeout(Duration::from_millis(100))
+            .build_sharded(&["redis://:secret@127.0.0.1:1", "redis://127.0.0.1:2"]);
+        match res {
+            Err(RedisCacheBuildError::Shard {
+                index,
+                connection_string,
+                error,
+            }) => {
+                assert_eq!(0, index);
+                assert_eq!("redis://***@127.0.0.1:1", connection_string);
+                assert!(matches!(*error, RedisCacheBuildError::Pool(_)));
+            }
+            _ => panic!("expected a shard build error"),
+        }
+    }
+
+    #[test]
+    fn pool_errors() {
+        struct AlwaysConnects;
+        impl r2d2::ManageConnection for AlwaysConnects {
+            type Connection = ();
+            type Error = redis::RedisError;
+            fn connect(&self) -> Result<(), redis::RedisError> {
+                Ok(())
+            }
+            fn is_valid(&self, _conn: &mut ()) -> Result<(), redis::RedisError> {
+                Ok(())
+            }
+            fn has_broken(&self, _conn: &mut ()) -> bool {
+                false
+            }
+        }
+
+        let busy = r2d2::Pool::builder()
+            .max_size(1)
+            .connection_timeout(Duration::from_millis(50))
+            .build(AlwaysConnects)
+            .unwrap();
+        let _held = busy.get().unwrap();
+        let error = RedisCacheError::from(busy.get().err().unwrap());
+        assert!(matches!(error, RedisCacheError::PoolTimeout));
+
+        let unreachable = r2d2::Pool::builder()
+            .connection_timeout(Duration::from_millis(50))
+            .build_unchecked(redis::Client::open("redis://127.0.0.1:1").unwrap());
+        match RedisCacheError::from(unreachable.get().err().unwrap()) {
+            RedisCacheError::PoolConnection { error } => {
+                assert!(!error.starts_with(':'), "{}", error);
+                assert!(!error.is_empty());
+            }
+            error => panic!("expected a connection error, got {:?}", error),
+        }
+    }
+
+    #[test]
+    fn command_timeout() {
+        // accepts connections but never replies, like a hung server
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("redis://{}", listener.local_addr().unwrap());
+
+        let manager = ClientManager {
+            client: redis::Client::open(url.as_str()).unwrap(),
+            command_timeout: Some(Duration::from_millis(50)),
+        };
+        let mut conn = r2d2::ManageConnection::connect(&manager).unwrap();
+        assert!(!r2d2::ManageConnection::has_broken(&manager, &mut conn));
+        let res = redis::cmd("GET")
+            .arg("key")
+            .query::<Option<String>>(&mut conn);
+        assert!(matches!(
+            res.map_err(RedisCacheError::from),
+            Err(RedisCacheError::Timeout)
+        ));
+        assert!(r2d2::ManageConnection::has_broken(&manager, &mut conn));
+
+        let c: RedisCache<u32, u32> = RedisCache::new("redis-cache-test-command-timeout", 10)
+            .set_connection_string(&url)
+            .set_command_timeout(Duration::from_millis(50))
+            .without_pool()
+            .build()
+            .unwrap();
+        assert!(matches!(c.cache_get(&1), Err(RedisCacheError::Timeout)));
+        let snapshot = c.stats.serializable(None);
+        assert_eq!(1, snapshot.acquisitions);
+        assert_eq!(0, snapshot.waited_acquisitions);
+    }
+
+    #[test]
+    fn auto_prefix() {
+        assert_eq!("my-lib", redis_auto_prefix("my-lib", "my_lib"));
+        assert_eq!(
+            "my-lib.users.cache",
+            redis_auto_prefix("my-lib", "my_lib::users::cache")
+        );
+        let prefix = crate::redis_auto_prefix!();
+        assert_eq!("cached.stores.redis.tests", prefix);
+        assert!(validate_prefix(&prefix).is_ok());
+    }
+
+    #[test]
+    fn redacted_credentials() {
+        assert_eq!(
+            "redis://127.0.0.1:6379",
+            redact_credentials("redis://127.0.0.1:6379")
+        );
+        assert_eq!(
+            "rediss://***@example.com:6380/1",
+            redact_credentials("rediss://user:p@ss@example.com:6380/1")
+        );
+        assert_eq!(
+            "unix:///tmp/redis.sock",
+            redact_credentials("unix:///tmp/redis.sock")
+        );
+    }
+
+    #[test]
+    fn replace_multi() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-replace-multi", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.cache_set(2, 200).unwrap().is_none());
+        let previous = c.cache_replace_multi(vec![(1, 100), (2, 201)]).unwrap();
+        assert_eq!(vec![None, Some(200)], previous);
+        assert_eq!(201, c.cache_get(&2).unwrap().unwrap());
+        assert!(c.cache_replace_multi(vec![]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn replace_multi_chunked() {
+        let c: RedisCache<u32, String> = RedisCache::new(
+            format!("{}-redis-cache-test-replace-multi-chunked", now_millis()),
+            10,
+        )
+        .set_chunked_storage(8)
+        .set_max_keys(10)
+        .build()
+        .unwrap();
+        let long = "a".repeat(100);
+
+        assert!(c.cache_set(1, long.clone()).unwrap().is_none());
+        assert!(c.cache_set(2, long.clone()).unwrap().is_none());
+        let entries = vec![
+            (1, "b".to_string()),
+            (2, long.clone() + "c"),
+            (3, long.clone()),
+        ];
+        let previous = c.cache_replace_multi(entries).unwrap();
+        assert_eq!(vec![Some(long.clone()), Some(long.clone()), None], previous);
+        assert_eq!(Some("b".to_string()), c.cache_get(&1).unwrap());
+        assert_eq!(Some(long.clone() + "c"), c.cache_get(&2).unwrap());
+        assert_eq!(Some(long), c.cache_get(&3).unwrap());
+        assert_eq!(3, c.cache_len().unwrap());
+    }
+
+    #[test]
+    fn warm() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-warm", now_millis()), 10)
+                .set_batch_chunk_size(3)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_set(2, 1).unwrap().is_none());
+        assert_eq!(10, c.warm((0..10).map(|i| (i, i * 100))).unwrap());
+        assert_eq!(Some(200), c.cache_get(&2).unwrap());
+        assert_eq!(Some(900), c.cache_get(&9).unwrap());
+        assert!(c.cache_ttl(&9).unwrap().unwrap() <= 10);
+        assert_eq!(0, c.warm(std::iter::empty()).unwrap());
+    }
+
+    #[test]
+    fn warm_chunked() {
+        let c: RedisCache<u32, String> = RedisCache::new(
+            format!("{}-redis-cache-test-warm-chunked", now_millis()),
+            10,
+        )
+        .set_chunked_storage(8)
+        .set_max_keys(3)
+        .build()
+        .unwrap();
+        let long = "a".repeat(100);
+
+        assert!(c.cache_set(1, long.clone()).unwrap().is_none());
+        let entries = vec![
+            (1, "b".to_string()),
+            (2, long.clone()),
+            (3, "c".to_string()),
+        ];
+        assert_eq!(3, c.warm(entries).unwrap());
+        assert_eq!(Some("b".to_string()), c.cache_get(&1).unwrap());
+        assert_eq!(Some(long), c.cache_get(&2).unwrap());
+        let mut conn = c.connection().unwrap();
+        let part_exists: bool = redis::cmd("EXISTS")
+            .arg(chunk_key(&c.generate_key(&1), 0))
+            .query(&mut *conn)
+            .unwrap();
+        assert!(!part_exists);
+        drop(conn);
+        assert_eq!(3, c.cache_len().unwrap());
+    }
+
+    #[cfg(feature = "chaos")]
+    #[test]
+    fn fault_injector() {
+        use std::sync::Arc;
+
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-chaos", now_millis()), 10)
+                .set_fault_injector(Arc::new(|op, _key| match op {
+                    CacheOp::Get => Some(RedisCacheError::WriteWaitTimeout {
+                        acked: 0,
+                        required: 1,
+                    }),
+                    _ => None,
+                }))
+                .build()
+                .unwrap();
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert!(matches!(
+            c.cache_get(&1),
+            Err(RedisCacheError::WriteWaitTimeout { .. })
+        ));
+        assert_eq!(1, c.cache_stats().errors);
+        assert_eq!(100, c.cache_remove(&1).unwrap().unwrap());
+    }
+
+    #[test]
+    fn get_detailed() {
+        let prefix = format!("{}-redis-cache-test-get-detailed", now_millis());
+        let c: RedisCache<u32, u32> = RedisCache::new(&prefix, 10).build().unwrap();
+        let s: RedisCache<u32, String> = RedisCache::new(&prefix, 10).build().unwrap();
+
+        assert!(matches!(
+            c.cache_get_detailed(&1).unwrap(),
+            GetOutcome::Miss
+        ));
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(100, c.cache_get_detailed(&1).unwrap().into_hit().unwrap());
+
+        assert!(s
+            .cache_set(2, "not a number".to_string())
+            .unwrap()
+            .is_none());
+        assert!(matches!(
+            c.cache_get_detailed(&2).unwrap(),
+            GetOutcome::Corrupt(RedisCacheError::CacheDeserializationError { .. })
+        ));
+        assert_eq!(1, c.cache_stats().errors);
+    }
+
+    #[test]
+    fn get_or_default() {
+        let prefix = format!("{}-redis-cache-test-get-or-default", now_millis());
+        let c: RedisCache<u32, u32> = RedisCache::new(&prefix, 10).build().unwrap();
+        let s: RedisCache<u32, String> = RedisCache::new(&prefix, 10).build().unwrap();
+
+        assert_eq!(7, c.cache_get_or_default(&1, 7).unwrap());
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(100, c.cache_get_or_default(&1, 7).unwrap());
+
+        assert!(s
+            .cache_set(2, "not a number".to_string())
+            .unwrap()
+            .is_none());
+        assert_eq!(7, c.cache_get_or_default(&2, 7).unwrap());
+        assert!(!c.cache_exists(&2).unwrap());
+    }
+
+    #[test]
+    fn negative_entries() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-negative", now_millis()), 10)
+                .set_negative_lifespan(2)
+                .build()
+                .unwrap();
+
+        // negative entries don't count as cached values, so read their TTL directly
+        let negative_ttl = |key: u32| -> i64 {
+            let mut conn = c.connection().unwrap();
+            redis::cmd("TTL")
+                .arg(c.generate_key(&key))
+                .query(&mut *conn)
+                .unwrap()
+        };
+        c.cache_set_miss(1).unwrap();
+        assert!(matches!(
+            c.cache_get_detailed(&1).unwrap(),
+            GetOutcome::Negative
+        ));
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(!c.cache_exists(&1).unwrap());
+        assert!(c.cache_ttl(&1).unwrap().is_none());
+        assert!(negative_ttl(1) <= 2);
+
+        c.cache_set_miss_with_lifespan(2, 5).unwrap();
+        assert!(negative_ttl(2) > 2);
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(100, c.cache_get_detailed(&1).unwrap().into_hit().unwrap());
+        assert!(c.cache_ttl(&1).unwrap().unwrap() > 2);
+    }
+
+    #[test]
+    fn pipeline() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-pipeline", now_millis()), 10)
+                .build()
+                .unwrap();
+        assert!(c.pipeline().execute().unwrap().is_empty());
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+
+        let replies = c
+            .pipeline()
+            .get(&1)
+            .set(2, 200)
+            .get(&2)
+            .remove(&1)
+            .get(&1)
+            .execute()
+            .unwrap();
+        assert_eq!(
+            vec![
+                PipelineReply::Get(Some(100)),
+                PipelineReply::Set(None),
+                PipelineReply::Get(Some(200)),
+                PipelineReply::Remove(Some(100)),
+                PipelineReply::Get(None),
+            ],
+            replies
+        );
+    }
+
+    #[test]
+    fn pipeline_set_hooks() {
+        let c: RedisCache<u32, String> = RedisCache::new(
+            format!("{}-redis-cache-test-pipeline-hooks", now_millis()),
+            10,
+        )
+        .set_max_keys(10)
+        .set_track_recency(true)
+        .set_chunked_storage(8)
+        .set_ttl_fn(Arc::new(|val: &String| val.len() as u64 * 10))
+        .build()
+        .unwrap();
+        let long = "a".repeat(20);
+        assert!(c.cache_set(1, long.clone()).unwrap().is_none());
+
+        let replies = c
+            .pipeline()
+            .get(&1)
+            .set(2, "b".to_string())
+            .set(3, long.clone())
+            .set(1, "c".to_string())
+            .execute()
+            .unwrap();
+        assert_eq!(
+            vec![
+                PipelineReply::Get(Some(long.clone())),
+                PipelineReply::Set(None),
+                PipelineReply::Set(None),
+                PipelineReply::Set(Some(long.clone())),
+            ],
+            replies
+        );
+        assert_eq!(Some(long), c.cache_get(&3).unwrap());
+        assert_eq!(Some("c".to_string()), c.cache_get(&1).unwrap());
+        assert!(c.cache_ttl(&2).unwrap().unwrap() <= 10);
+        assert_eq!(3, c.cache_len().unwrap());
+        assert_eq!(3, c.cache_recent(10).unwrap().len());
+    }
+
+    #[test]
+    fn pipeline_raw_replies() {
+        let reply = vec![redis::Value::Nil, redis::Value::Data(b"v".to_vec())];
+        assert_eq!(
+            vec![None, Some(b"v".to_vec())],
+            QueuedOps::raw_replies(reply.clone(), None).unwrap()
+        );
+        let mut waited = reply.clone();
+        waited.push(redis::Value::Int(1));
+        assert_eq!(
+            2,
+            QueuedOps::raw_replies(waited.clone(), Some(1))
+                .unwrap()
+                .len()
+        );
+        assert!(matches!(
+            QueuedOps::raw_replies(waited, Some(2)),
+            Err(RedisCacheError::WriteWaitTimeout {
+                acked: 1,
+                required: 2
+            })
+        ));
+
+        let mut queued = QueuedOps::new();
+        assert_eq!(None, queued.wait(Some((1, Duration::from_millis(10)))));
+        queued.set("key", b"v".to_vec(), 10);
+        assert_eq!(None, queued.wait(None));
+        assert_eq!(Some(1), queued.wait(Some((1, Duration::from_millis(10)))));
+    }
+
+    #[test]
+    fn soft_remove() {
+        let c: RedisCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-soft-remove", now_millis()), 10)
+                .set_soft_remove(2)
+                .build()
+                .unwrap();
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_remove(&1).unwrap());
+        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(c.cache_remove(&1).unwrap().is_none());
+        assert!(matches!(
+            c.cache_get_detailed(&1).unwrap(),
+            GetOutcome::Removed(100)
+        ));
+        assert!(!c.cache_exists(&1).unwrap());
+        assert!(c.cache_ttl(&1).unwrap().is_none());
+
+        // setting a soft-removed key revives it
+        assert!(c.cache_set(1, 200).unwrap().is_none());
+        assert_eq!(Some(200), c.cache_get(&1).unwrap());
+
+        c.cache_set_miss(2).unwrap();
+        assert!(c.cache_remove(&2).unwrap().is_none());
+        assert!(!c.cache_exists(&2).unwrap());
+
+        sleep(Duration::new(3, 0));
+        assert_eq!(Some(200), c.cache_get(&1).unwrap());
+    }
+
+    #[test]
+    fn discover_prefixes() {
+        let prefix = format!("{}-redis-cache-test-discover", now_millis());
+        let c: RedisCache<u32, u32> = RedisCache::new(&prefix, 10).build().unwrap();
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+
+        let prefixes = RedisCache::<u32, u32>::discover_prefixes(&c.connection_string()).unwrap();
+        assert!(prefixes.contains(&prefix));
+    }
+
+    #[test]
+    fn codec() {
+        /// Tags values with the schema version they were encoded with
+        struct VersionedCodec {
+            version: u32,
+        }
+
+        impl ValueCodec<u32> for VersionedCodec {
+            fn encode(&self, value: &u32) -> Result<String, RedisCacheError> {
+                Ok(format!("v{}/{}", self.version, value))
+            }
+
+            fn decode(&self, encoded: &str) -> Result<u32, RedisCacheError> {
+                let expected = format!("v{}/", self.version);
+                encoded
+                    .strip_prefix(&expected)
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| RedisCacheError::CodecError {
+                        error: format!("unexpected encoding {:?}", encoded).into(),
+                    })
+            }
+        }
+
+        let prefix = format!("{}-redis-cache-test-codec", now_millis());
+        let c: RedisCache<u32, u32> = RedisCache::new(&prefix, 10)
+            .set_codec(Arc::new(VersionedCodec { version: 2 }))
+            .build()
+            .unwrap();
+        let raw: RedisCache<u32, String> = RedisCache::new(&prefix, 10).build().unwrap();
+        let old: RedisCache<u32, u32> = RedisCache::new(&prefix, 10)
+            .set_codec(Arc::new(VersionedCodec { version: 1 }))
+            .build()
+            .unwrap();
+
+        assert!(c.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+        assert_eq!(Some("v2/100".to_string()), raw.cache_get(&1).unwrap());
+        assert!(matches!(
+            old.cache_get(&1),
+            Err(RedisCacheError::CodecError { .. })
+        ));
+    }
+
+    #[test]
+    fn fallback_codec() {
+        struct PrefixedCodec;
+
+        impl ValueCodec<u32> for PrefixedCodec {
+            fn encode(&self, value: &u32) -> Result<String, RedisCacheError> {
+                Ok(format!("p/{}", value))
+            }
+
+            fn decode(&self, encoded: &str) -> Result<u32, RedisCacheError> {
+                encoded
+                    .strip_prefix("p/")
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| RedisCacheError::CodecError {
+                        error: format!("unexpected encoding {:?}", encoded).into(),
+                    })
+            }
+        }
+
+        let prefix = format!("{}-redis-cache-test-fallback-codec", now_millis());
+        let json: RedisCache<u32, u32> = RedisCache::new(&prefix, 10).build().unwrap();
+        let raw: RedisCache<u32, String> = RedisCache::new(&prefix, 10).build().unwrap();
+        let c: RedisCache<u32, u32> = RedisCache::new(&prefix, 10)
+            .set_codec(Arc::new(PrefixedCodec))
+            .set_json_fallback()
+            .build()
+            .unwrap();
+
+        assert!(json.cache_set(1, 100).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+        // read with the fallback, so rewritten in the codec's format
+        assert_eq!(Some("p/100".to_string()), raw.cache_get(&1).unwrap());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+
+        // rewrites keep the lifespan recorded with the old entry
+        assert!(json.cache_set_with_lifespan(2, 200, 5).unwrap().is_none());
+        assert_eq!(Some(200), c.cache_get(&2).unwrap());
+        assert_eq!(Some("p/200".to_string()), raw.cache_get(&2).unwrap());
+        assert_eq!(Some(5), c.cache_original_lifespan(&2).unwrap());
+        assert!(c.cache_ttl(&2).unwrap().unwrap() <= 5);
+    }
+
+    #[test]
+    fn tagged_codec() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct Circle {
+            radius: u32,
+        }
 
-        async fn create_multiplexed_connection(
-            &self,
-        ) -> Result<redis::aio::MultiplexedConnection, RedisCacheBuildError> {
-            let s = self.connection_string()?;
-            let client = redis::Client::open(s)?;
-            let conn = client.get_multiplexed_async_connection().await?;
-            Ok(conn)
+        #[derive(Debug, PartialEq)]
+        enum Shape {
+            Circle(Circle),
+            Square(u32),
+            Point,
         }
 
-        pub async fn build(self) -> Result<AsyncRedisCache<K, V>, RedisCacheBuildError> {
-            Ok(AsyncRedisCache {
-                seconds: self.seconds,
-                refresh: self.refresh,
-                connection_string: self.connection_string()?,
-                multiplexed_connection: self.create_multiplexed_connection().await?,
-                prefix: self.prefix,
-                _phantom_k: self._phantom_k,
-                _phantom_v: self._phantom_v,
-            })
-        }
+        let codec = TaggedCodec::new()
+            .variant(
+                "circle",
+                |s| match s {
+                    Shape::Circle(c) => Some(c),
+                    _ => None,
+                },
+                Shape::Circle,
+            )
+            .variant(
+                "square",
+                |s| match s {
+                    Shape::Square(side) => Some(side),
+                    _ => None,
+                },
+                Shape::Square,
+            );
+
+        let circle = codec.encode(&Shape::Circle(Circle { radius: 2 })).unwrap();
+        assert_eq!(r#"{"tag":"circle","value":{"radius":2}}"#, circle);
+        assert_eq!(
+            Shape::Circle(Circle { radius: 2 }),
+            codec.decode(&circle).unwrap()
+        );
+        let square = codec.encode(&Shape::Square(3)).unwrap();
+        assert_eq!(Shape::Square(3), codec.decode(&square).unwrap());
+
+        assert!(matches!(
+            codec.encode(&Shape::Point),
+            Err(RedisCacheError::CodecError { .. })
+        ));
+        assert!(matches!(
+            codec.decode(r#"{"tag":"triangle","value":3}"#),
+            Err(RedisCacheError::CodecError { .. })
+        ));
+        assert!(matches!(
+            codec.decode(r#"{"tag":"square","value":"3"}"#),
+            Err(RedisCacheError::CodecError { .. })
+        ));
     }
 
-    /// Cache store backed by redis
-    ///
-    /// Values have a ttl applied and enforced by redis.
-    pub struct AsyncRedisCache<K, V> {
-        pub(super) seconds: u64,
-        pub(super) refresh: bool,
-        pub(super) prefix: String,
-        connection_string: String,
-        multiplexed_connection: redis::aio::MultiplexedConnection,
-        _phantom_k: PhantomData<K>,
-        _phantom_v: PhantomData<V>,
+    #[test]
+    fn validate_roundtrip() {
+        let c: RedisCache<u32, Option<Option<u32>>> = RedisCache::new(
+            format!("{}-redis-cache-test-validate-roundtrip", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+
+        assert!(c.validate_roundtrip(&Some(Some(1))).is_ok());
+        assert!(c.validate_roundtrip_eq(&Some(Some(1))).unwrap());
+        // `Some(None)` and `None` are both stored as `null`
+        assert!(c.validate_roundtrip(&Some(None)).is_ok());
+        assert!(!c.validate_roundtrip_eq(&Some(None)).unwrap());
+
+        let floats: RedisCache<u32, f64> = RedisCache::new(
+            format!(
+                "{}-redis-cache-test-validate-roundtrip-floats",
+                now_millis()
+            ),
+            10,
+        )
+        .build()
+        .unwrap();
+        assert!(floats.validate_roundtrip(&1.5).is_ok());
+        assert!(matches!(
+            floats.validate_roundtrip(&f64::NAN),
+            Err(RedisCacheError::CacheDeserializationError { .. })
+        ));
     }
 
-    impl<K, V> AsyncRedisCache<K, V>
-    where
-        K: Display + Send + Sync,
-        V: Serialize + DeserializeOwned + Send + Sync,
-    {
-        #[allow(clippy::new_ret_no_self)]
-        /// Initialize an `AsyncRedisCacheBuilder`
-        pub fn new<S: AsRef<str>>(prefix: S, seconds: u64) -> AsyncRedisCacheBuilder<K, V> {
-            AsyncRedisCacheBuilder::new(prefix, seconds)
-        }
+    #[test]
+    fn expire_option_support() {
+        let supports_expire_options =
+            |info| version_supports(parse_server_version(info), EXPIRE_OPTIONS_VERSION);
+        assert!(supports_expire_options(
+            "# Server\r\nredis_version:7.0.11\r\nredis_mode:standalone\r\n"
+        ));
+        assert!(!supports_expire_options(
+            "# Server\r\nredis_version:6.2.6\r\n"
+        ));
+        assert!(!supports_expire_options("# Server\r\n"));
+    }
 
-        fn generate_key(&self, key: &K) -> String {
-            format!("{}{}", self.prefix, key)
+    #[test]
+    fn server_version() {
+        assert_eq!(
+            Some((6, 2)),
+            parse_server_version("# Server\r\nredis_version:6.2.6\r\n")
+        );
+        assert_eq!(Some((7, 0)), parse_server_version("redis_version:7\r\n"));
+        assert_eq!(None, parse_server_version("# Server\r\n"));
+        assert_eq!(None, parse_server_version("redis_version:unstable\r\n"));
+
+        assert!(version_supports(Some((6, 0)), KEEPTTL_VERSION));
+        assert!(!version_supports(Some((5, 0)), KEEPTTL_VERSION));
+        assert!(!version_supports(None, KEEPTTL_VERSION));
+
+        assert!(require_version(Some((4, 0)), "MEMORY USAGE", MEMORY_USAGE_VERSION).is_ok());
+        assert!(require_version(None, "MEMORY USAGE", MEMORY_USAGE_VERSION).is_ok());
+        let error = require_version(Some((3, 2)), "MEMORY USAGE", MEMORY_USAGE_VERSION);
+        assert_eq!(
+            "MEMORY USAGE requires redis 4.0 or later, the server runs 3.2",
+            error.unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn refresh_expire_option() {
+        let c: RedisCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-expire-option", now_millis()),
+            10,
+        )
+        .set_refresh(true)
+        .set_refresh_expire_option(ExpireOption::Gt)
+        .build()
+        .unwrap();
+
+        assert!(c.cache_set_with_lifespan(1, 100, 60).unwrap().is_none());
+        assert_eq!(Some(100), c.cache_get(&1).unwrap());
+        let ttl = c.cache_ttl(&1).unwrap().unwrap();
+        if c.refresh_expire_option.is_some() {
+            assert!(ttl > 10);
+        } else {
+            assert!(ttl <= 10);
         }
+    }
 
-        /// Return the redis connection string used
-        pub fn connection_string(&self) -> String {
-            self.connection_string.clone()
+    #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+    #[test]
+    fn compression_roundtrip() {
+        let json = br#"{"value":"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"}"#;
+        assert_eq!(&json[..], &*decompress(json).unwrap());
+
+        #[cfg(feature = "redis_zstd")]
+        {
+            let compressed = compress(CompressionKind::Zstd, json).unwrap();
+            assert!(compressed.starts_with(ZSTD_MAGIC));
+            assert_eq!(&json[..], &*decompress(&compressed).unwrap());
+        }
+        #[cfg(feature = "redis_gzip")]
+        {
+            let compressed = compress(CompressionKind::Gzip, json).unwrap();
+            assert!(compressed.starts_with(GZIP_MAGIC));
+            assert_eq!(&json[..], &*decompress(&compressed).unwrap());
         }
     }
 
-    #[async_trait]
-    impl<'de, K, V> IOCachedAsync<K, V> for AsyncRedisCache<K, V>
-    where
-        K: Display + Send + Sync,
-        V: Serialize + DeserializeOwned + Send + Sync,
-    {
-        type Error = RedisCacheError;
+    #[cfg(feature = "redis_zstd")]
+    #[test]
+    fn compression_min_ratio() {
+        let codec = |min_ratio: Option<f32>| Codec::<String> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum: false,
+            compression: Some(CompressionKind::Zstd),
+            compression_min_ratio: min_ratio,
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let short = "a".to_string();
+        let long = "a".repeat(1000);
 
-        /// Get a cached value
-        async fn cache_get(&self, key: &K) -> Result<Option<V>, Self::Error> {
-            let mut conn = self.multiplexed_connection.clone();
-            let mut pipe = redis::pipe();
-            let key = self.generate_key(key);
+        let always = codec(None);
+        assert!(always
+            .serialize(&short, None)
+            .unwrap()
+            .starts_with(ZSTD_MAGIC));
 
-            pipe.get(key.clone());
-            if self.refresh {
-                pipe.expire(key, self.seconds as usize).ignore();
+        let smaller = codec(Some(1.0));
+        let raw = smaller.serialize(&short, None).unwrap();
+        assert!(raw.starts_with(b"{"));
+        assert_eq!(short, smaller.deserialize(&raw).unwrap());
+        let raw = smaller.serialize(&long, None).unwrap();
+        assert!(raw.starts_with(ZSTD_MAGIC));
+        assert_eq!(long, smaller.deserialize(&raw).unwrap());
+
+        let strict = codec(Some(1000.0));
+        assert!(strict.serialize(&long, None).unwrap().starts_with(b"{"));
+    }
+
+    #[test]
+    fn fallback_format() {
+        struct PrefixedCodec;
+
+        impl ValueCodec<u32> for PrefixedCodec {
+            fn encode(&self, value: &u32) -> Result<String, RedisCacheError> {
+                Ok(format!("p/{}", value))
             }
-            let res: (Option<String>,) = pipe.query_async(&mut conn).await?;
-            match res.0 {
-                None => Ok(None),
-                Some(s) => {
-                    let v: CachedRedisValue<V> = serde_json::from_str(&s).map_err(|e| {
-                        RedisCacheError::CacheDeserializationError {
-                            cached_value: s,
-                            error: e,
-                        }
-                    })?;
-                    Ok(Some(v.value))
-                }
+
+            fn decode(&self, encoded: &str) -> Result<u32, RedisCacheError> {
+                encoded
+                    .strip_prefix("p/")
+                    .and_then(|v| v.parse().ok())
+                    .ok_or_else(|| RedisCacheError::CodecError {
+                        error: format!("unexpected encoding {:?}", encoded).into(),
+                    })
             }
         }
 
-        /// Set a cached value
-        async fn cache_set(&self, key: K, val: V) -> Result<Option<V>, Self::Error> {
-            let mut conn = self.multiplexed_connection.clone();
-            let mut pipe = redis::pipe();
-            let key = self.generate_key(&key);
+        let codec =
+            |custom: Option<Arc<dyn ValueCodec<u32>>>,
+             fallback: Option<Option<Arc<dyn ValueCodec<u32>>>>| Codec::<u32> {
+                custom,
+                fallback,
+                json: JsonOptions::default(),
+                max_read_bytes: None,
+                checksum: false,
+                #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                compression: None,
+                #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+                compression_min_ratio: None,
+                #[cfg(feature = "redis_zstd")]
+                dictionary: None,
+                #[cfg(feature = "redis_encryption")]
+                cipher: None,
+            };
+        let json = codec(None, None);
+        let prefixed = codec(Some(Arc::new(PrefixedCodec)), None);
+        let json_raw = json.serialize(&7, None).unwrap();
+        let prefixed_raw = prefixed.serialize(&7, None).unwrap();
+        assert!(prefixed.deserialize(&json_raw).is_err());
 
-            let val = CachedRedisValue { value: val };
-            pipe.get(key.clone());
-            pipe.set_ex::<String, String>(
-                key,
-                serde_json::to_string(&val)
-                    .map_err(|e| RedisCacheError::CacheSerializationError { error: e })?,
-                self.seconds as usize,
-            )
-            .ignore();
+        let migrating = codec(Some(Arc::new(PrefixedCodec)), Some(None));
+        assert_eq!(
+            7,
+            migrating
+                .deserialize_any(&prefixed_raw)
+                .map(|(v, _)| v)
+                .unwrap()
+        );
+        assert!(!migrating.deserialize_any(&prefixed_raw).unwrap().1);
+        assert!(migrating.deserialize_any(&json_raw).unwrap().1);
+        assert_eq!(
+            Some((7, true)),
+            migrating.entry_any("key", &json_raw).unwrap()
+        );
+        assert_eq!(
+            None,
+            migrating
+                .entry_any("key", NEGATIVE_ENTRY.as_bytes())
+                .unwrap()
+        );
 
-            let res: (Option<String>,) = pipe.query_async(&mut conn).await?;
-            match res.0 {
-                None => Ok(None),
-                Some(s) => {
-                    let v: CachedRedisValue<V> = serde_json::from_str(&s).map_err(|e| {
-                        RedisCacheError::CacheDeserializationError {
-                            cached_value: s,
-                            error: e,
-                        }
-                    })?;
-                    Ok(Some(v.value))
-                }
-            }
-        }
+        let back = codec(None, Some(Some(Arc::new(PrefixedCodec))));
+        assert_eq!(
+            Some((7, true)),
+            back.entry_any("key", &prefixed_raw).unwrap()
+        );
+        assert!(matches!(
+            back.entry_any("key", br#"{"value":"x"}"#),
+            Err(RedisCacheError::CacheDeserializationError { .. })
+        ));
+    }
 
-        /// Remove a cached value
-        async fn cache_remove(&self, key: &K) -> Result<Option<V>, Self::Error> {
-            let mut conn = self.multiplexed_connection.clone();
-            let mut pipe = redis::pipe();
-            let key = self.generate_key(key);
+    #[test]
+    fn max_read_bytes() {
+        let codec = Codec::<String> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: Some(20),
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let small = codec.serialize(&"small".to_string(), None).unwrap();
+        assert_eq!("small", codec.deserialize(&small).unwrap());
 
-            pipe.get(key.clone());
-            pipe.del::<String>(key).ignore();
-            let res: (Option<String>,) = pipe.query_async(&mut conn).await?;
-            match res.0 {
-                None => Ok(None),
-                Some(s) => {
-                    let v: CachedRedisValue<V> = serde_json::from_str(&s).map_err(|e| {
-                        RedisCacheError::CacheDeserializationError {
-                            cached_value: s,
-                            error: e,
-                        }
-                    })?;
-                    Ok(Some(v.value))
-                }
-            }
-        }
+        let large = codec.serialize(&"a".repeat(100), None).unwrap();
+        assert!(matches!(
+            codec.deserialize(&large),
+            Err(RedisCacheError::ValueTooLarge { len: 112, max: 20 })
+        ));
+        assert!(matches!(
+            codec.outcome("key", Some(large)),
+            GetOutcome::Corrupt(RedisCacheError::ValueTooLarge { .. })
+        ));
+    }
 
-        /// Set the flag to control whether cache hits refresh the ttl of cached values, returns the old flag value
-        fn cache_set_refresh(&mut self, refresh: bool) -> bool {
-            let old = self.refresh;
-            self.refresh = refresh;
-            old
-        }
+    #[test]
+    fn invalid_encoding() {
+        let codec = Codec::<String> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let raw = b"{\"value\":\"\xff\xfe\"}".to_vec();
+        assert!(matches!(
+            codec.entry("prefix:1", raw.clone()),
+            Err(RedisCacheError::InvalidEncoding { ref key }) if key == "prefix:1"
+        ));
+        assert!(matches!(
+            codec.value_bytes("prefix:1", raw.clone()),
+            Err(RedisCacheError::InvalidEncoding { .. })
+        ));
+        assert!(matches!(
+            codec.outcome("prefix:1", Some(raw)),
+            GetOutcome::Corrupt(RedisCacheError::InvalidEncoding { ref key }) if key == "prefix:1"
+        ));
 
-        /// Return the lifespan of cached values (time to eviction)
-        fn cache_lifespan(&self) -> Option<u64> {
-            Some(self.seconds)
-        }
+        let plain = codec.serialize(&"plain".to_string(), None).unwrap();
+        assert_eq!(
+            Some("plain".to_string()),
+            codec.entry("prefix:1", plain).unwrap()
+        );
+    }
 
-        /// Set the lifespan of cached values, returns the old value
-        fn cache_set_lifespan(&mut self, seconds: u64) -> Option<u64> {
-            let old = self.seconds;
-            self.seconds = seconds;
-            Some(old)
+    #[test]
+    fn checksum() {
+        let codec = |checksum: bool| Codec::<String> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let value = "expensive".to_string();
+        let mut raw = codec(true).serialize(&value, None).unwrap();
+        assert!(raw.starts_with(CHECKSUM_MAGIC));
+        assert_eq!(value, codec(true).deserialize(&raw).unwrap());
+        assert_eq!(value, codec(false).deserialize(&raw).unwrap());
+
+        let plain = codec(false).serialize(&value, None).unwrap();
+        assert_eq!(value, codec(true).deserialize(&plain).unwrap());
+
+        let last = raw.len() - 3;
+        raw[last] ^= 1;
+        assert!(matches!(
+            codec(true).deserialize(&raw),
+            Err(RedisCacheError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            codec(true).deserialize(CHECKSUM_MAGIC),
+            Err(RedisCacheError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn empty_values() {
+        let codec = |non_finite_floats: NonFiniteFloats| Codec::<Option<u32>> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions { non_finite_floats },
+            max_read_bytes: None,
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        for codec in &[codec(NonFiniteFloats::Null), codec(NonFiniteFloats::String)] {
+            let none = codec.serialize(&None, Some(10)).unwrap();
+            assert_eq!(Some(None), codec.entry("key", none.clone()).unwrap());
+            assert!(matches!(
+                codec.outcome("key", Some(none)),
+                GetOutcome::Hit(None)
+            ));
+            let some = codec.serialize(&Some(3), None).unwrap();
+            assert_eq!(Some(Some(3)), codec.entry("key", some).unwrap());
+            assert_eq!(
+                None,
+                codec
+                    .entry("key", NEGATIVE_ENTRY.as_bytes().to_vec())
+                    .unwrap()
+            );
         }
+
+        let empty = Codec::<Vec<u32>> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let raw = empty.serialize(&vec![], None).unwrap();
+        assert_eq!(Some(vec![]), empty.entry("key", raw).unwrap());
     }
 
-    #[cfg(test)]
-    mod tests {
-        use super::*;
-        use std::thread::sleep;
-        use std::time::Duration;
+    #[test]
+    fn json_non_finite_floats() {
+        let codec = |non_finite_floats: NonFiniteFloats| Codec::<Vec<Option<f64>>> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions { non_finite_floats },
+            max_read_bytes: None,
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let value = vec![Some(1.5), Some(f64::NAN), Some(f64::NEG_INFINITY), None];
 
-        fn now_millis() -> u128 {
-            std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis()
-        }
+        let null = codec(NonFiniteFloats::Null);
+        let raw = null.serialize(&value, None).unwrap();
+        assert_eq!(br#"{"value":[1.5,null,null,null]}"#, &raw[..]);
+        assert_eq!(
+            vec![Some(1.5), None, None, None],
+            null.deserialize(&raw).unwrap()
+        );
 
-        #[async_std::test]
-        async fn test_async_redis_cache() {
-            let mut c: AsyncRedisCache<u32, u32> =
-                AsyncRedisCache::new(format!("{}:async-redis-cache-test", now_millis()), 2)
-                    .build()
-                    .await
-                    .unwrap();
+        let strings = codec(NonFiniteFloats::String);
+        let raw = strings.serialize(&value, None).unwrap();
+        assert_eq!(br#"{"value":[1.5,"NaN","-Infinity",null]}"#, &raw[..]);
+        let read = strings.deserialize(&raw).unwrap();
+        assert!(read[1].unwrap().is_nan());
+        assert_eq!(Some(f64::NEG_INFINITY), read[2]);
+        assert_eq!(None, read[3]);
 
-            assert!(c.cache_get(&1).await.unwrap().is_none());
+        let floats = Codec::<Vec<f64>> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let raw = floats.serialize(&vec![2.0, f64::INFINITY], None).unwrap();
+        assert!(floats.deserialize(&raw).is_err());
+        let nan = Codec {
+            json: JsonOptions {
+                non_finite_floats: NonFiniteFloats::NullAsNan,
+            },
+            ..floats
+        };
+        let read = nan.deserialize(&raw).unwrap();
+        assert_eq!(2.0, read[0]);
+        assert!(read[1].is_nan());
+    }
 
-            assert!(c.cache_set(1, 100).await.unwrap().is_none());
-            assert!(c.cache_get(&1).await.unwrap().is_some());
+    #[cfg(feature = "redis_zstd")]
+    #[test]
+    fn compression_dictionary_roundtrip() {
+        let codec = |dictionary: Option<&[u8]>| Codec::<String> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum: false,
+            compression: Some(CompressionKind::Zstd),
+            compression_min_ratio: None,
+            dictionary: dictionary.map(ZstdDictionary::new),
+            #[cfg(feature = "redis_encryption")]
+            cipher: None,
+        };
+        let dictionary = br#"{"value":"user-profile-","lifespan":3600}"#;
+        let with_dictionary = codec(Some(dictionary));
+        let without_dictionary = codec(None);
+        let value = "user-profile-42".to_string();
 
-            sleep(Duration::new(2, 500000));
-            assert!(c.cache_get(&1).await.unwrap().is_none());
+        let raw = with_dictionary.serialize(&value, Some(10)).unwrap();
+        assert!(raw.starts_with(ZSTD_DICT_MAGIC));
+        assert_eq!(value, with_dictionary.deserialize(&raw).unwrap());
+        assert!(matches!(
+            without_dictionary.deserialize(&raw),
+            Err(RedisCacheError::CompressionError { .. })
+        ));
+        assert!(matches!(
+            codec(Some(b"another dictionary")).deserialize(&raw),
+            Err(RedisCacheError::CompressionError { .. })
+        ));
 
-            let old = c.cache_set_lifespan(1).unwrap();
-            assert_eq!(2, old);
-            assert!(c.cache_set(1, 100).await.unwrap().is_none());
-            assert!(c.cache_get(&1).await.unwrap().is_some());
+        let plain = without_dictionary.serialize(&value, Some(10)).unwrap();
+        assert!(plain.starts_with(ZSTD_MAGIC));
+        assert_eq!(value, with_dictionary.deserialize(&plain).unwrap());
+    }
 
-            sleep(Duration::new(1, 600000));
-            assert!(c.cache_get(&1).await.unwrap().is_none());
+    #[cfg(feature = "redis_encryption")]
+    #[test]
+    fn encryption_roundtrip() {
+        let codec = |key: [u8; 32]| Codec::<String> {
+            custom: None,
+            fallback: None,
+            json: JsonOptions::default(),
+            max_read_bytes: None,
+            checksum: false,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression: None,
+            #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+            compression_min_ratio: None,
+            #[cfg(feature = "redis_zstd")]
+            dictionary: None,
+            cipher: Some(aes_gcm::KeyInit::new(&key.into())),
+        };
+        let encrypting = codec([7; 32]);
+        let value = "secret".to_string();
 
-            c.cache_set_lifespan(10).unwrap();
-            assert!(c.cache_set(1, 100).await.unwrap().is_none());
-            assert!(c.cache_set(2, 100).await.unwrap().is_none());
-            assert_eq!(c.cache_get(&1).await.unwrap().unwrap(), 100);
-            assert_eq!(c.cache_get(&1).await.unwrap().unwrap(), 100);
+        let raw = encrypting.serialize(&value, Some(10)).unwrap();
+        assert!(raw.starts_with(ENCRYPTED_MAGIC));
+        assert!(!String::from_utf8_lossy(&raw).contains("secret"));
+        assert_ne!(raw, encrypting.serialize(&value, Some(10)).unwrap());
+        assert_eq!(value, encrypting.deserialize(&raw).unwrap());
+        assert_eq!(Some(10), encrypting.meta("key", &raw).unwrap().lifespan);
+
+        assert!(matches!(
+            codec([8; 32]).deserialize(&raw),
+            Err(RedisCacheError::EncryptionError)
+        ));
+        assert!(matches!(
+            encrypting.deserialize(ENCRYPTED_MAGIC),
+            Err(RedisCacheError::EncryptionError)
+        ));
+        let plain = br#"{"value":"plain"}"#;
+        assert_eq!("plain", encrypting.deserialize(plain).unwrap());
+    }
+
+    #[cfg(all(feature = "redis_zstd", feature = "redis_gzip"))]
+    #[test]
+    fn mixed_compression() {
+        let prefix = format!("{}-redis-cache-test-compression", now_millis());
+        let zstd: RedisCache<u32, String> = RedisCache::new(&prefix, 10)
+            .set_compression(CompressionKind::Zstd)
+            .build()
+            .unwrap();
+        let gzip: RedisCache<u32, String> = RedisCache::new(&prefix, 10)
+            .set_compression(CompressionKind::Gzip)
+            .set_soft_remove(10)
+            .build()
+            .unwrap();
+        let plain: RedisCache<u32, String> = RedisCache::new(&prefix, 10).build().unwrap();
+
+        assert!(zstd.cache_set(1, "zstd".to_string()).unwrap().is_none());
+        assert!(gzip.cache_set(2, "gzip".to_string()).unwrap().is_none());
+        assert!(plain.cache_set(3, "plain".to_string()).unwrap().is_none());
+        for c in &[&zstd, &gzip, &plain] {
+            assert_eq!(Some("zstd".to_string()), c.cache_get(&1).unwrap());
+            assert_eq!(Some("gzip".to_string()), c.cache_get(&2).unwrap());
+            assert_eq!(Some("plain".to_string()), c.cache_get(&3).unwrap());
         }
+        assert_eq!(Some(10), plain.cache_original_lifespan(&1).unwrap());
+
+        assert_eq!(Some("zstd".to_string()), gzip.cache_remove(&1).unwrap());
+        assert!(matches!(
+            plain.cache_get_detailed(&1).unwrap(),
+            GetOutcome::Removed(v) if v == "zstd"
+        ));
     }
-}
 
-#[cfg(all(
-    feature = "async",
-    any(feature = "redis_async_std", feature = "redis_tokio")
-))]
-pub use async_redis::AsyncRedisCache;
+    #[test]
+    fn list_cache() {
+        let c: RedisListCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-list", now_millis()), 10)
+                .build_list()
+                .unwrap();
 
-#[cfg(test)]
-/// Cache store tests
-mod tests {
-    use std::thread::sleep;
-    use std::time::Duration;
+        assert!(c.cache_range(&1, 0, -1).unwrap().is_empty());
+        assert_eq!(1, c.cache_push(1, 100).unwrap());
+        assert_eq!(2, c.cache_push(1, 200).unwrap());
+        assert_eq!(3, c.cache_push(1, 300).unwrap());
+        assert_eq!(vec![100, 200, 300], c.cache_range(&1, 0, -1).unwrap());
+        assert_eq!(vec![200, 300], c.cache_range(&1, -2, -1).unwrap());
 
-    use super::*;
+        assert!(c.cache_remove(&1).unwrap());
+        assert!(!c.cache_remove(&1).unwrap());
+    }
 
-    fn now_millis() -> u128 {
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
+    #[test]
+    fn list_cache_max_len() {
+        let c: RedisListCache<u32, u32> = RedisCache::new(
+            format!("{}-redis-cache-test-list-max-len", now_millis()),
+            10,
+        )
+        .set_max_list_len(2)
+        .build_list()
+        .unwrap();
+
+        assert_eq!(1, c.cache_push(1, 100).unwrap());
+        assert_eq!(2, c.cache_push(1, 200).unwrap());
+        assert_eq!(2, c.cache_push(1, 300).unwrap());
+        assert_eq!(vec![200, 300], c.cache_range(&1, 0, -1).unwrap());
     }
 
     #[test]
-    fn redis_cache() {
-        let mut c: RedisCache<u32, u32> =
-            RedisCache::new(format!("{}:redis-cache-test", now_millis()), 2)
-                .build()
+    fn stream_cache() {
+        let c: RedisStreamCache<u32, u32> =
+            RedisCache::new(format!("{}-redis-cache-test-stream", now_millis()), 10)
+                .set_max_stream_len(3)
+                .build_stream()
                 .unwrap();
 
-        assert!(c.cache_get(&1).unwrap().is_none());
-
-        assert!(c.cache_set(1, 100).unwrap().is_none());
-        assert!(c.cache_get(&1).unwrap().is_some());
+        assert!(c.cache_events(&1, 10).unwrap().is_empty());
+        let first = c.cache_append(1, 100).unwrap();
+        let second = c.cache_append(1, 200).unwrap();
+        assert!(first < second);
+        c.cache_append(1, 300).unwrap();
+        c.cache_append(1, 400).unwrap();
+        assert_eq!(vec![200, 300, 400], c.cache_events(&1, 10).unwrap());
+        assert_eq!(vec![300, 400], c.cache_events(&1, 2).unwrap());
 
-        sleep(Duration::new(2, 500000));
-        assert!(c.cache_get(&1).unwrap().is_none());
+        assert!(c.cache_remove(&1).unwrap());
+        assert!(!c.cache_remove(&1).unwrap());
+    }
 
-        let old = c.cache_set_lifespan(1).unwrap();
-        assert_eq!(2, old);
-        assert!(c.cache_set(1, 100).unwrap().is_none());
-        assert!(c.cache_get(&1).unwrap().is_some());
+    #[test]
+    fn invalid_prefix() {
+        let res: Result<RedisCache<u32, u32>, _> =
+            RedisCache::new("redis-cache-test:invalid-prefix", 10).build();
+        assert!(matches!(
+            res,
+            Err(RedisCacheBuildError::InvalidPrefix { separator: ':', .. })
+        ));
+    }
 
-        sleep(Duration::new(1, 600000));
-        assert!(c.cache_get(&1).unwrap().is_none());
+    #[test]
+    fn env_namespace() {
+        let res: Result<RedisCache<u32, u32>, _> =
+            RedisCache::new("redis-cache-test-env-namespace", 10)
+                .set_env_namespace_from("CACHED_REDIS_TEST_UNSET_ENV")
+                .build();
+        assert!(matches!(
+            res,
+            Err(RedisCacheBuildError::MissingEnvNamespace { .. })
+        ));
 
-        c.cache_set_lifespan(10).unwrap();
-        assert!(c.cache_set(1, 100).unwrap().is_none());
-        assert!(c.cache_set(2, 100).unwrap().is_none());
-        assert_eq!(c.cache_get(&1).unwrap().unwrap(), 100);
-        assert_eq!(c.cache_get(&1).unwrap().unwrap(), 100);
+        std::env::set_var("CACHED_REDIS_TEST_ENV", "staging");
+        let c: RedisCache<u32, u32> = RedisCache::new("redis-cache-test-env-namespace", 10)
+            .set_env_namespace_from("CACHED_REDIS_TEST_ENV")
+            .build()
+            .unwrap();
+        assert_eq!(
+            "cached-redis-store:staging:redis-cache-test-env-namespace:",
+            c.prefix
+        );
     }
 
     #[test]
-    fn remove() {
+    fn hash_tag() {
+        for tag in &["", "a{b", "a}b"] {
+            let res: Result<RedisCache<u32, u32>, _> =
+                RedisCache::new("redis-cache-test-hash-tag", 10)
+                    .set_hash_tag(tag)
+                    .build();
+            assert!(matches!(
+                res,
+                Err(RedisCacheBuildError::InvalidHashTag { .. })
+            ));
+        }
+
         let c: RedisCache<u32, u32> =
-            RedisCache::new(format!("{}:redis-cache-test-remove", now_millis()), 3600)
+            RedisCache::new(format!("{}-redis-cache-test-hash-tag", now_millis()), 10)
+                .set_hash_tag("users")
                 .build()
                 .unwrap();
+        assert!(c.generate_key(&1).starts_with("{users}cached-redis-store:"));
+        assert!(c.generate_key_in("other", &1).starts_with("{users}"));
+        assert_eq!(
+            vec![true, true],
+            c.cache_set_multi_nx(vec![(1, 10), (2, 20)]).unwrap()
+        );
+        assert_eq!(Some(20), c.cache_get(&2).unwrap());
+    }
 
-        assert!(c.cache_set(1, 100).unwrap().is_none());
-        assert!(c.cache_set(2, 200).unwrap().is_none());
-        assert!(c.cache_set(3, 300).unwrap().is_none());
+    #[test]
+    fn resilience_policy() {
+        let builder = RedisCache::<u32, u32>::new("redis-cache-test-resilience", 10);
+        assert!(!builder.ignore_set_errors);
+        assert!(!builder.fail_fast_on_exhaustion);
+        assert_eq!(ResiliencePolicy::STRICT, ResiliencePolicy::default());
 
-        assert_eq!(100, c.cache_remove(&1).unwrap().unwrap());
+        let builder = builder.set_resilience(ResiliencePolicy::BEST_EFFORT);
+        assert!(builder.ignore_set_errors);
+        assert!(builder.fail_fast_on_exhaustion);
+        let builder = builder.set_ignore_set_errors(false);
+        assert!(!builder.ignore_set_errors);
+        assert!(builder.fail_fast_on_exhaustion);
+
+        let mut policy = ResiliencePolicy::RESILIENT;
+        policy.fail_fast_on_exhaustion = true;
+        let builder = builder.set_resilience(policy);
+        assert!(builder.ignore_set_errors);
+        assert!(builder.fail_fast_on_exhaustion);
+    }
+
+    #[test]
+    fn build_config_warnings() {
+        assert!(config_warnings(60, 10, false, false, false).is_empty());
+        assert_eq!(
+            vec![
+                ConfigWarning::ZeroLifespan,
+                ConfigWarning::JitterExceedsLifespan {
+                    jitter: 5,
+                    lifespan: 0
+                },
+            ],
+            config_warnings(0, 5, false, false, false)
+        );
+        assert_eq!(
+            vec![ConfigWarning::RefreshDropsJitter],
+            config_warnings(60, 10, true, true, false)
+        );
+        assert_eq!(
+            vec![
+                ConfigWarning::ExpireOptionWithoutRefresh,
+                ConfigWarning::UnusedCompressionSettings,
+            ],
+            config_warnings(60, 0, false, true, true)
+        );
+
+        let builder = RedisCache::<u32, u32>::new("warnings", 2).set_lifespan_jitter(10);
+        assert_eq!(
+            vec![ConfigWarning::JitterExceedsLifespan {
+                jitter: 10,
+                lifespan: 2
+            }],
+            builder.config_warnings()
+        );
+        #[cfg(any(feature = "redis_zstd", feature = "redis_gzip"))]
+        assert_eq!(
+            vec![ConfigWarning::UnusedCompressionSettings],
+            RedisCache::<u32, u32>::new("warnings", 60)
+                .set_compression_min_ratio(1.0)
+                .config_warnings()
+        );
+    }
+
+    #[test]
+    fn hash_tagged_prefix() {
+        assert_eq!("prefix:", hash_tagged(None, "prefix:".to_string()));
+        assert_eq!(
+            "{tag}prefix:",
+            hash_tagged(Some("tag"), "prefix:".to_string())
+        );
+        assert!(validate_hash_tag("tag").is_ok());
+        assert!(validate_hash_tag("").is_err());
+        assert!(validate_hash_tag("{tag}").is_err());
+    }
+
+    #[test]
+    fn from_env() {
+        std::env::remove_var(ENV_PREFIX_KEY);
+        assert!(matches!(
+            RedisCacheBuilder::<u32, u32>::from_env(),
+            Err(RedisCacheBuildError::MissingEnvVar { .. })
+        ));
+
+        std::env::set_var(ENV_PREFIX_KEY, "redis-cache-test-from-env");
+        std::env::set_var(ENV_LIFESPAN_KEY, "ten");
+        assert!(matches!(
+            RedisCacheBuilder::<u32, u32>::from_env(),
+            Err(RedisCacheBuildError::InvalidEnvVar { .. })
+        ));
+
+        std::env::set_var(ENV_LIFESPAN_KEY, "10");
+        std::env::set_var(ENV_REFRESH_KEY, "true");
+        let builder = RedisCacheBuilder::<u32, u32>::from_env().unwrap();
+        assert_eq!("redis-cache-test-from-env", builder.prefix);
+        assert_eq!(10, builder.seconds);
+        assert!(builder.refresh);
+    }
+
+    #[test]
+    fn ignore_set_errors() {
+        use std::collections::HashMap;
+
+        // serde_json can't serialize maps with non-string keys
+        let mut unserializable = HashMap::new();
+        unserializable.insert((1, 2), 3);
+
+        let c: RedisCache<u32, HashMap<(u32, u32), u32>> = RedisCache::new(
+            format!("{}-redis-cache-test-ignore-set-errors", now_millis()),
+            10,
+        )
+        .build()
+        .unwrap();
+        assert!(matches!(
+            c.cache_set(1, unserializable.clone()),
+            Err(RedisCacheError::CacheSerializationError { .. })
+        ));
+
+        let c: RedisCache<u32, HashMap<(u32, u32), u32>> = RedisCache::new(
+            format!("{}-redis-cache-test-ignore-set-errors", now_millis()),
+            10,
+        )
+        .set_ignore_set_errors(true)
+        .build()
+        .unwrap();
+        assert!(c.cache_set(1, unserializable).unwrap().is_none());
+        assert_eq!(1, c.cache_stats().errors);
     }
 }
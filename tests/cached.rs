@@ -326,12 +326,7 @@ cached_control! {
     CONTROL_CACHE: UnboundCache<String, String> = UnboundCache::new();
     Key = { input.to_owned() };
     PostGet(cached_val) = return Ok(cached_val.clone());
-    PostExec(body_result) = {
-        match body_result {
-            Ok(v) => v,
-            Err(e) => return Err(e),
-        }
-    };
+    PostExec(body_result) = body_result?;
     Set(set_value) = set_value.clone();
     Return(return_value) = {
         println!("{}", return_value);
@@ -908,8 +903,6 @@ fn cached_smartstring(s: smartstring::alias::String) -> smartstring::alias::Stri
 
 #[test]
 fn test_cached_smartstring() {
-    smartstring::validate();
-
     let mut string = smartstring::alias::String::new();
     string.push_str("very stringy");
     assert_eq!("equal", cached_smartstring(string.clone()));
@@ -946,8 +939,6 @@ fn cached_smartstring_from_str(s: &str) -> bool {
 
 #[test]
 fn test_cached_smartstring_from_str() {
-    smartstring::validate();
-
     assert!(cached_smartstring_from_str("true"));
     {
         let cache = CACHED_SMARTSTRING_FROM_STR.lock().unwrap();
@@ -1264,6 +1255,31 @@ mod redis_tests {
         assert_eq!(cached_redis_cache_create(6), Err(TestError::Count(6)));
     }
 
+    #[io_cached(
+        redis = true,
+        ttl = 1,
+        prefix = "__cached_redis_proc_macro_test_fn_cached_redis_builder_options",
+        time_refresh = true,
+        hash_keys = true,
+        max_keys = 100,
+        configure = r##"|b| b.set_key_normalizer(cached::Normalization::Lowercase)"##,
+        map_error = r##"|e| TestError::RedisError(format!("{:?}", e))"##
+    )]
+    fn cached_redis_builder_options(n: u32) -> Result<u32, TestError> {
+        if n < 5 {
+            Ok(n)
+        } else {
+            Err(TestError::Count(n))
+        }
+    }
+
+    #[test]
+    fn test_cached_redis_builder_options() {
+        assert_eq!(cached_redis_builder_options(1), Ok(1));
+        assert_eq!(cached_redis_builder_options(1), Ok(1));
+        assert_eq!(cached_redis_builder_options(5), Err(TestError::Count(5)));
+    }
+
     #[cfg(any(feature = "redis_async_std", feature = "redis_tokio"))]
     mod async_redis_tests {
         use super::*;
@@ -1312,6 +1328,32 @@ mod redis_tests {
             assert!(async_cached_redis_cached_flag(6).await.is_err());
         }
 
+        #[io_cached(
+            redis = true,
+            ttl = 1,
+            prefix = "__cached_redis_proc_macro_test_fn_async_cached_redis_builder_options",
+            max_keys = 100,
+            configure = r##"|b| b.set_lifespan_jitter(1)"##,
+            map_error = r##"|e| TestError::RedisError(format!("{:?}", e))"##
+        )]
+        async fn async_cached_redis_builder_options(n: u32) -> Result<u32, TestError> {
+            if n < 5 {
+                Ok(n)
+            } else {
+                Err(TestError::Count(n))
+            }
+        }
+
+        #[async_std::test]
+        async fn test_async_cached_redis_builder_options() {
+            assert_eq!(async_cached_redis_builder_options(1).await, Ok(1));
+            assert_eq!(async_cached_redis_builder_options(1).await, Ok(1));
+            assert_eq!(
+                async_cached_redis_builder_options(5).await,
+                Err(TestError::Count(5))
+            );
+        }
+
         use cached::AsyncRedisCache;
         #[io_cached(
             map_error = r##"|e| TestError::RedisError(format!("{:?}", e))"##,